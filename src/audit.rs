@@ -0,0 +1,228 @@
+use crate::auth::guard::AccessTokenAuthGuard;
+use crate::auth::token::ResourceId;
+use crate::clock::Clock;
+use crate::scalars::Date;
+use crate::time_entry::{fetch_entries_on_date, format_minutes, TimeEntryModel};
+use async_graphql::{Context, Enum, Object, Result, SimpleObject};
+use chrono::NaiveDate;
+use teamdeck::AsyncTeamdeck;
+
+/// Number of minutes which can be logged in a single day without being reported as over capacity.
+const DAILY_CAPACITY_MINUTES: u64 = 8 * 60;
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum EntryAnomaly {
+    /// The entry has no logged minutes.
+    ZeroMinutes,
+    /// The entry starts in the future.
+    FutureDated,
+    /// The entry has no tags assigned.
+    MissingTags,
+    /// The entry has no (or blank) description.
+    MissingDescription,
+    /// Another entry of the same project was logged on the same day.
+    Overlapping,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct EntryAudit {
+    entry: TimeEntryModel,
+    anomalies: Vec<EntryAnomaly>,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct DayAudit {
+    date: Date,
+    total_minutes: u64,
+    /// Total minutes of the day in `H:MM` format.
+    formatted_total: String,
+    /// Whether more than 8 hours were logged on the day.
+    over_capacity: bool,
+    entries: Vec<EntryAudit>,
+}
+
+fn entry_anomalies(
+    entry: &TimeEntryModel,
+    day_entries: &[TimeEntryModel],
+    today: NaiveDate,
+) -> Vec<EntryAnomaly> {
+    let mut anomalies = vec![];
+
+    if entry.minutes == 0 {
+        anomalies.push(EntryAnomaly::ZeroMinutes);
+    }
+
+    if entry.start_date.0 > today {
+        anomalies.push(EntryAnomaly::FutureDated);
+    }
+
+    if entry.tags.as_ref().map_or(true, |tags| tags.is_empty()) {
+        anomalies.push(EntryAnomaly::MissingTags);
+    }
+
+    if entry
+        .description
+        .as_ref()
+        .map_or(true, |description| description.trim().is_empty())
+    {
+        anomalies.push(EntryAnomaly::MissingDescription);
+    }
+
+    if day_entries
+        .iter()
+        .any(|other| other.id != entry.id && other.project_id == entry.project_id)
+    {
+        anomalies.push(EntryAnomaly::Overlapping);
+    }
+
+    anomalies
+}
+
+fn audit_entries(date: Date, entries: Vec<TimeEntryModel>, today: NaiveDate) -> DayAudit {
+    let total_minutes = entries.iter().map(|e| e.minutes).sum();
+    let anomalies: Vec<Vec<EntryAnomaly>> = entries
+        .iter()
+        .map(|entry| entry_anomalies(entry, &entries, today))
+        .collect();
+
+    DayAudit {
+        date,
+        total_minutes,
+        formatted_total: format_minutes(total_minutes),
+        over_capacity: total_minutes > DAILY_CAPACITY_MINUTES,
+        entries: entries
+            .into_iter()
+            .zip(anomalies)
+            .map(|(entry, anomalies)| EntryAudit { entry, anomalies })
+            .collect(),
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct AuditQuery;
+
+#[Object]
+impl AuditQuery {
+    /// Returns time entries of the authorized user for the given day
+    /// together with detected data anomalies.
+    #[tracing::instrument(name = "Auditing time entries of a day", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn audit_day(&self, ctx: &Context<'_>, date: Date) -> Result<DayAudit> {
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+
+        let entries = fetch_entries_on_date(client, resource_id, date).await?;
+
+        let today = ctx.data_unchecked::<Clock>().today();
+        Ok(audit_entries(date, entries, today))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    const TODAY: &str = "2022-01-10";
+
+    fn today() -> NaiveDate {
+        NaiveDate::parse_from_str(TODAY, "%Y-%m-%d").unwrap()
+    }
+
+    fn entry(id: u64, project_id: u64, minutes: u64, date: &str) -> TimeEntryModel {
+        serde_json::from_value(json!({
+            "id": id,
+            "resource_id": 1,
+            "project_id": project_id,
+            "minutes": minutes,
+            "weekend_booking": false,
+            "holidays_booking": false,
+            "vacations_booking": false,
+            "description": "Code review",
+            "external_id": null,
+            "start_date": date,
+            "end_date": date,
+            "creator_resource_id": 1,
+            "editor_resource_id": 1,
+            "tags": [{ "id": 1, "name": "Development", "icon": null, "color": null, "archived": 0 }],
+        }))
+        .unwrap()
+    }
+
+    fn anomalies_of(entry: TimeEntryModel) -> Vec<EntryAnomaly> {
+        let audit = audit_entries(entry.start_date, vec![entry], today());
+        audit.entries.into_iter().next().unwrap().anomalies
+    }
+
+    #[test]
+    fn test_valid_entry_has_no_anomalies() {
+        assert!(anomalies_of(entry(1, 1, 60, TODAY)).is_empty());
+    }
+
+    #[test]
+    fn test_zero_minutes() {
+        assert_eq!(
+            anomalies_of(entry(1, 1, 0, TODAY)),
+            vec![EntryAnomaly::ZeroMinutes]
+        );
+    }
+
+    #[test]
+    fn test_future_dated() {
+        assert_eq!(
+            anomalies_of(entry(1, 1, 60, "2022-01-11")),
+            vec![EntryAnomaly::FutureDated]
+        );
+    }
+
+    #[test]
+    fn test_missing_tags() {
+        let mut entry = entry(1, 1, 60, TODAY);
+        entry.tags = Some(vec![]);
+        assert_eq!(anomalies_of(entry), vec![EntryAnomaly::MissingTags]);
+    }
+
+    #[test]
+    fn test_missing_description() {
+        let mut entry = entry(1, 1, 60, TODAY);
+        entry.description = Some("  ".to_string());
+        assert_eq!(anomalies_of(entry), vec![EntryAnomaly::MissingDescription]);
+    }
+
+    #[test]
+    fn test_overlapping() {
+        let date = Date(today());
+        let audit = audit_entries(
+            date,
+            vec![
+                entry(1, 1, 60, TODAY),
+                entry(2, 1, 30, TODAY),
+                entry(3, 2, 30, TODAY),
+            ],
+            today(),
+        );
+        let anomalies: Vec<Vec<EntryAnomaly>> =
+            audit.entries.into_iter().map(|e| e.anomalies).collect();
+        assert_eq!(
+            anomalies,
+            vec![
+                vec![EntryAnomaly::Overlapping],
+                vec![EntryAnomaly::Overlapping],
+                vec![]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_over_capacity() {
+        let date = Date(today());
+        let audit = audit_entries(
+            date,
+            vec![entry(1, 1, 300, TODAY), entry(2, 2, 240, TODAY)],
+            today(),
+        );
+        assert!(audit.over_capacity);
+        assert_eq!(audit.total_minutes, 540);
+        assert_eq!(audit.formatted_total, "9:00");
+    }
+}
@@ -1,39 +1,122 @@
 mod google;
 pub mod guard;
+pub mod role;
+mod service_account;
+pub mod state;
 pub mod token;
 
-use async_graphql::{Context, Object, Result};
+pub(crate) use google::GoogleKeys;
+pub(crate) use service_account::ServiceAccounts;
+
+use async_graphql::{Context, Object, Result, ResultExt, SimpleObject};
+use std::sync::Arc;
 use teamdeck::{
     api::{resources::Resources, AsyncQuery},
     AsyncTeamdeck,
 };
 
-use crate::resource::ResourceModel;
+use crate::resource::{fetch_resource, ResourceModel};
+use crate::validation::ValidationError;
+use guard::AccessTokenAuthGuard;
+use role::{Role, RoleMapping};
+use state::OAuthStates;
+
+/// Google sign-in page along with the `state` it was issued for.
+#[derive(SimpleObject, Debug)]
+pub struct GoogleAuthUrl {
+    /// URL where the user should be redirected to sign in.
+    url: String,
+    /// Single-use `state` embedded in the URL.
+    ///
+    /// Clients should keep it until Google redirects back, check the redirect carries the same
+    /// state and send it along with the authorization code to `exchangeAuthorizationCodeForToken`.
+    state: String,
+}
 
 #[derive(Default, Debug)]
 pub struct AuthQuery;
 
 #[Object]
 impl AuthQuery {
-    #[tracing::instrument(name = "Fetch url for authorization")]
-    async fn google_auth_url(&self) -> Result<String> {
-        Ok(google::GoogleOAuth2::get_login_url())
+    /// URL of the Google sign-in page, with the `state` the client has to keep until
+    /// Google redirects back to `/google/redirect`.
+    #[tracing::instrument(name = "Fetch url for authorization", skip(ctx))]
+    async fn google_auth_url(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(
+            desc = "Whether to ask the user for offline access, so that Google issues a refresh token",
+            default = false
+        )]
+        offline: bool,
+    ) -> Result<GoogleAuthUrl> {
+        let state = ctx.data_unchecked::<OAuthStates>().issue()?;
+        let url = google::GoogleOAuth2::get_login_url(offline, &state);
+        Ok(GoogleAuthUrl { url, state })
+    }
+
+    /// Role of the authorized user, as embedded in the access token.
+    ///
+    /// `null` for tokens issued before roles were embedded in them.
+    #[tracing::instrument(name = "Fetch role of authorized user", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn token_role(&self, ctx: &Context<'_>) -> Option<Role> {
+        ctx.data_opt::<token::AccessToken>()
+            .and_then(|token| token.role())
     }
 }
 
 #[derive(Default, Debug)]
 pub struct AuthMutation;
 
+/// Prefix of the subject of tokens issued to service accounts, followed by their resource ID.
+const SERVICE_ACCOUNT_SUBJECT_PREFIX: &str = "service-account:";
+
+/// Resolves the current role of the user the refresh token was issued for.
+///
+/// The role is not copied from the token, so that role changes in Teamdeck apply on refresh.
+async fn current_role(ctx: &Context<'_>, refresh_token: &token::RefreshToken) -> Result<Role> {
+    // service accounts are members regardless of their Teamdeck role, as when signing in
+    if refresh_token
+        .subject()
+        .starts_with(SERVICE_ACCOUNT_SUBJECT_PREFIX)
+    {
+        return Ok(Role::Member);
+    }
+
+    let client = ctx.data_unchecked::<AsyncTeamdeck>();
+    match fetch_resource(client, refresh_token.resource_id().0).await? {
+        Some(resource) => Ok(ctx
+            .data_unchecked::<RoleMapping>()
+            .resolve(resource.role.as_deref())),
+        None => Err(async_graphql::Error::new(format!(
+            "No Teamdeck account found with `{}` email",
+            refresh_token.subject()
+        ))),
+    }
+}
+
 #[Object]
 impl AuthMutation {
+    /// Exchanges the authorization code Google redirected back with for a token.
+    ///
+    /// The `state` has to be the one returned by `googleAuthUrl`, it's verified
+    /// and can't be used again.
     async fn exchange_authorization_code_for_token(
         &self,
         ctx: &Context<'_>,
         authorization_code: String,
+        state: String,
     ) -> Result<token::TokenResponse> {
-        let google_token =
-            google::GoogleOAuth2::exchange_code_for_token(authorization_code).await?;
-        let email = google_token.email()?;
+        ctx.data_unchecked::<OAuthStates>().consume(Some(&state))?;
+
+        let google_token = google::GoogleOAuth2::exchange_code_for_token(authorization_code)
+            .await
+            .extend()?;
+        let email = google_token
+            .email(ctx.data_unchecked::<google::GoogleKeys>())
+            .await
+            .extend()?;
 
         let client = ctx.data_unchecked::<AsyncTeamdeck>();
         let endpoint = Resources::builder().email(&email).build().unwrap();
@@ -42,8 +125,13 @@ impl AuthMutation {
         let resource = resources.first();
 
         if let Some(resource) = resource {
+            let role = ctx
+                .data_unchecked::<RoleMapping>()
+                .resolve(resource.role.as_deref());
             let token =
-                token::TokenResponse::with_user_data(&email, token::ResourceId(resource.id))?;
+                token::TokenResponse::with_user_data(&email, token::ResourceId(resource.id), role)?;
+            let store = ctx.data_unchecked::<Arc<dyn token::TokenStore>>();
+            token.save_refresh_token(store.as_ref()).await?;
             Ok(token)
         } else {
             Err(async_graphql::Error::new(format!(
@@ -52,4 +140,66 @@ impl AuthMutation {
             )))
         }
     }
+
+    /// Signs in a service account, like a CI job or a script, with its pre-shared key.
+    ///
+    /// Separate from the Google sign-in and disabled unless `SERVICE_ACCOUNT_KEYS` is configured.
+    #[tracing::instrument(name = "Signing in service account", skip(self, ctx, key))]
+    async fn service_account_login(
+        &self,
+        ctx: &Context<'_>,
+        key: String,
+    ) -> Result<token::TokenResponse> {
+        let resource_id = ctx
+            .data_unchecked::<ServiceAccounts>()
+            .authenticate(&key)
+            .extend()?;
+
+        let token = token::TokenResponse::with_user_data(
+            &format!("{}{}", SERVICE_ACCOUNT_SUBJECT_PREFIX, resource_id.0),
+            resource_id,
+            Role::Member,
+        )?;
+        let store = ctx.data_unchecked::<Arc<dyn token::TokenStore>>();
+        token.save_refresh_token(store.as_ref()).await?;
+        Ok(token)
+    }
+
+    /// Exchanges the refresh token for a new pair of tokens.
+    ///
+    /// The refresh token is rotated, so it cannot be exchanged again.
+    /// Only refresh tokens recorded in the token store can be exchanged.
+    /// The role embedded in the new tokens is resolved again from the Teamdeck resource.
+    async fn refresh_access_token(
+        &self,
+        ctx: &Context<'_>,
+        refresh_token: String,
+    ) -> Result<token::TokenResponse> {
+        let refresh_token = token::RefreshToken::verify(&refresh_token)
+            .map_err(|e| ValidationError::new("refreshToken", e))
+            .extend()?;
+        let role = current_role(ctx, &refresh_token).await?;
+        let store = ctx.data_unchecked::<Arc<dyn token::TokenStore>>();
+
+        // tokens without ID, issued before rotation was supported, could never be rotated
+        let rotated = match refresh_token.jti() {
+            Some(jti) => store.take_refresh_token(jti).await?,
+            None => false,
+        };
+        if !rotated {
+            return Err(ValidationError::new(
+                "refreshToken",
+                token::TokenError::RefreshTokenRevoked,
+            )
+            .extend());
+        }
+
+        let token = token::TokenResponse::with_user_data(
+            refresh_token.subject(),
+            refresh_token.resource_id(),
+            role,
+        )?;
+        token.save_refresh_token(store.as_ref()).await?;
+        Ok(token)
+    }
 }
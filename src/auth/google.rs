@@ -1,12 +1,21 @@
+mod keys;
+
+use async_graphql::{ErrorExtensions, FieldError};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+pub use keys::GoogleKeys;
+
 const USER_INFO_EMAIL_SCOPE: &str = "https://www.googleapis.com/auth/userinfo.email";
 const OAUTH2_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
 const RESPONSE_TYPE_CODE: &str = "code";
 const ACCESS_TYPE_ONLINE: &str = "online";
-const EXPECTED_DOMAIN: &str = "moodup.team";
+const ACCESS_TYPE_OFFLINE: &str = "offline";
+const ALLOWED_DOMAINS_ENV_VARIABLE: &str = "GOOGLE_ALLOWED_DOMAINS";
 const GRANT_TYPE_AUTHORIZATION_CODE: &str = "authorization_code";
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_ISSUERS: [&str; 2] = ["accounts.google.com", "https://accounts.google.com"];
 
 struct GoogleOAuthConfig;
 
@@ -22,6 +31,55 @@ impl GoogleOAuthConfig {
     fn client_id() -> String {
         std::env::var("GOOGLE_OAUTH2_CLIENT_ID").unwrap()
     }
+
+    /// Domains of the Google accounts allowed to sign in, empty when not configured.
+    fn allowed_domains() -> Vec<String> {
+        parse_domains(&std::env::var(ALLOWED_DOMAINS_ENV_VARIABLE).unwrap_or_default())
+    }
+}
+
+fn parse_domains(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|domain| domain.trim().to_lowercase())
+        .filter(|domain| !domain.is_empty())
+        .collect()
+}
+
+fn check_issuer_and_audience(
+    claims: &GoogleIdTokenClaims,
+    client_id: &str,
+) -> Result<(), GoogleAuthError> {
+    if !GOOGLE_ISSUERS.contains(&claims.iss.as_str()) {
+        return Err(GoogleAuthError::InvalidIssuer(claims.iss.clone()));
+    }
+
+    if claims.aud != client_id {
+        return Err(GoogleAuthError::InvalidAudience(claims.aud.clone()));
+    }
+
+    Ok(())
+}
+
+/// Ensures the domain is one of the allowed ones.
+///
+/// No domain is allowed when none are configured.
+fn check_domain(domain: &str, allowed_domains: &[String]) -> Result<(), GoogleAuthError> {
+    if allowed_domains.is_empty() {
+        return Err(GoogleAuthError::AllowedDomainsNotConfigured);
+    }
+
+    if allowed_domains
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(domain))
+    {
+        Ok(())
+    } else {
+        Err(GoogleAuthError::InvalidDomain {
+            expected: allowed_domains.join(", "),
+            found: domain.to_string(),
+        })
+    }
 }
 
 #[derive(Error, Debug)]
@@ -34,32 +92,76 @@ pub enum GoogleAuthError {
     EmailNotVerified(String),
     #[error("invalid domain (expected {expected:?}, found {found:?})")]
     InvalidDomain { expected: String, found: String },
+    #[error("allowed domains are not configured, set the `GOOGLE_ALLOWED_DOMAINS` env variable")]
+    AllowedDomainsNotConfigured,
+    #[error("could not fetch Google public keys")]
+    KeysUnavailable { source: reqwest::Error },
+    #[error("ID token is signed with unknown key `{0}`")]
+    UnknownSigningKey(String),
+    #[error("ID token has invalid signature or has expired")]
+    InvalidIdToken { source: jsonwebtoken::errors::Error },
+    #[error("ID token was issued by `{0}` instead of Google")]
+    InvalidIssuer(String),
+    #[error("ID token was issued for another client (`{0}`)")]
+    InvalidAudience(String),
+    #[error("could not exchange authorization code, Google is unreachable")]
+    ExchangeRequestFailed { source: reqwest::Error },
+    #[error("Google rejected the authorization code with status {status}: {body}")]
+    ExchangeRejected { status: u16, body: String },
+    #[error("could not decode token response from Google")]
+    InvalidTokenResponse { source: reqwest::Error },
+}
+
+impl ErrorExtensions for GoogleAuthError {
+    fn extend(&self) -> FieldError {
+        self.extend_with(|err, e| match err {
+            GoogleAuthError::ExchangeRequestFailed { .. }
+            | GoogleAuthError::KeysUnavailable { .. } => e.set("code", "GOOGLE_UNAVAILABLE"),
+            GoogleAuthError::ExchangeRejected { status, .. } => {
+                e.set("code", "GOOGLE_CODE_REJECTED");
+                e.set("status", *status);
+            }
+            GoogleAuthError::InvalidTokenResponse { .. } => {
+                e.set("code", "GOOGLE_INVALID_RESPONSE")
+            }
+            GoogleAuthError::IdTokenMissing => e.set("code", "GOOGLE_ID_TOKEN_MISSING"),
+            GoogleAuthError::EmailNotVerified(_) => e.set("code", "EMAIL_NOT_VERIFIED"),
+            GoogleAuthError::InvalidDomain { .. } => e.set("code", "INVALID_DOMAIN"),
+            GoogleAuthError::AllowedDomainsNotConfigured => e.set("code", "NOT_CONFIGURED"),
+            GoogleAuthError::TokenDecodeError { .. }
+            | GoogleAuthError::UnknownSigningKey(_)
+            | GoogleAuthError::InvalidIdToken { .. }
+            | GoogleAuthError::InvalidIssuer(_)
+            | GoogleAuthError::InvalidAudience(_) => e.set("code", "INVALID_ID_TOKEN"),
+        })
+    }
 }
 
 /// Struct representing response from Google OAuth2 API
 /// after exchanging authorization code for token.
-///
-/// Available fields:
-///     - `access_token` (String)
-///         The token that your application sends to authorize a Google API request.
-///     - `expires_in` (u64)
-///         The remaining lifetime of the access token in seconds.
-///     - `refresh_token` (Option<String>)
-///         A token that you can use to obtain a new access token.
-///         Refresh tokens are valid until the user revokes access.
-///         Again, this field is only present in this response if you set the
-///         access_type parameter to `offline` in the initial request to Google's authorization server.
-///     - `scope` (String)
-///         The scopes of access granted by the access_token expressed as a list of space-delimited, case-sensitive strings.
-///     - `token_type` (String)
-///         The type of token returned. At this time, this field's value is always set to `Bearer`.
 #[derive(Deserialize, Debug)]
 pub struct GoogleTokenResponse {
     id_token: Option<String>,
+    /// The token that your application sends to authorize a Google API request.
+    pub access_token: Option<String>,
+    /// The remaining lifetime of the access token in seconds.
+    pub expires_in: Option<u64>,
+    /// A token that you can use to obtain a new access token.
+    /// Refresh tokens are valid until the user revokes access.
+    ///
+    /// This field is only present in this response if the user was asked for offline access,
+    /// see [`GoogleOAuth2::get_login_url`].
+    pub refresh_token: Option<String>,
+    /// The scopes of access granted by the access_token expressed as a list of space-delimited, case-sensitive strings.
+    pub scope: Option<String>,
+    /// The type of token returned. At this time, this field's value is always set to `Bearer`.
+    pub token_type: Option<String>,
 }
 
 #[derive(Deserialize)]
 struct GoogleIdTokenClaims {
+    iss: String,
+    aud: String,
     email: String,
     email_verified: bool,
     #[serde(rename(deserialize = "hd"))]
@@ -67,26 +169,32 @@ struct GoogleIdTokenClaims {
 }
 
 impl GoogleTokenResponse {
-    pub fn email(&self) -> Result<String, GoogleAuthError> {
+    /// Returns email of the user, after verifying the ID token was signed by Google for this client.
+    pub async fn email(&self, keys: &GoogleKeys) -> Result<String, GoogleAuthError> {
         let id_token = match &self.id_token {
             Some(token) => token,
             None => return Err(GoogleAuthError::IdTokenMissing),
         };
 
-        let token_data = jsonwebtoken::dangerous_insecure_decode::<GoogleIdTokenClaims>(id_token)
+        let header = jsonwebtoken::decode_header(id_token)
             .map_err(|e| GoogleAuthError::TokenDecodeError { source: e })?;
+        let kid = header.kid.unwrap_or_default();
+        let key = keys.find(&kid).await?;
+
+        let token_data = jsonwebtoken::decode::<GoogleIdTokenClaims>(
+            id_token,
+            &DecodingKey::from_rsa_components(&key.n, &key.e),
+            &Validation::new(Algorithm::RS256),
+        )
+        .map_err(|e| GoogleAuthError::InvalidIdToken { source: e })?;
         let claims = token_data.claims;
+        check_issuer_and_audience(&claims, &GoogleOAuthConfig::client_id())?;
 
         if !claims.email_verified {
             return Err(GoogleAuthError::EmailNotVerified(claims.email));
         }
 
-        if claims.domain != EXPECTED_DOMAIN {
-            return Err(GoogleAuthError::InvalidDomain {
-                expected: EXPECTED_DOMAIN.to_string(),
-                found: claims.domain,
-            });
-        }
+        check_domain(&claims.domain, &GoogleOAuthConfig::allowed_domains())?;
 
         Ok(claims.email)
     }
@@ -107,24 +215,35 @@ impl GoogleOAuth2 {
     // NOTE: Done this way in order to not being required to store
     // Google credentials on the clients. They simply ask for the URL
     // where they should redirect the user
-    pub fn get_login_url() -> String {
+    //
+    // With `offline` access, the exchanged token response includes a Google refresh token.
+    pub fn get_login_url(offline: bool, state: &str) -> String {
         let base_url = OAUTH2_URL;
         let client_id = GoogleOAuthConfig::client_id();
         let redirect_uri = GoogleOAuthConfig::redirect_uri();
 
         let scope = USER_INFO_EMAIL_SCOPE;
         let response_type = RESPONSE_TYPE_CODE;
-        let access_type = ACCESS_TYPE_ONLINE;
+        let access_type = if offline {
+            ACCESS_TYPE_OFFLINE
+        } else {
+            ACCESS_TYPE_ONLINE
+        };
+        let mut url = format!(
+            "{}?client_id={}&redirect_uri={}&scope={}&response_type={}&access_type={}&state={}",
+            base_url, client_id, redirect_uri, scope, response_type, access_type, state
+        );
+        if offline {
+            // Google issues refresh tokens only when the user is asked for consent
+            url.push_str("&prompt=consent");
+        }
 
-        format!(
-            "{}?client_id={}&redirect_uri={}&scope={}&response_type={}&access_type={}",
-            base_url, client_id, redirect_uri, scope, response_type, access_type
-        )
+        url
     }
 
     pub async fn exchange_code_for_token(
         code: String,
-    ) -> Result<GoogleTokenResponse, reqwest::Error> {
+    ) -> Result<GoogleTokenResponse, GoogleAuthError> {
         let params = ExchangeCodeForTokenParams {
             client_id: GoogleOAuthConfig::client_id(),
             client_secret: GoogleOAuthConfig::client_secret(),
@@ -134,13 +253,161 @@ impl GoogleOAuth2 {
         };
 
         let response = reqwest::Client::new()
-            .post("https://oauth2.googleapis.com/token")
+            .post(TOKEN_URL)
             .form(&params)
             .send()
-            .await?
+            .await
+            .map_err(|e| GoogleAuthError::ExchangeRequestFailed { source: e })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(GoogleAuthError::ExchangeRejected {
+                status: status.as_u16(),
+                body,
+            });
+        }
+
+        response
             .json()
-            .await?;
+            .await
+            .map_err(|e| GoogleAuthError::InvalidTokenResponse { source: e })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn claims(iss: &str, aud: &str) -> GoogleIdTokenClaims {
+        GoogleIdTokenClaims {
+            iss: iss.to_string(),
+            aud: aud.to_string(),
+            email: "user@moodup.team".to_string(),
+            email_verified: true,
+            domain: "moodup.team".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_google_issuer_and_audience() {
+        assert!(
+            check_issuer_and_audience(&claims("accounts.google.com", "client"), "client").is_ok()
+        );
+        assert!(check_issuer_and_audience(
+            &claims("https://accounts.google.com", "client"),
+            "client"
+        )
+        .is_ok());
+    }
+
+    fn code(error: GoogleAuthError) -> Option<async_graphql::Value> {
+        error
+            .extend()
+            .extensions
+            .and_then(|extensions| extensions.get("code").cloned())
+    }
+
+    #[test]
+    fn test_token_response_fields() {
+        let response: GoogleTokenResponse = serde_json::from_str(
+            r#"{
+                "access_token": "access",
+                "expires_in": 3599,
+                "refresh_token": "refresh",
+                "scope": "https://www.googleapis.com/auth/userinfo.email",
+                "token_type": "Bearer",
+                "id_token": "id"
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(response.id_token.as_deref(), Some("id"));
+        assert_eq!(response.access_token.as_deref(), Some("access"));
+        assert_eq!(response.expires_in, Some(3599));
+        assert_eq!(response.refresh_token.as_deref(), Some("refresh"));
+        assert_eq!(response.token_type.as_deref(), Some("Bearer"));
+    }
+
+    #[test]
+    fn test_token_response_without_refresh_token() {
+        let response: GoogleTokenResponse =
+            serde_json::from_str(r#"{"access_token": "access", "id_token": "id"}"#).unwrap();
+
+        assert_eq!(response.refresh_token, None);
+        assert_eq!(response.id_token.as_deref(), Some("id"));
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let rejected = GoogleAuthError::ExchangeRejected {
+            status: 400,
+            body: r#"{"error":"invalid_grant"}"#.to_string(),
+        };
+        let extensions = rejected.extend().extensions.unwrap();
+        assert_eq!(
+            extensions.get("code"),
+            Some(&async_graphql::Value::from("GOOGLE_CODE_REJECTED"))
+        );
+        assert_eq!(
+            extensions.get("status"),
+            Some(&async_graphql::Value::from(400))
+        );
+
+        assert_eq!(
+            code(GoogleAuthError::IdTokenMissing),
+            Some(async_graphql::Value::from("GOOGLE_ID_TOKEN_MISSING"))
+        );
+        assert_eq!(
+            code(GoogleAuthError::InvalidIssuer("evil".to_string())),
+            Some(async_graphql::Value::from("INVALID_ID_TOKEN"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_issuer() {
+        assert!(matches!(
+            check_issuer_and_audience(&claims("https://evil.example.com", "client"), "client"),
+            Err(GoogleAuthError::InvalidIssuer(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_audience() {
+        assert!(matches!(
+            check_issuer_and_audience(&claims("accounts.google.com", "other"), "client"),
+            Err(GoogleAuthError::InvalidAudience(_))
+        ));
+    }
+
+    #[test]
+    fn test_single_allowed_domain() {
+        let allowed = parse_domains("moodup.team");
+        assert!(check_domain("moodup.team", &allowed).is_ok());
+    }
+
+    #[test]
+    fn test_multiple_allowed_domains() {
+        let allowed = parse_domains("moodup.team, Example.com,");
+        assert_eq!(allowed, vec!["moodup.team", "example.com"]);
+        assert!(check_domain("moodup.team", &allowed).is_ok());
+        assert!(check_domain("example.com", &allowed).is_ok());
+    }
+
+    #[test]
+    fn test_rejected_domain() {
+        let allowed = parse_domains("moodup.team,example.com");
+        assert!(matches!(
+            check_domain("gmail.com", &allowed),
+            Err(GoogleAuthError::InvalidDomain { found, .. }) if found == "gmail.com"
+        ));
+    }
 
-        Ok(response)
+    #[test]
+    fn test_no_allowed_domains() {
+        assert!(matches!(
+            check_domain("moodup.team", &parse_domains("")),
+            Err(GoogleAuthError::AllowedDomainsNotConfigured)
+        ));
     }
 }
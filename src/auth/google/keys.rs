@@ -0,0 +1,67 @@
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::GoogleAuthError;
+
+const GOOGLE_CERTS_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
+const KEYS_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Public RSA key used by Google to sign ID tokens.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Jwk {
+    pub kid: String,
+    pub n: String,
+    pub e: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+/// Cache of Google's public keys.
+///
+/// Keys are refreshed when they get older than an hour,
+/// or when a token is signed with a key which is not cached yet (Google rotates its keys).
+#[derive(Default, Debug)]
+pub struct GoogleKeys {
+    cached: Mutex<Option<(Instant, Vec<Jwk>)>>,
+}
+
+impl GoogleKeys {
+    /// Returns the key with the given ID, fetching the keys from Google when needed.
+    pub async fn find(&self, kid: &str) -> Result<Jwk, GoogleAuthError> {
+        if let Some(key) = self.cached_key(kid) {
+            return Ok(key);
+        }
+
+        let keys = Self::fetch().await?;
+        let key = keys.iter().find(|key| key.kid == kid).cloned();
+        *self.cached.lock().unwrap() = Some((Instant::now(), keys));
+
+        key.ok_or_else(|| GoogleAuthError::UnknownSigningKey(kid.to_string()))
+    }
+
+    fn cached_key(&self, kid: &str) -> Option<Jwk> {
+        let cached = self.cached.lock().unwrap();
+        match &*cached {
+            Some((fetched_at, keys)) if fetched_at.elapsed() < KEYS_CACHE_TTL => {
+                keys.iter().find(|key| key.kid == kid).cloned()
+            }
+            _ => None,
+        }
+    }
+
+    async fn fetch() -> Result<Vec<Jwk>, GoogleAuthError> {
+        let keys: JwkSet = reqwest::get(GOOGLE_CERTS_URL)
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| GoogleAuthError::KeysUnavailable { source: e })?
+            .json()
+            .await
+            .map_err(|e| GoogleAuthError::KeysUnavailable { source: e })?;
+
+        Ok(keys.keys)
+    }
+}
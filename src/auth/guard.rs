@@ -1,7 +1,11 @@
+use async_graphql::dataloader::DataLoader;
 use async_graphql::{async_trait::async_trait, Guard};
+use std::sync::Arc;
 use thiserror::Error;
 
-use super::token::{AccessToken, ResourceId};
+use super::role::{Role, RoleMapping};
+use super::token::{AccessToken, ResourceId, TokenStore};
+use crate::loader::ResourceLoader;
 
 #[derive(Debug)]
 pub struct AccessTokenAuthGuard;
@@ -22,16 +26,197 @@ impl Default for AccessTokenAuthGuard {
 pub enum AuthError {
     #[error("Unauthorized, missing, invalid or expired access token")]
     InvalidAccessToken,
+    #[error("Forbidden, action available only for admins")]
+    NotAnAdmin,
 }
 
 #[async_trait]
 impl Guard for AccessTokenAuthGuard {
     #[tracing::instrument(name = "Checking access token with guard", skip(ctx))]
     async fn check(&self, ctx: &async_graphql::Context<'_>) -> async_graphql::Result<()> {
-        if ctx.data_opt::<AccessToken>().is_some() && ctx.data_opt::<ResourceId>().is_some() {
+        let token = match ctx.data_opt::<AccessToken>() {
+            Some(token) if ctx.data_opt::<ResourceId>().is_some() => token,
+            _ => return Err(AuthError::InvalidAccessToken.into()),
+        };
+
+        if let (Some(store), Some(jti)) = (ctx.data_opt::<Arc<dyn TokenStore>>(), token.jti()) {
+            if store.is_revoked(jti).await? {
+                return Err(AuthError::InvalidAccessToken.into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+const ADMIN_RESOURCE_IDS_ENV_VARIABLE: &str = "ADMIN_RESOURCE_IDS";
+
+/// Resources which are always admins, configured with the `ADMIN_RESOURCE_IDS` env variable.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AdminResourceIds(Vec<u64>);
+
+impl AdminResourceIds {
+    pub fn from_env() -> Self {
+        AdminResourceIds(parse_resource_ids(
+            &std::env::var(ADMIN_RESOURCE_IDS_ENV_VARIABLE).unwrap_or_default(),
+        ))
+    }
+
+    fn contains(&self, resource_id: u64) -> bool {
+        self.0.contains(&resource_id)
+    }
+}
+
+fn parse_resource_ids(value: &str) -> Vec<u64> {
+    value
+        .split(',')
+        .filter_map(|id| id.trim().parse().ok())
+        .collect()
+}
+
+/// Checks whether the request was made by an authorized admin.
+///
+/// Resources listed in `ADMIN_RESOURCE_IDS` are always admins, otherwise the role embedded
+/// in the access token is used. For tokens without role, the Teamdeck role of the resource
+/// is mapped with the configured `RoleMapping`.
+///
+/// Fails when the resource cannot be fetched from Teamdeck.
+pub async fn is_admin(ctx: &async_graphql::Context<'_>) -> async_graphql::Result<bool> {
+    let resource_id = match ctx.data_opt::<ResourceId>() {
+        Some(resource_id) => resource_id.0,
+        None => return Ok(false),
+    };
+
+    if ctx
+        .data_unchecked::<AdminResourceIds>()
+        .contains(resource_id)
+    {
+        return Ok(true);
+    }
+
+    if let Some(role) = ctx.data_opt::<AccessToken>().and_then(|token| token.role()) {
+        return Ok(role == Role::Admin);
+    }
+
+    let role_mapping = ctx.data_unchecked::<RoleMapping>();
+    let resource = ctx
+        .data_unchecked::<DataLoader<ResourceLoader>>()
+        .load_one(resource_id)
+        .await?;
+    Ok(resource.map_or(false, |resource| {
+        role_mapping.resolve(resource.role.as_deref()) == Role::Admin
+    }))
+}
+
+#[derive(Debug)]
+pub struct AdminGuard;
+
+impl AdminGuard {
+    pub fn new() -> Self {
+        AdminGuard
+    }
+}
+
+impl Default for AdminGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Guard for AdminGuard {
+    #[tracing::instrument(name = "Checking admin access with guard", skip(ctx))]
+    async fn check(&self, ctx: &async_graphql::Context<'_>) -> async_graphql::Result<()> {
+        if is_admin(ctx).await? {
             Ok(())
         } else {
-            Err(AuthError::InvalidAccessToken.into())
+            Err(AuthError::NotAnAdmin.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::time_entry::{visible_time_entry, TimeEntryModel};
+    use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Request, Schema};
+
+    struct RawEntryQuery;
+
+    #[Object]
+    impl RawEntryQuery {
+        async fn time_entry(
+            &self,
+            ctx: &Context<'_>,
+            raw: Option<bool>,
+        ) -> async_graphql::Result<Option<TimeEntryModel>> {
+            visible_time_entry(ctx, raw.unwrap_or(false), async {
+                Ok(Some(serde_json::json!({
+                    "id": 7,
+                    "resource_id": 2,
+                    "project_id": 3,
+                    "minutes": 60,
+                    "weekend_booking": false,
+                    "holidays_booking": false,
+                    "vacations_booking": false,
+                    "description": null,
+                    "external_id": null,
+                    "start_date": "2022-01-10",
+                    "end_date": "2022-01-10",
+                    "creator_resource_id": 2,
+                    "editor_resource_id": 2,
+                    "tags": null,
+                })))
+            })
+            .await
         }
     }
+
+    async fn query_raw_entry(role: Role) -> async_graphql::Response {
+        let schema = Schema::build(RawEntryQuery, EmptyMutation, EmptySubscription)
+            .data(AdminResourceIds::default())
+            .data(RoleMapping::default())
+            .finish();
+        let request = Request::new("{ timeEntry(raw: true) { id raw } }")
+            .data(AccessToken::for_resource(ResourceId(1), role))
+            .data(ResourceId(1));
+
+        schema.execute(request).await
+    }
+
+    #[actix_web::test]
+    async fn test_raw_entry_is_forbidden_for_members() {
+        let response = query_raw_entry(Role::Member).await;
+
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(
+            response.errors[0].message,
+            AuthError::NotAnAdmin.to_string()
+        );
+        assert_eq!(
+            response.data.into_json().unwrap(),
+            serde_json::json!({ "timeEntry": null })
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_raw_entry_is_present_for_admins() {
+        let response = query_raw_entry(Role::Admin).await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.into_json().unwrap();
+        assert_eq!(data["timeEntry"]["id"], 7);
+        assert_eq!(data["timeEntry"]["raw"]["resource_id"], 2);
+    }
+
+    #[test]
+    fn test_parse_resource_ids() {
+        assert_eq!(parse_resource_ids("1, 2,3"), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_parse_resource_ids_skips_invalid() {
+        assert_eq!(parse_resource_ids("1,admin,,4"), vec![1, 4]);
+        assert!(parse_resource_ids("").is_empty());
+    }
 }
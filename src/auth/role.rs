@@ -0,0 +1,113 @@
+use async_graphql::Enum;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const ROLE_MAPPING_ENV_VARIABLE: &str = "TEAMDECK_ROLE_MAPPING";
+const DEFAULT_ROLE_MAPPING: &str = "owner=admin,administrator=admin,admin=admin,manager=manager";
+
+/// Role of the user within the app, resolved from the Teamdeck resource role.
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Manager,
+    Member,
+}
+
+impl Default for Role {
+    fn default() -> Self {
+        Role::Member
+    }
+}
+
+impl Role {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "admin" => Some(Role::Admin),
+            "manager" => Some(Role::Manager),
+            "member" => Some(Role::Member),
+            _ => None,
+        }
+    }
+}
+
+/// Mapping from Teamdeck role names (case insensitive) to app roles.
+///
+/// Configured with the `TEAMDECK_ROLE_MAPPING` env variable as comma-separated
+/// `teamdeck role=app role` pairs, e.g. `Owner=admin,Project Manager=manager`.
+/// Roles missing from the mapping resolve to `Member`.
+#[derive(Debug, Clone)]
+pub struct RoleMapping {
+    roles: HashMap<String, Role>,
+}
+
+impl Default for RoleMapping {
+    fn default() -> Self {
+        Self::parse(DEFAULT_ROLE_MAPPING).unwrap()
+    }
+}
+
+impl RoleMapping {
+    pub fn from_env() -> Self {
+        match std::env::var(ROLE_MAPPING_ENV_VARIABLE) {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|| {
+                panic!(
+                    "Invalid {} env variable: {}",
+                    ROLE_MAPPING_ENV_VARIABLE, value
+                )
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let mut roles = HashMap::new();
+        for pair in value.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (teamdeck_role, role) = pair.split_once('=')?;
+            roles.insert(teamdeck_role.trim().to_lowercase(), Role::parse(role)?);
+        }
+
+        Some(RoleMapping { roles })
+    }
+
+    /// Returns the app role for the given Teamdeck role.
+    pub fn resolve(&self, teamdeck_role: Option<&str>) -> Role {
+        teamdeck_role
+            .and_then(|role| self.roles.get(&role.trim().to_lowercase()))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_mapping() {
+        let mapping = RoleMapping::default();
+        assert_eq!(mapping.resolve(Some("Owner")), Role::Admin);
+        assert_eq!(mapping.resolve(Some("manager")), Role::Manager);
+    }
+
+    #[test]
+    fn test_custom_mapping() {
+        let mapping = RoleMapping::parse("Project Manager=manager, CEO=admin").unwrap();
+        assert_eq!(mapping.resolve(Some("project manager")), Role::Manager);
+        assert_eq!(mapping.resolve(Some("CEO")), Role::Admin);
+        assert_eq!(mapping.resolve(Some("Owner")), Role::Member);
+    }
+
+    #[test]
+    fn test_unmapped_role_falls_back_to_member() {
+        let mapping = RoleMapping::default();
+        assert_eq!(mapping.resolve(Some("Developer")), Role::Member);
+        assert_eq!(mapping.resolve(None), Role::Member);
+    }
+
+    #[test]
+    fn test_invalid_mapping() {
+        assert!(RoleMapping::parse("Owner=superuser").is_none());
+        assert!(RoleMapping::parse("Owner").is_none());
+    }
+}
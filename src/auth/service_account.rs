@@ -0,0 +1,128 @@
+use async_graphql::{ErrorExtensions, FieldError};
+use std::collections::HashMap;
+use thiserror::Error;
+
+use super::token::ResourceId;
+
+const SERVICE_ACCOUNT_KEYS_ENV_VARIABLE: &str = "SERVICE_ACCOUNT_KEYS";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ServiceAccountError {
+    #[error("service account login is disabled, set the `SERVICE_ACCOUNT_KEYS` env variable")]
+    Disabled,
+    #[error("invalid service account key")]
+    InvalidKey,
+}
+
+impl ErrorExtensions for ServiceAccountError {
+    fn extend(&self) -> FieldError {
+        self.extend_with(|err, e| match err {
+            ServiceAccountError::Disabled => e.set("code", "SERVICE_ACCOUNTS_DISABLED"),
+            ServiceAccountError::InvalidKey => e.set("code", "INVALID_SERVICE_ACCOUNT_KEY"),
+        })
+    }
+}
+
+/// Pre-shared keys of service accounts, like CI jobs or scripts, which cannot sign in with Google.
+///
+/// Configured with the `SERVICE_ACCOUNT_KEYS` env variable as comma-separated
+/// `resource id=key` pairs, e.g. `42=ci-secret`. Service account login is disabled
+/// when the variable is not set.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceAccounts {
+    keys: Vec<(String, ResourceId)>,
+}
+
+impl ServiceAccounts {
+    pub fn from_env() -> Self {
+        match std::env::var(SERVICE_ACCOUNT_KEYS_ENV_VARIABLE) {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|| {
+                panic!("Invalid {} env variable", SERVICE_ACCOUNT_KEYS_ENV_VARIABLE)
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let mut keys = HashMap::new();
+        for pair in value.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (resource_id, key) = pair.split_once('=')?;
+            let key = key.trim();
+            if key.is_empty() {
+                return None;
+            }
+            keys.insert(
+                key.to_string(),
+                ResourceId(resource_id.trim().parse().ok()?),
+            );
+        }
+
+        Some(ServiceAccounts {
+            keys: keys.into_iter().collect(),
+        })
+    }
+
+    /// Returns the resource of the service account with the given key.
+    pub fn authenticate(&self, key: &str) -> Result<ResourceId, ServiceAccountError> {
+        if self.keys.is_empty() {
+            return Err(ServiceAccountError::Disabled);
+        }
+
+        // every key is compared, so that the time taken doesn't reveal which one matched
+        self.keys
+            .iter()
+            .fold(None, |found, (candidate, resource_id)| {
+                if constant_time_eq(candidate.as_bytes(), key.as_bytes()) {
+                    Some(*resource_id)
+                } else {
+                    found
+                }
+            })
+            .ok_or(ServiceAccountError::InvalidKey)
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_valid_key() {
+        let accounts = ServiceAccounts::parse("42=ci-secret, 7=script-secret").unwrap();
+        assert_eq!(accounts.authenticate("ci-secret").unwrap().0, 42);
+        assert_eq!(accounts.authenticate("script-secret").unwrap().0, 7);
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let accounts = ServiceAccounts::parse("42=ci-secret").unwrap();
+        assert_eq!(
+            accounts.authenticate("ci-secre").unwrap_err(),
+            ServiceAccountError::InvalidKey
+        );
+        assert_eq!(
+            accounts.authenticate("").unwrap_err(),
+            ServiceAccountError::InvalidKey
+        );
+    }
+
+    #[test]
+    fn test_disabled_without_keys() {
+        let accounts = ServiceAccounts::default();
+        assert_eq!(
+            accounts.authenticate("ci-secret").unwrap_err(),
+            ServiceAccountError::Disabled
+        );
+    }
+
+    #[test]
+    fn test_invalid_keys() {
+        assert!(ServiceAccounts::parse("ci-secret").is_none());
+        assert!(ServiceAccounts::parse("admin=ci-secret").is_none());
+        assert!(ServiceAccounts::parse("42=").is_none());
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+const STATE_EXPIRATION_TIME: Duration = Duration::from_secs(60 * 10);
+
+#[derive(Debug, Error)]
+pub enum OAuthStateError {
+    #[error("error while signing OAuth2 state")]
+    SigningError { source: jsonwebtoken::errors::Error },
+    #[error("missing OAuth2 state")]
+    Missing,
+    #[error("invalid or expired OAuth2 state")]
+    Invalid { source: jsonwebtoken::errors::Error },
+    #[error("OAuth2 state was already used or not issued by this server")]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StateClaims {
+    jti: String,
+    iat: u64,
+    exp: u64,
+}
+
+/// Signed `state` parameter of the Google OAuth2 flow, protecting the redirect from CSRF.
+///
+/// The state is a short-lived JWT signed with the Google OAuth2 client secret.
+/// The signature alone only proves the state was issued by this server, use [`OAuthStates`]
+/// to tie it to the client which started the sign-in.
+pub struct OAuthState;
+
+impl OAuthState {
+    fn secret() -> String {
+        std::env::var("GOOGLE_OAUTH2_CLIENT_SECRET").unwrap()
+    }
+
+    pub fn issue() -> Result<String, OAuthStateError> {
+        Self::issue_with_secret(&Self::secret())
+    }
+
+    pub fn verify(state: Option<&str>) -> Result<(), OAuthStateError> {
+        Self::verify_with_secret(state, &Self::secret())
+    }
+
+    fn issue_with_secret(secret: &str) -> Result<String, OAuthStateError> {
+        Self::sign(&Self::new_claims(), secret)
+    }
+
+    fn verify_with_secret(state: Option<&str>, secret: &str) -> Result<(), OAuthStateError> {
+        Self::claims(state, secret).map(|_| ())
+    }
+
+    fn new_claims() -> StateClaims {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let issued_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap();
+        StateClaims {
+            jti: format!(
+                "{}-{}",
+                issued_at.as_nanos(),
+                COUNTER.fetch_add(1, Ordering::SeqCst)
+            ),
+            iat: issued_at.as_secs(),
+            exp: (issued_at + STATE_EXPIRATION_TIME).as_secs(),
+        }
+    }
+
+    fn sign(claims: &StateClaims, secret: &str) -> Result<String, OAuthStateError> {
+        encode(
+            &Header::default(),
+            claims,
+            &EncodingKey::from_secret(secret.as_bytes()),
+        )
+        .map_err(|e| OAuthStateError::SigningError { source: e })
+    }
+
+    fn claims(state: Option<&str>, secret: &str) -> Result<StateClaims, OAuthStateError> {
+        let state = state.ok_or(OAuthStateError::Missing)?;
+
+        decode::<StateClaims>(
+            state,
+            &DecodingKey::from_secret(secret.as_bytes()),
+            &Validation::default(),
+        )
+        .map(|data| data.claims)
+        .map_err(|e| OAuthStateError::Invalid { source: e })
+    }
+}
+
+/// States issued by this server which haven't been used yet.
+///
+/// Every state can be consumed exactly once, within [`STATE_EXPIRATION_TIME`] from issuing it,
+/// so a state handed to one client can't be replayed to complete a sign-in started by another.
+#[derive(Debug, Default)]
+pub struct OAuthStates {
+    // maps state IDs to their expiration time, in seconds since the UNIX epoch
+    pending: Mutex<HashMap<String, u64>>,
+}
+
+impl OAuthStates {
+    /// Issues a new state and remembers it until it's consumed or expires.
+    pub fn issue(&self) -> Result<String, OAuthStateError> {
+        self.issue_with_secret(&OAuthState::secret())
+    }
+
+    /// Verifies the state was issued by this server and hasn't been used yet, then forgets it.
+    pub fn consume(&self, state: Option<&str>) -> Result<(), OAuthStateError> {
+        self.consume_with_secret(state, &OAuthState::secret())
+    }
+
+    fn issue_with_secret(&self, secret: &str) -> Result<String, OAuthStateError> {
+        let claims = OAuthState::new_claims();
+        let state = OAuthState::sign(&claims, secret)?;
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, expires_at| *expires_at > now);
+        pending.insert(claims.jti, claims.exp);
+
+        Ok(state)
+    }
+
+    fn consume_with_secret(
+        &self,
+        state: Option<&str>,
+        secret: &str,
+    ) -> Result<(), OAuthStateError> {
+        let claims = OAuthState::claims(state, secret)?;
+
+        self.pending
+            .lock()
+            .unwrap()
+            .remove(&claims.jti)
+            .map(|_| ())
+            .ok_or(OAuthStateError::Unknown)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SECRET: &str = "test-secret";
+
+    #[test]
+    fn test_valid_state() {
+        let state = OAuthState::issue_with_secret(SECRET).unwrap();
+        assert!(OAuthState::verify_with_secret(Some(&state), SECRET).is_ok());
+    }
+
+    #[test]
+    fn test_tampered_state() {
+        let state = OAuthState::issue_with_secret(SECRET).unwrap();
+        let mut tampered = state.clone();
+        tampered.insert(state.find('.').unwrap() + 2, 'x');
+
+        assert!(matches!(
+            OAuthState::verify_with_secret(Some(&tampered), SECRET),
+            Err(OAuthStateError::Invalid { .. })
+        ));
+        assert!(matches!(
+            OAuthState::verify_with_secret(Some(&state), "other-secret"),
+            Err(OAuthStateError::Invalid { .. })
+        ));
+    }
+
+    #[test]
+    fn test_missing_state() {
+        assert!(matches!(
+            OAuthState::verify_with_secret(None, SECRET),
+            Err(OAuthStateError::Missing)
+        ));
+    }
+
+    #[test]
+    fn test_state_consumed_once() {
+        let states = OAuthStates::default();
+        let state = states.issue_with_secret(SECRET).unwrap();
+
+        assert!(states.consume_with_secret(Some(&state), SECRET).is_ok());
+        assert!(matches!(
+            states.consume_with_secret(Some(&state), SECRET),
+            Err(OAuthStateError::Unknown)
+        ));
+    }
+
+    #[test]
+    fn test_state_not_issued_by_store() {
+        let states = OAuthStates::default();
+        let state = OAuthState::issue_with_secret(SECRET).unwrap();
+
+        assert!(matches!(
+            states.consume_with_secret(Some(&state), SECRET),
+            Err(OAuthStateError::Unknown)
+        ));
+        assert!(matches!(
+            states.consume_with_secret(None, SECRET),
+            Err(OAuthStateError::Missing)
+        ));
+    }
+}
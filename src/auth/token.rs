@@ -1,13 +1,22 @@
+mod store;
+
 use std::{
     ops::Deref,
+    sync::atomic::{AtomicU64, Ordering},
     time::{Duration, SystemTime},
 };
 
 use async_graphql::SimpleObject;
+use chrono::{DateTime as ChronoDateTime, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use super::role::Role;
+use crate::scalars::DateTime;
+
+pub use store::{token_store_from_env, InMemoryTokenStore, TokenStore, TokenStoreError};
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ResourceId(pub u64);
 
@@ -31,6 +40,12 @@ pub enum TokenError {
     EncodingError { source: jsonwebtoken::errors::Error },
     #[error("error while decoding token")]
     DecodingError { source: jsonwebtoken::errors::Error },
+    #[error("invalid refresh token")]
+    InvalidRefreshToken { source: jsonwebtoken::errors::Error },
+    #[error("expected refresh token, found access token")]
+    NotARefreshToken,
+    #[error("refresh token has already been used or revoked")]
+    RefreshTokenRevoked,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -39,7 +54,27 @@ struct Claims {
     iat: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     exp: Option<u64>,
+    /// Unique ID of the token, missing in tokens issued before revocation was supported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    jti: Option<String>,
     resource_id: ResourceId,
+    /// Role of the user at the time of signing in, missing in tokens issued before roles were supported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<Role>,
+}
+
+/// Returns a new token ID, unique within the process and across restarts.
+fn new_jti() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let issued_at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap();
+    format!(
+        "{}-{}",
+        issued_at.as_nanos(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    )
 }
 
 trait Token {
@@ -57,7 +92,7 @@ trait Token {
         let token = token_str.to_string();
         let secret = Self::secret();
         let decoding_key = DecodingKey::from_secret(secret.as_bytes());
-        let validation = Validation::default();
+        let validation = Self::validation();
 
         let token_data = decode::<Claims>(&token, &decoding_key, &validation)
             .map_err(|e| TokenError::DecodingError { source: e })?;
@@ -68,6 +103,10 @@ trait Token {
     fn expiration_time() -> Option<Duration> {
         None
     }
+
+    fn validation() -> Validation {
+        Validation::default()
+    }
 }
 
 #[derive(Debug)]
@@ -96,19 +135,73 @@ impl AccessToken {
     pub fn resource_id(&self) -> ResourceId {
         self.0.resource_id
     }
+
+    pub fn jti(&self) -> Option<&str> {
+        self.0.jti.as_deref()
+    }
+
+    pub fn role(&self) -> Option<Role> {
+        self.0.role
+    }
 }
 
-struct RefreshToken(Claims);
+/// Time a refresh token can be exchanged for, since it was issued.
+///
+/// Refresh tokens carry no `exp` claim, their lifetime is enforced by the `TokenStore`,
+/// which forgets them once it passes.
+const REFRESH_TOKEN_LIFETIME: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+
+#[derive(Debug)]
+pub struct RefreshToken(Claims);
 impl Token for RefreshToken {
     fn secret() -> String {
         std::env::var("JWT_REFRESH_TOKEN_SECRET").unwrap()
     }
+
+    // refresh tokens are intentionally issued without expiration time
+    fn validation() -> Validation {
+        Validation {
+            validate_exp: false,
+            ..Validation::default()
+        }
+    }
 }
 
 impl RefreshToken {
     fn encode(&self) -> Result<String, TokenError> {
         Self::encode_claims(&self.0)
     }
+
+    pub fn verify(token_str: &str) -> Result<RefreshToken, TokenError> {
+        let claims = Self::decode_claims(token_str).map_err(|e| match e {
+            TokenError::DecodingError { source } => TokenError::InvalidRefreshToken { source },
+            e => e,
+        })?;
+
+        // only access tokens expire
+        if claims.exp.is_some() {
+            return Err(TokenError::NotARefreshToken);
+        }
+
+        Ok(Self(claims))
+    }
+
+    pub fn resource_id(&self) -> ResourceId {
+        self.0.resource_id
+    }
+
+    /// Email of the user the token was issued for.
+    pub fn subject(&self) -> &str {
+        &self.0.sub
+    }
+
+    pub fn jti(&self) -> Option<&str> {
+        self.0.jti.as_deref()
+    }
+
+    pub fn role(&self) -> Option<Role> {
+        self.0.role
+    }
 }
 
 #[derive(SimpleObject, Debug, Serialize)]
@@ -116,22 +209,41 @@ pub struct TokenResponse {
     access_token: String,
     refresh_token: String,
     expires_in: u64,
+    /// Expiry time of the access token, same as its `exp` claim.
+    expires_at: DateTime,
+    /// Role of the user, embedded in the tokens.
+    role: Role,
+    #[graphql(skip)]
+    #[serde(skip)]
+    refresh_token_id: String,
+    #[graphql(skip)]
+    #[serde(skip)]
+    refresh_token_expires_at: u64,
 }
 
 impl TokenResponse {
-    pub fn with_user_data(email: &str, resource_id: ResourceId) -> Result<Self, TokenError> {
+    pub fn with_user_data(
+        email: &str,
+        resource_id: ResourceId,
+        role: Role,
+    ) -> Result<Self, TokenError> {
         let issued_at = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap();
         let expires_in = AccessToken::expiration_time().unwrap_or_default();
+        let expires_at = (issued_at + expires_in).as_secs();
         let access_token_claims = Claims {
             sub: email.to_string(),
             iat: issued_at.as_secs(),
-            exp: Some((issued_at + expires_in).as_secs()),
+            exp: Some(expires_at),
+            jti: Some(new_jti()),
             resource_id,
+            role: Some(role),
         };
+        let refresh_token_id = new_jti();
         let refresh_token_claims = Claims {
             exp: None,
+            jti: Some(refresh_token_id.clone()),
             ..access_token_claims.clone()
         };
 
@@ -142,6 +254,145 @@ impl TokenResponse {
             access_token,
             refresh_token,
             expires_in: expires_in.as_secs(),
+            // built from the claim rather than the exact issue time, so both are equal to the second
+            expires_at: DateTime(ChronoDateTime::<Utc>::from(
+                SystemTime::UNIX_EPOCH + Duration::from_secs(expires_at),
+            )),
+            role,
+            refresh_token_id,
+            refresh_token_expires_at: (issued_at + REFRESH_TOKEN_LIFETIME).as_secs(),
         })
     }
+
+    /// Records the issued refresh token in the store, so that it can be exchanged
+    /// until it expires.
+    pub async fn save_refresh_token(&self, store: &dyn TokenStore) -> Result<(), TokenStoreError> {
+        store
+            .save_refresh_token(&self.refresh_token_id, Some(self.refresh_token_expires_at))
+            .await
+    }
+}
+
+#[cfg(test)]
+impl AccessToken {
+    /// Access token of the resource, as verified from a request of an authorized user.
+    pub(crate) fn for_resource(resource_id: ResourceId, role: Role) -> Self {
+        AccessToken(Claims {
+            sub: "user@moodup.team".to_string(),
+            iat: 0,
+            exp: None,
+            jti: None,
+            resource_id,
+            role: Some(role),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use jsonwebtoken::errors::ErrorKind;
+
+    fn set_secrets() {
+        std::env::set_var("JWT_ACCESS_TOKEN_SECRET", "access-secret");
+        std::env::set_var("JWT_REFRESH_TOKEN_SECRET", "refresh-secret");
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn claims(exp: Option<u64>) -> Claims {
+        Claims {
+            sub: "user@moodup.team".to_string(),
+            iat: now() - 60 * 60 * 24 * 365,
+            exp,
+            jti: Some(new_jti()),
+            resource_id: ResourceId(1),
+            role: Some(Role::Admin),
+        }
+    }
+
+    #[test]
+    fn test_valid_access_token() {
+        set_secrets();
+        let token = AccessToken(claims(Some(now() + 60))).encode().unwrap();
+
+        let verified = AccessToken::verify(&token).unwrap();
+        assert_eq!(verified.resource_id().0, 1);
+        assert_eq!(verified.role(), Some(Role::Admin));
+    }
+
+    #[test]
+    fn test_expired_access_token() {
+        set_secrets();
+        let token = AccessToken(claims(Some(now() - 60))).encode().unwrap();
+
+        match AccessToken::verify(&token) {
+            Err(TokenError::DecodingError { source }) => {
+                assert!(matches!(source.kind(), ErrorKind::ExpiredSignature))
+            }
+            other => panic!("expected expired token error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_expires_at_matches_exp_claim() {
+        set_secrets();
+        let response =
+            TokenResponse::with_user_data("user@moodup.team", ResourceId(1), Role::Member).unwrap();
+
+        let verified = AccessToken::verify(&response.access_token).unwrap();
+        assert_eq!(
+            Some(response.expires_at.0.timestamp() as u64),
+            verified.0.exp
+        );
+    }
+
+    #[test]
+    fn test_refresh_token_never_expires() {
+        set_secrets();
+        let token = RefreshToken(claims(None)).encode().unwrap();
+
+        let verified = RefreshToken::verify(&token).unwrap();
+        assert_eq!(verified.resource_id().0, 1);
+        assert_eq!(verified.subject(), "user@moodup.team");
+    }
+
+    #[test]
+    fn test_refresh_token_rejects_access_token() {
+        set_secrets();
+        let access_token = AccessToken(claims(Some(now() + 60))).encode().unwrap();
+        assert!(matches!(
+            RefreshToken::verify(&access_token),
+            Err(TokenError::InvalidRefreshToken { .. })
+        ));
+
+        let expiring_token = RefreshToken(claims(Some(now() + 60))).encode().unwrap();
+        assert!(matches!(
+            RefreshToken::verify(&expiring_token),
+            Err(TokenError::NotARefreshToken)
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_refresh_token_is_saved_until_it_expires() {
+        set_secrets();
+        let issued_at = now();
+        let token =
+            TokenResponse::with_user_data("user@moodup.team", ResourceId(1), Role::Member).unwrap();
+        let lifetime = REFRESH_TOKEN_LIFETIME.as_secs();
+        assert!(token.refresh_token_expires_at >= issued_at + lifetime);
+        assert!(token.refresh_token_expires_at <= now() + lifetime);
+
+        let store = InMemoryTokenStore::default();
+        token.save_refresh_token(&store).await.unwrap();
+        assert!(store
+            .take_refresh_token(&token.refresh_token_id)
+            .await
+            .unwrap());
+    }
 }
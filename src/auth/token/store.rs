@@ -0,0 +1,230 @@
+use async_graphql::async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use thiserror::Error;
+
+#[cfg(feature = "redis-token-store")]
+const TOKEN_STORE_REDIS_URL_ENV_VARIABLE: &str = "TOKEN_STORE_REDIS_URL";
+
+#[derive(Debug, Error)]
+pub enum TokenStoreError {
+    #[cfg(feature = "redis-token-store")]
+    #[error("could not access Redis token store")]
+    Redis(#[from] redis::RedisError),
+}
+
+/// Shared state of the issued tokens, used for revocation and refresh token rotation.
+///
+/// Expiry times are UNIX timestamps in seconds, `None` meaning the entry never expires.
+#[async_trait]
+pub trait TokenStore: Send + Sync {
+    /// Marks the token with the given ID as revoked until it expires.
+    async fn revoke(&self, jti: &str, expires_at: Option<u64>) -> Result<(), TokenStoreError>;
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool, TokenStoreError>;
+
+    /// Records an issued refresh token, so that it can be exchanged later.
+    async fn save_refresh_token(
+        &self,
+        jti: &str,
+        expires_at: Option<u64>,
+    ) -> Result<(), TokenStoreError>;
+
+    /// Removes the refresh token, returning whether it was issued and not expired yet.
+    ///
+    /// Each refresh token can be taken only once, which allows rotating them.
+    async fn take_refresh_token(&self, jti: &str) -> Result<bool, TokenStoreError>;
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn is_expired(expires_at: Option<u64>) -> bool {
+    expires_at.map_or(false, |expires_at| expires_at <= now())
+}
+
+/// Store keeping tokens in the memory of the process.
+#[derive(Default, Debug)]
+pub struct InMemoryTokenStore {
+    revoked: Mutex<HashMap<String, Option<u64>>>,
+    refresh_tokens: Mutex<HashMap<String, Option<u64>>>,
+}
+
+#[async_trait]
+impl TokenStore for InMemoryTokenStore {
+    async fn revoke(&self, jti: &str, expires_at: Option<u64>) -> Result<(), TokenStoreError> {
+        let mut revoked = self.revoked.lock().unwrap();
+        revoked.retain(|_, expires_at| !is_expired(*expires_at));
+        revoked.insert(jti.to_string(), expires_at);
+        Ok(())
+    }
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool, TokenStoreError> {
+        let revoked = self.revoked.lock().unwrap();
+        Ok(revoked
+            .get(jti)
+            .map_or(false, |expires_at| !is_expired(*expires_at)))
+    }
+
+    async fn save_refresh_token(
+        &self,
+        jti: &str,
+        expires_at: Option<u64>,
+    ) -> Result<(), TokenStoreError> {
+        let mut refresh_tokens = self.refresh_tokens.lock().unwrap();
+        refresh_tokens.retain(|_, expires_at| !is_expired(*expires_at));
+        refresh_tokens.insert(jti.to_string(), expires_at);
+        Ok(())
+    }
+
+    async fn take_refresh_token(&self, jti: &str) -> Result<bool, TokenStoreError> {
+        let mut refresh_tokens = self.refresh_tokens.lock().unwrap();
+        Ok(refresh_tokens
+            .remove(jti)
+            .map_or(false, |expires_at| !is_expired(expires_at)))
+    }
+}
+
+/// Store keeping tokens in Redis, shared by all instances of the API.
+///
+/// A single multiplexed connection is shared by all requests, reconnecting when it breaks.
+#[cfg(feature = "redis-token-store")]
+pub struct RedisTokenStore {
+    client: redis::Client,
+    connection: tokio::sync::OnceCell<redis::aio::ConnectionManager>,
+}
+
+#[cfg(feature = "redis-token-store")]
+impl RedisTokenStore {
+    pub fn new(url: &str) -> Result<Self, TokenStoreError> {
+        Ok(RedisTokenStore {
+            client: redis::Client::open(url)?,
+            connection: tokio::sync::OnceCell::new(),
+        })
+    }
+
+    /// Returns the shared connection, connecting on the first use.
+    async fn connection(&self) -> Result<redis::aio::ConnectionManager, TokenStoreError> {
+        let connection = self
+            .connection
+            .get_or_try_init(|| redis::aio::ConnectionManager::new(self.client.clone()))
+            .await?;
+        Ok(connection.clone())
+    }
+
+    async fn set(&self, key: String, expires_at: Option<u64>) -> Result<(), TokenStoreError> {
+        use redis::AsyncCommands;
+
+        let mut connection = self.connection().await?;
+        match expires_at {
+            Some(expires_at) => {
+                let ttl = expires_at.saturating_sub(now());
+                if ttl > 0 {
+                    connection
+                        .set_ex::<_, _, ()>(key, true, ttl as usize)
+                        .await?;
+                }
+            }
+            None => connection.set::<_, _, ()>(key, true).await?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "redis-token-store")]
+#[async_trait]
+impl TokenStore for RedisTokenStore {
+    async fn revoke(&self, jti: &str, expires_at: Option<u64>) -> Result<(), TokenStoreError> {
+        self.set(format!("revoked:{}", jti), expires_at).await
+    }
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool, TokenStoreError> {
+        use redis::AsyncCommands;
+
+        let mut connection = self.connection().await?;
+        Ok(connection.exists(format!("revoked:{}", jti)).await?)
+    }
+
+    async fn save_refresh_token(
+        &self,
+        jti: &str,
+        expires_at: Option<u64>,
+    ) -> Result<(), TokenStoreError> {
+        self.set(format!("refresh:{}", jti), expires_at).await
+    }
+
+    async fn take_refresh_token(&self, jti: &str) -> Result<bool, TokenStoreError> {
+        use redis::AsyncCommands;
+
+        let mut connection = self.connection().await?;
+        let removed: u64 = connection.del(format!("refresh:{}", jti)).await?;
+        Ok(removed > 0)
+    }
+}
+
+/// Returns the store configured with the `TOKEN_STORE_REDIS_URL` env variable.
+///
+/// Tokens are kept in Redis when the `redis-token-store` feature is enabled and the variable is set,
+/// only in memory otherwise. Panics when the variable is not a valid Redis URL, rather than
+/// silently falling back to a store not shared by other instances.
+pub fn token_store_from_env() -> Arc<dyn TokenStore> {
+    #[cfg(feature = "redis-token-store")]
+    if let Ok(url) = std::env::var(TOKEN_STORE_REDIS_URL_ENV_VARIABLE) {
+        let store = RedisTokenStore::new(&url).unwrap_or_else(|e| {
+            panic!(
+                "Invalid {} env variable: {}",
+                TOKEN_STORE_REDIS_URL_ENV_VARIABLE, e
+            )
+        });
+        return Arc::new(store);
+    }
+
+    Arc::new(InMemoryTokenStore::default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_revoke_token() {
+        let store = InMemoryTokenStore::default();
+        assert!(!store.is_revoked("a").await.unwrap());
+
+        store.revoke("a", Some(now() + 60)).await.unwrap();
+        assert!(store.is_revoked("a").await.unwrap());
+        assert!(!store.is_revoked("b").await.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_revocation_expires_with_token() {
+        let store = InMemoryTokenStore::default();
+        store.revoke("a", Some(now() - 1)).await.unwrap();
+        assert!(!store.is_revoked("a").await.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_refresh_token_can_be_taken_once() {
+        let store = InMemoryTokenStore::default();
+        assert!(!store.take_refresh_token("a").await.unwrap());
+
+        store.save_refresh_token("a", None).await.unwrap();
+        assert!(store.take_refresh_token("a").await.unwrap());
+        assert!(!store.take_refresh_token("a").await.unwrap());
+    }
+
+    #[actix_web::test]
+    async fn test_expired_refresh_token_cannot_be_taken() {
+        let store = InMemoryTokenStore::default();
+        store
+            .save_refresh_token("a", Some(now() - 1))
+            .await
+            .unwrap();
+        assert!(!store.take_refresh_token("a").await.unwrap());
+    }
+}
@@ -0,0 +1,146 @@
+use async_graphql::{ErrorExtensions, FieldError, Result, SimpleObject};
+use thiserror::Error;
+
+const MAX_BATCH_SIZE_ENV_VARIABLE: &str = "MAX_BATCH_SIZE";
+const DEFAULT_MAX_BATCH_SIZE: usize = 50;
+
+/// Maximum number of items which can be processed by a single bulk mutation.
+///
+/// Configured with the `MAX_BATCH_SIZE` env variable, defaults to 50.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchLimit(pub usize);
+
+impl Default for BatchLimit {
+    fn default() -> Self {
+        BatchLimit(DEFAULT_MAX_BATCH_SIZE)
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum BatchError {
+    #[error("Batch of {size} items exceeds the limit of {limit} items")]
+    TooLarge { size: usize, limit: usize },
+}
+
+impl ErrorExtensions for BatchError {
+    fn extend(&self) -> FieldError {
+        self.extend_with(|err, e| match err {
+            BatchError::TooLarge { size, limit } => {
+                e.set("code", "BATCH_TOO_LARGE");
+                e.set("size", *size as u64);
+                e.set("limit", *limit as u64);
+            }
+        })
+    }
+}
+
+impl BatchLimit {
+    pub fn from_env() -> Self {
+        match std::env::var(MAX_BATCH_SIZE_ENV_VARIABLE) {
+            Ok(value) => BatchLimit(value.parse().unwrap_or_else(|_| {
+                panic!(
+                    "Invalid {} env variable: {}",
+                    MAX_BATCH_SIZE_ENV_VARIABLE, value
+                )
+            })),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Ensures the batch of items can be processed by a bulk mutation.
+    pub fn check<T>(&self, items: &[T]) -> Result<(), BatchError> {
+        if items.len() > self.0 {
+            Err(BatchError::TooLarge {
+                size: items.len(),
+                limit: self.0,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Error of a single item of a bulk mutation, which did not prevent processing other items.
+#[derive(SimpleObject, Debug, Clone, PartialEq, Eq)]
+pub struct BatchItemError {
+    /// Index of the failed item in the input list.
+    pub index: u64,
+    pub message: String,
+}
+
+/// Splits results of the batch items, given with their indexes, into successes and errors,
+/// both ordered as the items were in the input list.
+pub fn partition_results<T>(mut results: Vec<(usize, Result<T>)>) -> (Vec<T>, Vec<BatchItemError>) {
+    results.sort_by_key(|(index, _)| *index);
+
+    let mut successes = vec![];
+    let mut errors = vec![];
+    for (index, result) in results {
+        match result {
+            Ok(item) => successes.push(item),
+            Err(e) => errors.push(BatchItemError {
+                index: index as u64,
+                message: e.message,
+            }),
+        }
+    }
+
+    (successes, errors)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_batch_within_limit() {
+        let limit = BatchLimit(3);
+        assert_eq!(limit.check(&[1, 2, 3]), Ok(()));
+        assert_eq!(limit.check::<u64>(&[]), Ok(()));
+    }
+
+    #[test]
+    fn test_batch_too_large() {
+        let limit = BatchLimit(2);
+        assert_eq!(
+            limit.check(&["a", "b", "c"]),
+            Err(BatchError::TooLarge { size: 3, limit: 2 })
+        );
+    }
+
+    #[test]
+    fn test_partition_results() {
+        let results = vec![
+            (2, Ok("c")),
+            (0, Ok("a")),
+            (3, Err(async_graphql::Error::new("failed"))),
+            (1, Err(async_graphql::Error::new("invalid"))),
+        ];
+
+        let (successes, errors) = partition_results(results);
+        assert_eq!(successes, vec!["a", "c"]);
+        assert_eq!(
+            errors,
+            vec![
+                BatchItemError {
+                    index: 1,
+                    message: "invalid".to_string()
+                },
+                BatchItemError {
+                    index: 3,
+                    message: "failed".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch_too_large_error_code() {
+        let error = BatchError::TooLarge { size: 3, limit: 2 }.extend();
+        let extensions = error.extensions.unwrap();
+        assert_eq!(
+            extensions.get("code"),
+            Some(&async_graphql::Value::from("BATCH_TOO_LARGE"))
+        );
+    }
+}
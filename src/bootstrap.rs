@@ -0,0 +1,230 @@
+use crate::auth::guard::AccessTokenAuthGuard;
+use crate::auth::token::ResourceId;
+use crate::clock::Clock;
+use crate::loader::{ProjectLoader, ResourceLoader};
+use crate::project::{fetch_projects, ProjectModel};
+use crate::range::DateRange;
+use crate::resource::ResourceModel;
+use crate::scalars::Date;
+use crate::time_entry::{fetch_entries_in_range, TimeEntryModel};
+use crate::time_entry_tag::{fetch_time_entry_tags, TimeEntryTagModel};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::futures_util::future::try_join4;
+use async_graphql::{Context, Object, Result, SimpleObject};
+use chrono::Duration;
+use std::collections::HashMap;
+use std::future::Future;
+use teamdeck::AsyncTeamdeck;
+
+/// Number of days taken into account when looking for favorite projects.
+const FAVORITE_PROJECTS_PERIOD_DAYS: i64 = 30;
+const FAVORITE_PROJECTS_LIMIT: usize = 5;
+
+#[derive(SimpleObject, Debug)]
+pub struct Bootstrap {
+    me: Option<ResourceModel>,
+    projects: Vec<ProjectModel>,
+    tags: Vec<TimeEntryTagModel>,
+    /// Projects the authorized user logged the most time on in the last 30 days.
+    favorite_projects: Vec<ProjectModel>,
+}
+
+/// Returns IDs of the projects with the most logged minutes, most used first.
+fn favorite_project_ids(entries: &[TimeEntryModel], limit: usize) -> Vec<u64> {
+    rank_projects(entries.iter().map(|e| (e.project_id, e.minutes)), limit)
+}
+
+fn rank_projects(minutes: impl Iterator<Item = (u64, u64)>, limit: usize) -> Vec<u64> {
+    let mut minutes_by_project: HashMap<u64, u64> = HashMap::new();
+    for (project_id, minutes) in minutes {
+        *minutes_by_project.entry(project_id).or_default() += minutes;
+    }
+
+    let mut totals: Vec<(u64, u64)> = minutes_by_project.into_iter().collect();
+    totals.sort_by(|(a_id, a_minutes), (b_id, b_minutes)| {
+        b_minutes.cmp(a_minutes).then(a_id.cmp(b_id))
+    });
+
+    totals.into_iter().take(limit).map(|(id, _)| id).collect()
+}
+
+/// Awaits the sections of the bootstrap data concurrently, then loads the favorite projects
+/// found in the recent entries.
+async fn load_bootstrap<F>(
+    me: impl Future<Output = Result<Option<ResourceModel>>>,
+    projects: impl Future<Output = Result<Vec<ProjectModel>>>,
+    tags: impl Future<Output = Result<Vec<TimeEntryTagModel>>>,
+    recent_entries: impl Future<Output = Result<Vec<TimeEntryModel>>>,
+    load_projects: impl FnOnce(Vec<u64>) -> F,
+) -> Result<Bootstrap>
+where
+    F: Future<Output = Result<HashMap<u64, ProjectModel>>>,
+{
+    let (me, projects, tags, recent_entries) =
+        try_join4(me, projects, tags, recent_entries).await?;
+
+    let favorite_ids = favorite_project_ids(&recent_entries, FAVORITE_PROJECTS_LIMIT);
+    let mut favorites = load_projects(favorite_ids.clone()).await?;
+    let favorite_projects = favorite_ids
+        .iter()
+        .filter_map(|id| favorites.remove(id))
+        .collect();
+
+    Ok(Bootstrap {
+        me,
+        projects,
+        tags,
+        favorite_projects,
+    })
+}
+
+#[derive(Default, Debug)]
+pub struct BootstrapQuery;
+
+#[Object]
+impl BootstrapQuery {
+    /// Returns all data needed by the client on startup in a single round trip.
+    #[tracing::instrument(name = "Fetching bootstrap data", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn bootstrap(&self, ctx: &Context<'_>) -> Result<Bootstrap> {
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+        let resources = ctx.data_unchecked::<DataLoader<ResourceLoader>>();
+        let projects = ctx.data_unchecked::<DataLoader<ProjectLoader>>();
+
+        let today = ctx.data_unchecked::<Clock>().today();
+        let range = DateRange {
+            from: Date(today - Duration::days(FAVORITE_PROJECTS_PERIOD_DAYS)),
+            to: Date(today),
+        };
+
+        load_bootstrap(
+            resources.load_one(resource_id.0),
+            fetch_projects(client),
+            fetch_time_entry_tags(client),
+            fetch_entries_in_range(client, resource_id, &range),
+            |ids| projects.load_many(ids),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn resource(id: u64) -> ResourceModel {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": "Jane Doe",
+            "active": true,
+            "avatar": null,
+            "email": "jane@example.com",
+            "role": null,
+        }))
+        .unwrap()
+    }
+
+    fn project(id: u64) -> ProjectModel {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": "Tracker",
+            "color": "#000000",
+            "archived": false,
+        }))
+        .unwrap()
+    }
+
+    fn tag(id: u64) -> TimeEntryTagModel {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": "Meeting",
+            "icon": null,
+            "color": null,
+            "archived": 0,
+        }))
+        .unwrap()
+    }
+
+    fn entry(project_id: u64, minutes: u64) -> TimeEntryModel {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "resource_id": 1,
+            "project_id": project_id,
+            "minutes": minutes,
+            "weekend_booking": false,
+            "holidays_booking": false,
+            "vacations_booking": false,
+            "description": null,
+            "external_id": null,
+            "start_date": "2022-01-10",
+            "end_date": "2022-01-10",
+            "creator_resource_id": 1,
+            "editor_resource_id": 1,
+            "tags": null,
+        }))
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_load_bootstrap_populates_all_sections() {
+        let bootstrap = load_bootstrap(
+            async { Ok(Some(resource(1))) },
+            async { Ok(vec![project(1), project(2)]) },
+            async { Ok(vec![tag(3)]) },
+            async { Ok(vec![entry(2, 120), entry(1, 30), entry(7, 60)]) },
+            |ids| async move { Ok(ids.into_iter().map(|id| (id, project(id))).collect()) },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(bootstrap.me.map(|me| me.id), Some(1));
+        assert_eq!(
+            bootstrap.projects.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(
+            bootstrap.tags.iter().map(|t| t.id).collect::<Vec<_>>(),
+            vec![3]
+        );
+        assert_eq!(
+            bootstrap
+                .favorite_projects
+                .iter()
+                .map(|p| p.id)
+                .collect::<Vec<_>>(),
+            vec![2, 7, 1]
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_load_bootstrap_fails_when_any_section_fails() {
+        let bootstrap = load_bootstrap(
+            async { Ok(Some(resource(1))) },
+            async { Ok(vec![project(1)]) },
+            async { Err("Teamdeck is unreachable".into()) },
+            async { Ok(vec![]) },
+            |_| async { Ok(HashMap::new()) },
+        )
+        .await;
+
+        assert!(bootstrap.is_err());
+    }
+
+    #[test]
+    fn test_rank_projects_by_total_minutes() {
+        let minutes = vec![(1, 30), (2, 120), (1, 60), (3, 15)];
+        assert_eq!(rank_projects(minutes.into_iter(), 5), vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn test_rank_projects_respects_limit() {
+        let minutes = vec![(1, 30), (2, 120), (3, 15)];
+        assert_eq!(rank_projects(minutes.into_iter(), 2), vec![2, 1]);
+    }
+
+    #[test]
+    fn test_rank_projects_empty() {
+        assert!(rank_projects(std::iter::empty(), 5).is_empty());
+    }
+}
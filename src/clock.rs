@@ -0,0 +1,22 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Source of the current time, which can be fixed to make time-dependent logic testable.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Clock {
+    fixed: Option<DateTime<Utc>>,
+}
+
+impl Clock {
+    /// Clock always returning the given time.
+    pub fn fixed(at: DateTime<Utc>) -> Self {
+        Clock { fixed: Some(at) }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.fixed.unwrap_or_else(Utc::now)
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+}
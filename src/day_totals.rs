@@ -0,0 +1,92 @@
+use crate::auth::token::ResourceId;
+use crate::scalars::Date;
+use crate::time_entry::fetch_entries_on_date;
+use async_graphql::Result;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use teamdeck::AsyncTeamdeck;
+
+/// Minutes logged by a resource on a single day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DayTotal {
+    pub minutes: u64,
+    pub entries_count: usize,
+}
+
+impl DayTotal {
+    /// Returns the fraction of the day's total represented by the given minutes.
+    ///
+    /// An entry being the only one on its day always makes 100% of it.
+    pub fn share_of(&self, minutes: u64) -> f64 {
+        if self.entries_count <= 1 {
+            1.0
+        } else if self.minutes == 0 {
+            0.0
+        } else {
+            minutes as f64 / self.minutes as f64
+        }
+    }
+}
+
+/// Per-request cache of day totals, so sibling entries are fetched once per resource and day.
+#[derive(Default, Debug)]
+pub struct DayTotalsCache {
+    totals: Mutex<HashMap<(u64, NaiveDate), DayTotal>>,
+}
+
+impl DayTotalsCache {
+    pub async fn get(
+        &self,
+        client: &AsyncTeamdeck,
+        resource_id: u64,
+        date: Date,
+    ) -> Result<DayTotal> {
+        let key = (resource_id, date.0);
+        if let Some(total) = self.totals.lock().unwrap().get(&key) {
+            return Ok(*total);
+        }
+
+        let entries = fetch_entries_on_date(client, ResourceId(resource_id), date).await?;
+        let total = DayTotal {
+            minutes: entries.iter().map(|e| e.minutes).sum(),
+            entries_count: entries.len(),
+        };
+        self.totals.lock().unwrap().insert(key, total);
+
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_share_of_day() {
+        let total = DayTotal {
+            minutes: 480,
+            entries_count: 3,
+        };
+        assert_eq!(total.share_of(120), 0.25);
+        assert_eq!(total.share_of(480), 1.0);
+    }
+
+    #[test]
+    fn test_share_of_single_entry_day() {
+        let total = DayTotal {
+            minutes: 0,
+            entries_count: 1,
+        };
+        assert_eq!(total.share_of(0), 1.0);
+    }
+
+    #[test]
+    fn test_share_of_day_without_minutes() {
+        let total = DayTotal {
+            minutes: 0,
+            entries_count: 2,
+        };
+        assert_eq!(total.share_of(0), 0.0);
+    }
+}
@@ -0,0 +1,309 @@
+use crate::teamdeck::error::TeamdeckApiError;
+use async_graphql::{Context, Result, Value};
+use std::any::Any;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+const STALE_CACHE_TTL_ENV_VARIABLE: &str = "STALE_CACHE_TTL_SECONDS";
+const STALE_CACHE_MAX_SIZE_ENV_VARIABLE: &str = "STALE_CACHE_MAX_SIZE";
+const DEFAULT_STALE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+const DEFAULT_STALE_CACHE_MAX_SIZE: usize = 1000;
+
+/// Cache of the last successful responses of read queries,
+/// served when Teamdeck is unreachable.
+///
+/// Responses older than the configured TTL (`STALE_CACHE_TTL_SECONDS` env variable,
+/// 10 minutes by default) are never served and are evicted when new responses are cached.
+/// At most `STALE_CACHE_MAX_SIZE` responses (1000 by default) are kept,
+/// the oldest ones are evicted first.
+pub struct StaleCache {
+    ttl: Duration,
+    max_size: usize,
+    entries: Mutex<HashMap<String, (Instant, Box<dyn Any + Send + Sync>)>>,
+}
+
+impl Default for StaleCache {
+    fn default() -> Self {
+        StaleCache::new(DEFAULT_STALE_CACHE_TTL, DEFAULT_STALE_CACHE_MAX_SIZE)
+    }
+}
+
+/// Marks the request as served, at least partially, from stale data.
+#[derive(Default, Debug, Clone)]
+pub struct StaleFlag(Arc<AtomicBool>);
+
+impl StaleFlag {
+    pub fn mark(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl StaleCache {
+    pub fn new(ttl: Duration, max_size: usize) -> Self {
+        StaleCache {
+            ttl,
+            max_size,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        StaleCache::new(
+            Duration::from_secs(env_or(
+                STALE_CACHE_TTL_ENV_VARIABLE,
+                DEFAULT_STALE_CACHE_TTL.as_secs(),
+            )),
+            env_or(
+                STALE_CACHE_MAX_SIZE_ENV_VARIABLE,
+                DEFAULT_STALE_CACHE_MAX_SIZE,
+            ),
+        )
+    }
+
+    /// Returns the result of `fetch`, caching it under the `key`.
+    ///
+    /// When `fetch` fails because Teamdeck is unreachable and a fresh enough cached value exists,
+    /// the cached value is returned instead and the `stale` flag is marked.
+    /// Other errors, e.g. rejected credentials or invalid requests, are always returned.
+    pub async fn fetch<T, F>(&self, key: &str, stale: Option<&StaleFlag>, fetch: F) -> Result<T>
+    where
+        T: Clone + Send + Sync + 'static,
+        F: Future<Output = Result<T>>,
+    {
+        match fetch.await {
+            Ok(value) => {
+                self.insert(key, Box::new(value.clone()));
+                Ok(value)
+            }
+            Err(error) if !is_upstream_outage(&error) => Err(error),
+            Err(error) => match self.get(key) {
+                Some(value) => {
+                    warn!("Serving stale `{}` data: {:?}", key, error);
+                    if let Some(stale) = stale {
+                        stale.mark();
+                    }
+                    Ok(value)
+                }
+                None => Err(error),
+            },
+        }
+    }
+
+    fn get<T: Clone + 'static>(&self, key: &str) -> Option<T> {
+        let entries = self.entries.lock().unwrap();
+        let (cached_at, value) = entries.get(key)?;
+        if cached_at.elapsed() > self.ttl {
+            return None;
+        }
+
+        value.downcast_ref::<T>().cloned()
+    }
+
+    fn insert(&self, key: &str, value: Box<dyn Any + Send + Sync>) {
+        if self.max_size == 0 {
+            return;
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let ttl = self.ttl;
+        entries.retain(|_, (cached_at, _)| cached_at.elapsed() <= ttl);
+        if entries.len() >= self.max_size && !entries.contains_key(key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (cached_at, _))| *cached_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key.to_string(), (Instant::now(), value));
+    }
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid {} env variable: {}", name, value)),
+        Err(_) => default,
+    }
+}
+
+/// Returns whether the error means Teamdeck is unreachable or failing, i.e. a connection error,
+/// a timeout or a `5xx` response, rather than Teamdeck rejecting the request.
+///
+/// Errors of the Teamdeck client library carry only their message, so errors of unknown kind
+/// are taken as outages, unless they are marked with a `code` extension other than `TIMEOUT`.
+fn is_upstream_outage(error: &async_graphql::Error) -> bool {
+    let source = error.source.as_deref();
+    if let Some(error) = source.and_then(|source| source.downcast_ref::<TeamdeckApiError>()) {
+        return matches!(
+            error,
+            TeamdeckApiError::Timeout | TeamdeckApiError::ServerError(_)
+        );
+    }
+    if let Some(error) = source.and_then(|source| source.downcast_ref::<reqwest::Error>()) {
+        return error.is_connect()
+            || error.is_timeout()
+            || error
+                .status()
+                .map_or(false, |status| status.is_server_error());
+    }
+
+    match error
+        .extensions
+        .as_ref()
+        .and_then(|extensions| extensions.get("code"))
+    {
+        Some(code) => *code == Value::from("TIMEOUT"),
+        None => true,
+    }
+}
+
+/// Fetches data with the `StaleCache` configured in the schema.
+pub async fn with_stale_fallback<T, F>(ctx: &Context<'_>, key: &str, fetch: F) -> Result<T>
+where
+    T: Clone + Send + Sync + 'static,
+    F: Future<Output = Result<T>>,
+{
+    let cache = ctx.data_unchecked::<StaleCache>();
+    cache.fetch(key, ctx.data_opt::<StaleFlag>(), fetch).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_graphql::ErrorExtensions;
+
+    async fn upstream_failure() -> Result<Vec<u64>> {
+        Err(async_graphql::Error::new("Teamdeck is unreachable"))
+    }
+
+    #[actix_web::test]
+    async fn test_fetch_caches_successful_response() {
+        let cache = StaleCache::default();
+        let stale = StaleFlag::default();
+
+        let value = cache.fetch("projects", Some(&stale), async { Ok(vec![1, 2]) });
+        assert_eq!(value.await.unwrap(), vec![1, 2]);
+        assert!(!stale.is_stale());
+    }
+
+    #[actix_web::test]
+    async fn test_upstream_failure_serves_stale_data() {
+        let cache = StaleCache::default();
+        let stale = StaleFlag::default();
+
+        cache
+            .fetch("projects", Some(&stale), async { Ok(vec![1, 2]) })
+            .await
+            .unwrap();
+        let value = cache
+            .fetch("projects", Some(&stale), upstream_failure())
+            .await;
+
+        assert_eq!(value.unwrap(), vec![1, 2]);
+        assert!(stale.is_stale());
+    }
+
+    #[actix_web::test]
+    async fn test_upstream_failure_without_cache_fails() {
+        let cache = StaleCache::default();
+        let stale = StaleFlag::default();
+
+        let value = cache
+            .fetch("projects", Some(&stale), upstream_failure())
+            .await;
+
+        assert!(value.is_err());
+        assert!(!stale.is_stale());
+    }
+
+    #[actix_web::test]
+    async fn test_rejected_request_does_not_serve_stale_data() {
+        let cache = StaleCache::default();
+        let stale = StaleFlag::default();
+
+        cache
+            .fetch("projects", Some(&stale), async { Ok(vec![1, 2]) })
+            .await
+            .unwrap();
+        let unauthorized = cache
+            .fetch("projects", Some(&stale), async {
+                Err::<Vec<u64>, _>(TeamdeckApiError::Unauthorized.into())
+            })
+            .await;
+        let invalid = cache
+            .fetch("projects", Some(&stale), async {
+                Err::<Vec<u64>, _>(TeamdeckApiError::Validation(vec![]).extend())
+            })
+            .await;
+
+        assert!(unauthorized.is_err());
+        assert!(invalid.is_err());
+        assert!(!stale.is_stale());
+    }
+
+    #[actix_web::test]
+    async fn test_timeout_serves_stale_data() {
+        let cache = StaleCache::default();
+        let stale = StaleFlag::default();
+
+        cache
+            .fetch("projects", Some(&stale), async { Ok(vec![1, 2]) })
+            .await
+            .unwrap();
+        let value = cache
+            .fetch("projects", Some(&stale), async {
+                Err::<Vec<u64>, _>(TeamdeckApiError::Timeout.extend())
+            })
+            .await;
+
+        assert_eq!(value.unwrap(), vec![1, 2]);
+        assert!(stale.is_stale());
+    }
+
+    #[actix_web::test]
+    async fn test_oldest_response_is_evicted() {
+        let cache = StaleCache::new(Duration::from_secs(60), 1);
+        let stale = StaleFlag::default();
+
+        cache
+            .fetch("projects", Some(&stale), async { Ok(vec![1]) })
+            .await
+            .unwrap();
+        cache
+            .fetch("resources", Some(&stale), async { Ok(vec![2]) })
+            .await
+            .unwrap();
+
+        assert!(cache.get::<Vec<u64>>("projects").is_none());
+        assert_eq!(cache.get::<Vec<u64>>("resources"), Some(vec![2]));
+    }
+
+    #[actix_web::test]
+    async fn test_expired_cache_is_not_served() {
+        let cache = StaleCache::new(Duration::from_secs(0), DEFAULT_STALE_CACHE_MAX_SIZE);
+        let stale = StaleFlag::default();
+
+        cache
+            .fetch("projects", Some(&stale), async { Ok(vec![1, 2]) })
+            .await
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        let value = cache
+            .fetch("projects", Some(&stale), upstream_failure())
+            .await;
+
+        assert!(value.is_err());
+        assert!(!stale.is_stale());
+    }
+}
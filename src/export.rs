@@ -0,0 +1,181 @@
+use crate::auth::token::ResourceId;
+use crate::project::{fetch_projects_by_id, ProjectModel};
+use crate::range::{DateRange, RangeError};
+use crate::scalars::Date;
+use crate::time_entry::{fetch_entries_in_range, format_minutes, TimeEntryModel};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use teamdeck::AsyncTeamdeck;
+use thiserror::Error;
+
+const CSV_HEADER: [&str; 6] = [
+    "date",
+    "project",
+    "minutes",
+    "duration",
+    "description",
+    "tags",
+];
+const TAGS_SEPARATOR: &str = "; ";
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error(transparent)]
+    InvalidRange(#[from] RangeError),
+    #[error("Failed to fetch time entries from Teamdeck: {0}")]
+    Teamdeck(String),
+}
+
+impl From<async_graphql::Error> for ExportError {
+    fn from(e: async_graphql::Error) -> Self {
+        ExportError::Teamdeck(e.message)
+    }
+}
+
+/// Exports time entries of a resource as CSV.
+pub struct TimeEntriesExport {
+    client: AsyncTeamdeck,
+}
+
+impl Default for TimeEntriesExport {
+    fn default() -> Self {
+        let api_key = std::env::var("TEAMDECK_API_KEY").unwrap();
+        TimeEntriesExport {
+            client: AsyncTeamdeck::new(api_key),
+        }
+    }
+}
+
+impl TimeEntriesExport {
+    /// Returns CSV lines (header first) of the resource's entries starting within the range.
+    ///
+    /// Names of all projects are fetched at once, regardless of the number of entries.
+    pub async fn csv_lines(
+        &self,
+        resource_id: ResourceId,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<String>, ExportError> {
+        let range = DateRange {
+            from: Date(from),
+            to: Date(to),
+        };
+        range.validate()?;
+
+        let entries = fetch_entries_in_range(&self.client, resource_id, &range).await?;
+        let mut project_ids: Vec<u64> = entries.iter().map(|e| e.project_id).collect();
+        project_ids.sort_unstable();
+        project_ids.dedup();
+        let projects = fetch_projects_by_id(&self.client, &project_ids).await?;
+
+        Ok(csv_lines(&entries, &projects))
+    }
+}
+
+fn csv_lines(entries: &[TimeEntryModel], projects: &HashMap<u64, ProjectModel>) -> Vec<String> {
+    let header = csv_line(CSV_HEADER.iter().map(|column| column.to_string()));
+    let rows = entries.iter().map(|entry| {
+        let tags = entry
+            .tags
+            .iter()
+            .flatten()
+            .map(|tag| tag.name.as_str())
+            .collect::<Vec<_>>()
+            .join(TAGS_SEPARATOR);
+
+        csv_line(vec![
+            entry.start_date.0.to_string(),
+            projects
+                .get(&entry.project_id)
+                .map(|project| project.name.clone())
+                .unwrap_or_default(),
+            entry.minutes.to_string(),
+            format_minutes(entry.minutes),
+            entry.description.clone().unwrap_or_default(),
+            tags,
+        ])
+    });
+
+    std::iter::once(header).chain(rows).collect()
+}
+
+fn csv_line(fields: impl IntoIterator<Item = String>) -> String {
+    let fields: Vec<String> = fields.into_iter().map(|field| escape(&field)).collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+/// Quotes the field if it contains characters with special meaning in CSV.
+fn escape(field: &str) -> String {
+    if field.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(project_id: u64, description: Option<&str>) -> TimeEntryModel {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "resource_id": 1,
+            "project_id": project_id,
+            "minutes": 90,
+            "weekend_booking": false,
+            "holidays_booking": false,
+            "vacations_booking": false,
+            "description": description,
+            "external_id": null,
+            "start_date": "2022-05-02",
+            "end_date": "2022-05-02",
+            "creator_resource_id": 1,
+            "editor_resource_id": 1,
+            "tags": [
+                {"id": 1, "name": "meeting", "icon": null, "color": null, "archived": 0},
+                {"id": 2, "name": "client", "icon": null, "color": null, "archived": 0},
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn projects() -> HashMap<u64, ProjectModel> {
+        let project: ProjectModel = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": "Tracker",
+            "color": "#000000",
+            "archived": false,
+        }))
+        .unwrap();
+        vec![(1, project)].into_iter().collect()
+    }
+
+    #[test]
+    fn test_empty_export_has_only_header() {
+        assert_eq!(
+            csv_lines(&[], &projects()),
+            vec!["date,project,minutes,duration,description,tags\r\n"]
+        );
+    }
+
+    #[test]
+    fn test_export_rows() {
+        let lines = csv_lines(&[entry(1, Some("Planning")), entry(2, None)], &projects());
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            "2022-05-02,Tracker,90,1:30,Planning,meeting; client\r\n"
+        );
+        assert!(lines[2].starts_with("2022-05-02,,90,"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("a, b"), "\"a, b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape("two\nlines"), "\"two\nlines\"");
+    }
+}
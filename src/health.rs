@@ -0,0 +1,89 @@
+use crate::teamdeck::api::TeamdeckApiClient;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const READINESS_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// Result of the last check, reused until it gets older than the TTL.
+#[derive(Debug)]
+struct CachedCheck {
+    ttl: Duration,
+    last: Mutex<Option<(Instant, bool)>>,
+}
+
+impl CachedCheck {
+    fn new(ttl: Duration) -> Self {
+        CachedCheck {
+            ttl,
+            last: Mutex::new(None),
+        }
+    }
+
+    fn get(&self) -> Option<bool> {
+        match *self.last.lock().unwrap() {
+            Some((checked_at, result)) if checked_at.elapsed() < self.ttl => Some(result),
+            _ => None,
+        }
+    }
+
+    fn set(&self, result: bool) {
+        *self.last.lock().unwrap() = Some((Instant::now(), result));
+    }
+}
+
+/// Readiness of the API to serve requests, which requires Teamdeck to be reachable.
+///
+/// The result is cached for a few seconds, so frequent probes do not hammer Teamdeck.
+pub struct Readiness {
+    client: TeamdeckApiClient,
+    cache: CachedCheck,
+}
+
+impl Default for Readiness {
+    fn default() -> Self {
+        Readiness {
+            client: TeamdeckApiClient::default(),
+            cache: CachedCheck::new(READINESS_CACHE_TTL),
+        }
+    }
+}
+
+impl Readiness {
+    pub async fn is_ready(&self) -> bool {
+        if let Some(ready) = self.cache.get() {
+            return ready;
+        }
+
+        let ready = match self.client.ping().await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Teamdeck API is unreachable: {:?}", e);
+                false
+            }
+        };
+        self.cache.set(ready);
+
+        ready
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cached_check() {
+        let cache = CachedCheck::new(Duration::from_secs(60));
+        assert_eq!(cache.get(), None);
+
+        cache.set(false);
+        assert_eq!(cache.get(), Some(false));
+    }
+
+    #[test]
+    fn test_cached_check_expires() {
+        let cache = CachedCheck::new(Duration::ZERO);
+        cache.set(true);
+        assert_eq!(cache.get(), None);
+    }
+}
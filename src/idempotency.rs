@@ -0,0 +1,278 @@
+use async_graphql::{ErrorExtensions, FieldError, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+const IDEMPOTENCY_KEY_TTL: Duration = Duration::from_secs(10 * 60);
+const MAX_IDEMPOTENCY_KEYS: usize = 10_000;
+
+/// Result of an operation guarded by an idempotency key.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Idempotent<T> {
+    /// The operation was performed for the first time.
+    Created(T),
+    /// The operation was already performed with the key, creating the item with this ID.
+    Replayed(u64),
+}
+
+#[derive(Debug, Error)]
+pub enum IdempotencyError {
+    #[error("A request with the same idempotency key is still in progress")]
+    InProgress,
+}
+
+impl ErrorExtensions for IdempotencyError {
+    fn extend(&self) -> FieldError {
+        self.extend_with(|err, e| match err {
+            IdempotencyError::InProgress => e.set("code", "IDEMPOTENCY_KEY_IN_PROGRESS"),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum KeyState {
+    /// The item is being created by a request which hasn't finished yet.
+    Pending,
+    /// The item with this ID was created.
+    Created(u64),
+}
+
+type Key = (u64, String);
+
+/// Remembers which time entries were created with which idempotency keys,
+/// so client retries of `createTimeEntry` don't create duplicates.
+///
+/// Keys are scoped to resources and remembered in memory for 10 minutes.
+/// A key is reserved before the entry is created, so a concurrent request with the same key
+/// fails with an `IDEMPOTENCY_KEY_IN_PROGRESS` error instead of creating a duplicate,
+/// and the reservation is released when the creation fails.
+/// Expired keys are evicted when new ones are added, and when the limit of 10 000 keys
+/// is reached, the oldest completed ones are evicted first.
+#[derive(Debug)]
+pub struct IdempotencyKeys {
+    ttl: Duration,
+    max_keys: usize,
+    keys: Mutex<HashMap<Key, (Instant, KeyState)>>,
+}
+
+/// Releases the reserved key, unless the item was created.
+struct Reservation<'a> {
+    keys: &'a IdempotencyKeys,
+    key: Option<Key>,
+}
+
+impl Reservation<'_> {
+    fn complete(mut self, id: u64) {
+        if let Some(key) = self.key.take() {
+            self.keys
+                .keys
+                .lock()
+                .unwrap()
+                .insert(key, (Instant::now(), KeyState::Created(id)));
+        }
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.keys.keys.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+impl Default for IdempotencyKeys {
+    fn default() -> Self {
+        IdempotencyKeys::new(IDEMPOTENCY_KEY_TTL, MAX_IDEMPOTENCY_KEYS)
+    }
+}
+
+impl IdempotencyKeys {
+    pub fn new(ttl: Duration, max_keys: usize) -> Self {
+        IdempotencyKeys {
+            ttl,
+            max_keys,
+            keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Runs `create`, unless it was already run for the resource with the same key.
+    ///
+    /// `id` returns the ID of the created item, which is remembered when `create` succeeds.
+    /// Fails with [`IdempotencyError::InProgress`] while another `create` with the same key runs.
+    pub async fn create_once<T, F, Fut>(
+        &self,
+        resource_id: u64,
+        key: &str,
+        create: F,
+        id: impl Fn(&T) -> u64,
+    ) -> Result<Idempotent<T>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let reservation = match self.reserve((resource_id, key.to_string()))? {
+            Ok(reservation) => reservation,
+            Err(created_id) => return Ok(Idempotent::Replayed(created_id)),
+        };
+
+        let created = create().await?;
+        reservation.complete(id(&created));
+        Ok(Idempotent::Created(created))
+    }
+
+    /// Reserves the key, or returns the ID of the item already created with it.
+    fn reserve(&self, key: Key) -> Result<Result<Reservation<'_>, u64>> {
+        let mut keys = self.keys.lock().unwrap();
+        let ttl = self.ttl;
+        keys.retain(|_, (created_at, _)| created_at.elapsed() < ttl);
+
+        match keys.get(&key) {
+            Some((_, KeyState::Created(id))) => return Ok(Err(*id)),
+            Some((_, KeyState::Pending)) => return Err(IdempotencyError::InProgress.extend()),
+            None => {}
+        }
+
+        if keys.len() >= self.max_keys {
+            let oldest = keys
+                .iter()
+                .filter(|(_, (_, state))| matches!(state, KeyState::Created(_)))
+                .min_by_key(|(_, (created_at, _))| *created_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                keys.remove(&oldest);
+            }
+        }
+        keys.insert(key.clone(), (Instant::now(), KeyState::Pending));
+
+        Ok(Ok(Reservation {
+            keys: self,
+            key: Some(key),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::rt::task::yield_now;
+    use async_graphql::futures_util::future::join;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    async fn create(
+        keys: &IdempotencyKeys,
+        resource_id: u64,
+        key: &str,
+        created: &AtomicU64,
+    ) -> Idempotent<u64> {
+        keys.create_once(
+            resource_id,
+            key,
+            || async { Ok(created.fetch_add(1, Ordering::SeqCst) + 100) },
+            |id| *id,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_same_key_creates_once() {
+        let keys = IdempotencyKeys::default();
+        let created = AtomicU64::new(0);
+
+        assert_eq!(
+            create(&keys, 1, "key", &created).await,
+            Idempotent::Created(100)
+        );
+        assert_eq!(
+            create(&keys, 1, "key", &created).await,
+            Idempotent::Replayed(100)
+        );
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_keys_are_scoped_to_resources() {
+        let keys = IdempotencyKeys::default();
+        let created = AtomicU64::new(0);
+
+        create(&keys, 1, "key", &created).await;
+        create(&keys, 2, "key", &created).await;
+        create(&keys, 1, "other-key", &created).await;
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_failed_creation_is_not_remembered() {
+        let keys = IdempotencyKeys::default();
+        let failed: Result<Idempotent<u64>> = keys
+            .create_once(
+                1,
+                "key",
+                || async { Err(async_graphql::Error::new("Teamdeck is unreachable")) },
+                |id| *id,
+            )
+            .await;
+        assert!(failed.is_err());
+
+        let created = AtomicU64::new(0);
+        assert_eq!(
+            create(&keys, 1, "key", &created).await,
+            Idempotent::Created(100)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_concurrent_requests_with_same_key_create_once() {
+        let keys = IdempotencyKeys::default();
+        let created = AtomicU64::new(0);
+        let counter = &created;
+        let create_slowly = || async move {
+            yield_now().await;
+            Ok(counter.fetch_add(1, Ordering::SeqCst) + 100)
+        };
+
+        let (first, second) = join(
+            keys.create_once(1, "key", create_slowly, |id| *id),
+            keys.create_once(1, "key", create_slowly, |id| *id),
+        )
+        .await;
+
+        assert_eq!(first.unwrap(), Idempotent::Created(100));
+        let extensions = second.unwrap_err().extensions.unwrap();
+        assert_eq!(
+            extensions.get("code"),
+            Some(&async_graphql::Value::from("IDEMPOTENCY_KEY_IN_PROGRESS"))
+        );
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            create(&keys, 1, "key", &created).await,
+            Idempotent::Replayed(100)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_expired_keys_are_forgotten() {
+        let keys = IdempotencyKeys::new(Duration::ZERO, 10);
+        let created = AtomicU64::new(0);
+
+        create(&keys, 1, "key", &created).await;
+        create(&keys, 1, "key", &created).await;
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_oldest_key_is_evicted() {
+        let keys = IdempotencyKeys::new(Duration::from_secs(60), 1);
+        let created = AtomicU64::new(0);
+
+        create(&keys, 1, "first", &created).await;
+        create(&keys, 1, "second", &created).await;
+        assert!(matches!(
+            create(&keys, 1, "first", &created).await,
+            Idempotent::Created(_)
+        ));
+    }
+}
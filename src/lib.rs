@@ -1,25 +1,65 @@
+mod audit;
 pub mod auth;
+mod batch;
+mod bootstrap;
+mod clock;
+mod day_totals;
+mod degraded;
+pub mod export;
+pub mod health;
+mod idempotency;
+mod loader;
+mod lookup_cache;
 mod project;
+mod query_limits;
+pub mod range;
+mod rate_limit;
+pub mod request_id;
 mod resource;
 mod scalars;
 mod sort_by_enum;
+mod summary;
+mod tag_policy;
+mod tag_validation;
 mod teamdeck;
 mod time_entry;
 mod time_entry_tag;
 mod timer;
+mod validation;
+mod warnings;
 
+use crate::audit::AuditQuery;
+use crate::batch::BatchLimit;
+use crate::bootstrap::BootstrapQuery;
+use crate::clock::Clock;
+use crate::day_totals::DayTotalsCache;
+use crate::degraded::{StaleCache, StaleFlag};
+use crate::idempotency::IdempotencyKeys;
+use crate::loader::{ProjectLoader, ResourceLoader};
+use crate::lookup_cache::LookupCache;
 use crate::project::ProjectQuery;
+use crate::query_limits::QueryLimits;
+use crate::rate_limit::MutationRateLimiter;
 use crate::resource::ResourceQuery;
+use crate::summary::SummaryQuery;
+use crate::tag_policy::TagPolicy;
+use crate::tag_validation::TagValidator;
 use crate::teamdeck::api::TeamdeckApiClient;
-use crate::time_entry::{TimeEntryMutation, TimeEntryQuery};
-use crate::timer::{TimerMutation, TimerQuery, Timers};
+use crate::time_entry::{DefaultMinutes, TimeEntryMutation, TimeEntryQuery};
+use crate::timer::{store_from_env, TimerMutation, TimerQuery, TimerSubscription, Timers};
+use crate::warnings::Warnings;
 use ::teamdeck::AsyncTeamdeck;
+use async_graphql::dataloader::DataLoader;
 use async_graphql::extensions::ApolloTracing;
-use async_graphql::{EmptySubscription, MergedObject, Schema};
-use auth::{AuthMutation, AuthQuery};
+use async_graphql::{MergedObject, MergedSubscription, Schema};
+use auth::{
+    guard::AdminResourceIds, role::RoleMapping, state::OAuthStates, token::TokenStore,
+    AuthMutation, AuthQuery, GoogleKeys, ServiceAccounts,
+};
+use std::sync::Arc;
 use time_entry_tag::TimeEntryTagQuery;
 
-pub type ApiSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+pub type ApiSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
 
 #[derive(MergedObject, Default)]
 pub struct QueryRoot(
@@ -28,23 +68,84 @@ pub struct QueryRoot(
     ProjectQuery,
     TimeEntryQuery,
     TimeEntryTagQuery,
+    SummaryQuery,
+    BootstrapQuery,
+    AuditQuery,
     AuthQuery,
 );
 
 #[derive(MergedObject, Default)]
 pub struct MutationRoot(TimerMutation, TimeEntryMutation, AuthMutation);
 
-pub fn create_schema() -> ApiSchema {
-    Schema::build(
+#[derive(MergedSubscription, Default)]
+pub struct SubscriptionRoot(TimerSubscription);
+
+/// Creates the schema, sharing the token store with the handlers outside of it.
+pub fn create_schema(token_store: Arc<dyn TokenStore>) -> ApiSchema {
+    let api_key = std::env::var("TEAMDECK_API_KEY").unwrap();
+
+    let builder = Schema::build(
         QueryRoot::default(),
         MutationRoot::default(),
-        EmptySubscription,
-    )
-    .data(TeamdeckApiClient::default())
-    .data(AsyncTeamdeck::new(
-        std::env::var("TEAMDECK_API_KEY").unwrap(),
-    ))
-    .data(Timers::default())
-    .extension(ApolloTracing)
-    .finish()
+        SubscriptionRoot::default(),
+    );
+
+    QueryLimits::from_env()
+        .apply(builder)
+        .data(TeamdeckApiClient::default())
+        .data(AsyncTeamdeck::new(api_key.clone()))
+        .data(DataLoader::new(
+            ProjectLoader::new(AsyncTeamdeck::new(api_key.clone()), LookupCache::from_env()),
+            actix_web::rt::spawn,
+        ))
+        .data(DataLoader::new(
+            ResourceLoader::new(AsyncTeamdeck::new(api_key), LookupCache::from_env()),
+            actix_web::rt::spawn,
+        ))
+        .data(Timers::with_store(store_from_env()))
+        .data(Clock::default())
+        .data(StaleCache::from_env())
+        .data(TagPolicy::from_env())
+        .data(TagValidator::from_env())
+        .data(BatchLimit::from_env())
+        .data(DefaultMinutes::from_env())
+        .data(IdempotencyKeys::default())
+        .data(MutationRateLimiter::from_env())
+        .data(RoleMapping::from_env())
+        .data(AdminResourceIds::from_env())
+        .data(token_store)
+        .data(GoogleKeys::default())
+        .data(OAuthStates::default())
+        .data(ServiceAccounts::from_env())
+        .extension(ApolloTracing)
+        .finish()
+}
+
+/// Executes the GraphQL request with per-request data attached.
+///
+/// Responses served, at least partially, from stale cache
+/// when Teamdeck is unreachable are marked with `stale: true` extension.
+/// Warnings about incomplete results are returned in `warnings` extension.
+pub async fn execute_request(
+    schema: &ApiSchema,
+    request: async_graphql::Request,
+) -> async_graphql::Response {
+    let stale = StaleFlag::default();
+    let warnings = Warnings::default();
+    let request = request
+        .data(DayTotalsCache::default())
+        .data(stale.clone())
+        .data(warnings.clone());
+
+    let mut response = schema.execute(request).await;
+    if stale.is_stale() {
+        response
+            .extensions
+            .insert("stale".to_string(), async_graphql::Value::Boolean(true));
+    }
+    if let Some(warnings) = warnings.to_extension() {
+        response.extensions.insert("warnings".to_string(), warnings);
+    }
+
+    response
 }
@@ -0,0 +1,65 @@
+use crate::lookup_cache::LookupCache;
+use crate::project::{fetch_projects_by_id, ProjectModel};
+use crate::resource::{fetch_resources_by_id, ResourceModel};
+use async_graphql::async_trait::async_trait;
+use async_graphql::dataloader::Loader;
+use std::collections::HashMap;
+use teamdeck::AsyncTeamdeck;
+
+/// Batches lookups of projects by ID made while resolving a single query.
+///
+/// Projects found in the cache shared between queries are not fetched again.
+pub struct ProjectLoader {
+    client: AsyncTeamdeck,
+    cache: LookupCache<ProjectModel>,
+}
+
+impl ProjectLoader {
+    pub fn new(client: AsyncTeamdeck, cache: LookupCache<ProjectModel>) -> Self {
+        ProjectLoader { client, cache }
+    }
+}
+
+#[async_trait]
+impl Loader<u64> for ProjectLoader {
+    type Value = ProjectModel;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[u64]) -> Result<HashMap<u64, Self::Value>, Self::Error> {
+        let client = &self.client;
+        self.cache
+            .load(keys, |missing| async move {
+                fetch_projects_by_id(client, &missing).await
+            })
+            .await
+    }
+}
+
+/// Batches lookups of resources by ID made while resolving a single query.
+///
+/// Resources found in the cache shared between queries are not fetched again.
+pub struct ResourceLoader {
+    client: AsyncTeamdeck,
+    cache: LookupCache<ResourceModel>,
+}
+
+impl ResourceLoader {
+    pub fn new(client: AsyncTeamdeck, cache: LookupCache<ResourceModel>) -> Self {
+        ResourceLoader { client, cache }
+    }
+}
+
+#[async_trait]
+impl Loader<u64> for ResourceLoader {
+    type Value = ResourceModel;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[u64]) -> Result<HashMap<u64, Self::Value>, Self::Error> {
+        let client = &self.client;
+        self.cache
+            .load(keys, |missing| async move {
+                fetch_resources_by_id(client, &missing).await
+            })
+            .await
+    }
+}
@@ -0,0 +1,192 @@
+use async_graphql::Result;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const LOOKUP_CACHE_TTL_ENV_VARIABLE: &str = "LOOKUP_CACHE_TTL_SECONDS";
+const LOOKUP_CACHE_MAX_SIZE_ENV_VARIABLE: &str = "LOOKUP_CACHE_MAX_SIZE";
+const DEFAULT_LOOKUP_CACHE_TTL: Duration = Duration::from_secs(60);
+const DEFAULT_LOOKUP_CACHE_MAX_SIZE: usize = 1000;
+
+/// Cache of projects or resources looked up by ID, which rarely change.
+///
+/// Entries expire after the configured TTL (`LOOKUP_CACHE_TTL_SECONDS` env variable,
+/// a minute by default), setting it to `0` bypasses the cache. At most `LOOKUP_CACHE_MAX_SIZE`
+/// entries (1000 by default) are kept, the oldest ones are evicted first.
+pub struct LookupCache<V> {
+    ttl: Duration,
+    max_size: usize,
+    entries: Mutex<HashMap<u64, (Instant, V)>>,
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid {} env variable: {}", name, value)),
+        Err(_) => default,
+    }
+}
+
+impl<V: Clone> LookupCache<V> {
+    pub fn new(ttl: Duration, max_size: usize) -> Self {
+        LookupCache {
+            ttl,
+            max_size,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        LookupCache::new(
+            Duration::from_secs(env_or(
+                LOOKUP_CACHE_TTL_ENV_VARIABLE,
+                DEFAULT_LOOKUP_CACHE_TTL.as_secs(),
+            )),
+            env_or(
+                LOOKUP_CACHE_MAX_SIZE_ENV_VARIABLE,
+                DEFAULT_LOOKUP_CACHE_MAX_SIZE,
+            ),
+        )
+    }
+
+    fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero() && self.max_size > 0
+    }
+
+    /// Returns values with the given IDs, calling `fetch` only with IDs which are not cached.
+    ///
+    /// IDs missing from the fetched values are not cached, so they are fetched again next time.
+    pub async fn load<F, Fut>(&self, ids: &[u64], fetch: F) -> Result<HashMap<u64, V>>
+    where
+        F: FnOnce(Vec<u64>) -> Fut,
+        Fut: Future<Output = Result<HashMap<u64, V>>>,
+    {
+        if !self.is_enabled() {
+            return fetch(ids.to_vec()).await;
+        }
+
+        let (mut values, missing) = self.get_many(ids);
+        if missing.is_empty() {
+            return Ok(values);
+        }
+
+        let fetched = fetch(missing).await?;
+        self.insert_many(&fetched);
+        values.extend(fetched);
+        Ok(values)
+    }
+
+    /// Splits the IDs into cached values and IDs which need to be fetched.
+    fn get_many(&self, ids: &[u64]) -> (HashMap<u64, V>, Vec<u64>) {
+        let entries = self.entries.lock().unwrap();
+        let mut values = HashMap::new();
+        let mut missing = vec![];
+        for id in ids {
+            match entries.get(id) {
+                Some((cached_at, value)) if cached_at.elapsed() < self.ttl => {
+                    values.insert(*id, value.clone());
+                }
+                _ => missing.push(*id),
+            }
+        }
+
+        (values, missing)
+    }
+
+    fn insert_many(&self, values: &HashMap<u64, V>) {
+        let mut entries = self.entries.lock().unwrap();
+        for (id, value) in values {
+            if entries.len() >= self.max_size && !entries.contains_key(id) {
+                let ttl = self.ttl;
+                entries.retain(|_, (cached_at, _)| cached_at.elapsed() < ttl);
+            }
+            if entries.len() >= self.max_size && !entries.contains_key(id) {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, (cached_at, _))| *cached_at)
+                    .map(|(id, _)| *id);
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+            entries.insert(*id, (Instant::now(), value.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn load(
+        cache: &LookupCache<String>,
+        ids: &[u64],
+        fetched: &AtomicUsize,
+    ) -> HashMap<u64, String> {
+        cache
+            .load(ids, |missing| async move {
+                fetched.fetch_add(missing.len(), Ordering::SeqCst);
+                Ok(missing
+                    .into_iter()
+                    .map(|id| (id, format!("project {}", id)))
+                    .collect())
+            })
+            .await
+            .unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_second_lookup_is_cached() {
+        let cache = LookupCache::new(Duration::from_secs(60), 10);
+        let fetched = AtomicUsize::new(0);
+
+        load(&cache, &[1, 2], &fetched).await;
+        let values = load(&cache, &[1, 2], &fetched).await;
+
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+        assert_eq!(values[&1], "project 1");
+        assert_eq!(values[&2], "project 2");
+    }
+
+    #[actix_web::test]
+    async fn test_only_missing_ids_are_fetched() {
+        let cache = LookupCache::new(Duration::from_secs(60), 10);
+        let fetched = AtomicUsize::new(0);
+
+        load(&cache, &[1], &fetched).await;
+        let values = load(&cache, &[1, 2, 3], &fetched).await;
+
+        assert_eq!(fetched.load(Ordering::SeqCst), 3);
+        assert_eq!(values.len(), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_zero_ttl_bypasses_cache() {
+        let cache = LookupCache::new(Duration::ZERO, 10);
+        let fetched = AtomicUsize::new(0);
+
+        load(&cache, &[1], &fetched).await;
+        load(&cache, &[1], &fetched).await;
+
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+    }
+
+    #[actix_web::test]
+    async fn test_oldest_entries_are_evicted() {
+        let cache = LookupCache::new(Duration::from_secs(60), 2);
+        let fetched = AtomicUsize::new(0);
+
+        load(&cache, &[1], &fetched).await;
+        load(&cache, &[2], &fetched).await;
+        load(&cache, &[3], &fetched).await;
+        assert_eq!(fetched.load(Ordering::SeqCst), 3);
+
+        load(&cache, &[2, 3], &fetched).await;
+        assert_eq!(fetched.load(Ordering::SeqCst), 3);
+        load(&cache, &[1], &fetched).await;
+        assert_eq!(fetched.load(Ordering::SeqCst), 4);
+    }
+}
@@ -1,15 +1,32 @@
 mod telemetry;
 
-use crate::telemetry::{get_logs_subscriber, init_logs_subscriber};
+use crate::telemetry::{
+    get_logs_subscriber, init_logs_subscriber, shutdown_telemetry, RequestIdRootSpanBuilder,
+};
 use actix_cors::Cors;
 
-use actix_web::web::Data;
-use actix_web::{guard, web, App, HttpRequest, HttpResponse, HttpServer, Result};
+use actix_web::dev::Service;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::web::{Bytes, Data};
+use actix_web::{guard, web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Result};
+use async_graphql::futures_util::stream;
 use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
-use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse, GraphQLSubscription};
+use chrono::NaiveDate;
 use reqwest::header::AUTHORIZATION;
 use serde::{Deserialize, Serialize};
-use teamdeck_tracker_api::{auth::token::AccessToken, create_schema, ApiSchema};
+use teamdeck_tracker_api::{
+    auth::{
+        state::OAuthState,
+        token::{token_store_from_env, AccessToken, TokenStore},
+    },
+    create_schema, execute_request,
+    export::{ExportError, TimeEntriesExport},
+    health::Readiness,
+    request_id::{RequestId, REQUEST_ID_HEADER},
+    ApiSchema,
+};
 use tracing_actix_web::TracingLogger;
 
 async fn index(
@@ -19,28 +36,67 @@ async fn index(
 ) -> GraphQLResponse {
     let mut query: async_graphql::Request = req.into_inner();
 
-    let auth_token = dbg!(get_token(http_req));
-    let access_token = dbg!(auth_token.and_then(|t| AccessToken::verify(&t).ok()));
-
-    if let Some(token) = access_token {
+    if let Some(token) = get_token(http_req).and_then(verify_token) {
         let resource_id = token.resource_id();
         query = query.data(token).data(resource_id);
     }
 
-    schema.execute(query).await.into()
+    execute_request(&schema, query).await.into()
+}
+
+async fn index_ws(
+    schema: web::Data<ApiSchema>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse> {
+    GraphQLSubscription::new(ApiSchema::clone(&schema))
+        .on_connection_init(on_connection_init)
+        .start(&req, payload)
+}
+
+/// Authorizes the subscription connection with the `Authorization` field of its init payload.
+async fn on_connection_init(
+    payload: serde_json::Value,
+) -> async_graphql::Result<async_graphql::Data> {
+    let mut data = async_graphql::Data::default();
+
+    let token = payload
+        .get("Authorization")
+        .or_else(|| payload.get("authorization"))
+        .and_then(|value| value.as_str())
+        .and_then(token_from_header_value);
+    if let Some(token) = token.and_then(verify_token) {
+        data.insert(token.resource_id());
+        data.insert(token);
+    }
+
+    Ok(data)
+}
+
+fn verify_token(token: String) -> Option<AccessToken> {
+    match AccessToken::verify(&token) {
+        Ok(token) => Some(token),
+        Err(e) => {
+            // the token itself is never logged
+            tracing::debug!("Rejected access token: {}", e);
+            None
+        }
+    }
 }
 
 fn get_token(req: HttpRequest) -> Option<String> {
     let authorization_header = req.headers().get(AUTHORIZATION);
     if let Some(value) = authorization_header {
-        let contents = value.to_str().unwrap_or("");
-        let token = contents.split_whitespace().last().map(|t| t.to_string());
-        token
+        token_from_header_value(value.to_str().unwrap_or(""))
     } else {
         None
     }
 }
 
+fn token_from_header_value(contents: &str) -> Option<String> {
+    contents.split_whitespace().last().map(|t| t.to_string())
+}
+
 async fn index_playground() -> Result<HttpResponse> {
     Ok(HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
@@ -52,13 +108,88 @@ async fn index_playground() -> Result<HttpResponse> {
 #[derive(Debug, Serialize, Deserialize)]
 struct GoogleSignInQuery {
     code: String,
+    state: Option<String>,
 }
 
 async fn google_signin_redirect(query: web::Query<GoogleSignInQuery>) -> Result<HttpResponse> {
+    if let Err(e) = OAuthState::verify(query.state.as_deref()) {
+        return Ok(HttpResponse::BadRequest().body(e.to_string()));
+    }
+
+    // the state is consumed when exchanging the code, the client needs it along with the code
     // TODO: Add some HTML template for displaying the code in more user friendly way
     Ok(HttpResponse::Ok().body(serde_json::to_string(&query.0)?))
 }
 
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+/// Downloads time entries of the authorized user starting within the range as a CSV file.
+async fn export_time_entries(
+    export: web::Data<TimeEntriesExport>,
+    token_store: web::Data<dyn TokenStore>,
+    query: web::Query<ExportQuery>,
+    http_req: HttpRequest,
+) -> HttpResponse {
+    let token = match get_token(http_req).and_then(verify_token) {
+        Some(token) => token,
+        None => return HttpResponse::Unauthorized().finish(),
+    };
+    // revoked tokens are rejected the same way as by the GraphQL guard
+    if let Some(jti) = token.jti() {
+        match token_store.is_revoked(jti).await {
+            Ok(false) => {}
+            Ok(true) => return HttpResponse::Unauthorized().finish(),
+            Err(e) => {
+                tracing::error!("Failed to check revocation of access token: {}", e);
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    }
+
+    let lines = match export
+        .csv_lines(token.resource_id(), query.from, query.to)
+        .await
+    {
+        Ok(lines) => lines,
+        Err(e @ ExportError::InvalidRange(_)) => {
+            return HttpResponse::BadRequest().body(e.to_string())
+        }
+        Err(e @ ExportError::Teamdeck(_)) => {
+            tracing::error!("Failed to export time entries: {}", e);
+            return HttpResponse::BadGateway().body(e.to_string());
+        }
+    };
+
+    let filename = format!("time-entries-{}-{}.csv", query.from, query.to);
+    HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename)],
+        })
+        .streaming(stream::iter(
+            lines
+                .into_iter()
+                .map(|line| Ok::<_, actix_web::Error>(Bytes::from(line))),
+        ))
+}
+
+async fn health() -> HttpResponse {
+    HttpResponse::Ok().json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn ready(readiness: web::Data<Readiness>) -> HttpResponse {
+    if readiness.is_ready().await {
+        HttpResponse::Ok().json(serde_json::json!({ "status": "ready" }))
+    } else {
+        HttpResponse::ServiceUnavailable().json(serde_json::json!({ "status": "unavailable" }))
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv::dotenv().ok();
@@ -70,6 +201,12 @@ async fn main() -> std::io::Result<()> {
 
     // println!("Playground: http://localhost:8000");
 
+    let readiness = Data::new(Readiness::default());
+    let export = Data::new(TimeEntriesExport::default());
+    let token_store: Data<dyn TokenStore> = Data::from(token_store_from_env());
+    // shared by all workers, so that timer changes reach subscribers connected to any of them
+    let schema = Data::new(create_schema(token_store.clone().into_inner()));
+
     HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
@@ -79,17 +216,55 @@ async fn main() -> std::io::Result<()> {
 
         App::new()
             .wrap(cors)
-            .wrap(TracingLogger::default())
-            .app_data(Data::new(create_schema()))
+            // runs within the span of `TracingLogger`, which assigns the request ID
+            .wrap_fn(|req, srv| {
+                let request_id = req
+                    .extensions()
+                    .get::<RequestId>()
+                    .cloned()
+                    .unwrap_or_else(RequestId::generate);
+                let response = request_id.clone().scope(srv.call(req));
+
+                async move {
+                    let mut response = response.await?;
+                    if let Ok(value) = HeaderValue::from_str(request_id.as_str()) {
+                        response
+                            .headers_mut()
+                            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                    }
+                    Ok(response)
+                }
+            })
+            .wrap(TracingLogger::<RequestIdRootSpanBuilder>::new())
+            .app_data(schema.clone())
+            .app_data(readiness.clone())
+            .app_data(export.clone())
+            .app_data(token_store.clone())
             .service(web::resource("/").guard(guard::Post()).to(index))
+            .service(
+                web::resource("/")
+                    .guard(guard::Get())
+                    .guard(guard::Header("upgrade", "websocket"))
+                    .to(index_ws),
+            )
             .service(web::resource("/").guard(guard::Get()).to(index_playground))
             .service(
                 web::resource("/google/redirect")
                     .guard(guard::Get())
                     .to(google_signin_redirect),
             )
+            .service(
+                web::resource("/export/time-entries")
+                    .guard(guard::Get())
+                    .to(export_time_entries),
+            )
+            .service(web::resource("/health").guard(guard::Get()).to(health))
+            .service(web::resource("/ready").guard(guard::Get()).to(ready))
     })
     .bind(format!("0.0.0.0:{}", port))?
     .run()
-    .await
+    .await?;
+
+    shutdown_telemetry();
+    Ok(())
 }
@@ -1,29 +1,325 @@
-use async_graphql::{Context, Object, Result, SimpleObject};
+use crate::auth::guard::{is_admin, AccessTokenAuthGuard};
+use crate::auth::token::ResourceId;
+use crate::clock::Clock;
+use crate::degraded::with_stale_fallback;
+use crate::range::DateRange;
+use crate::scalars::Date;
+use crate::sort_by_enum::sort_by_enum;
+use crate::summary::month_bounds;
+use crate::teamdeck::api::{ListPageParams, PaginationInfo, TeamdeckApiClient};
+use crate::time_entry::TimeEntryModel;
+use crate::validation::ValidationError;
+use async_graphql::{ComplexObject, Context, InputObject, Object, Result, ResultExt, SimpleObject};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use teamdeck::{
-    api::{projects::Projects, AsyncQuery},
+    api::{
+        paged,
+        projects::{Project, Projects},
+        time_entries::{TimeEntries, TimeEntriesExpand},
+        AsyncQuery, Pagination,
+    },
     AsyncTeamdeck,
 };
 
-#[derive(Serialize, Deserialize, SimpleObject, Debug)]
+const ARCHIVED_SUFFIX: &str = "(archived)";
+
+sort_by_enum!(
+    ProjectsSortBy { Name },
+    teamdeck::api::projects::ProjectsSortBy
+);
+
+#[derive(InputObject, Default, Debug)]
+pub struct ProjectFilter {
+    /// Sorts results by the given field in the given direction.
+    ///
+    /// Default: `Name` ascending
+    pub sort: Option<ProjectsSortBy>,
+
+    /// Returns only archived (`true`) or only active (`false`) projects.
+    ///
+    /// By default, all of the projects are returned.
+    pub archived: Option<bool>,
+}
+
+impl ProjectFilter {
+    fn sort(&self) -> ProjectsSortBy {
+        self.sort.unwrap_or(ProjectsSortBy::NameAsc)
+    }
+
+    fn matches(&self, project: &ProjectModel) -> bool {
+        self.archived
+            .map_or(true, |archived| project.archived == archived)
+    }
+}
+
+/// Single page of projects.
+#[derive(SimpleObject, Debug)]
+pub struct ProjectConnection {
+    pub items: Vec<ProjectModel>,
+    pub pagination: PaginationInfo,
+}
+
+#[derive(Serialize, Deserialize, SimpleObject, Debug, Clone)]
+#[graphql(complex)]
 pub struct ProjectModel {
-    id: u64,
-    name: String,
+    pub id: u64,
+    pub name: String,
     color: String,
     archived: bool,
 }
 
+impl ProjectModel {
+    fn archived_aware_name(&self) -> String {
+        if self.archived {
+            format!("{} {}", self.name, ARCHIVED_SUFFIX)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+#[ComplexObject]
+impl ProjectModel {
+    /// Name of the project, suffixed with `(archived)` for archived projects.
+    async fn display_name(&self) -> String {
+        self.archived_aware_name()
+    }
+
+    /// Time entries logged to the project, by default within the current month.
+    ///
+    /// Admins get entries of all resources, other users only their own entries.
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn time_entries(
+        &self,
+        ctx: &Context<'_>,
+        date_range: Option<DateRange>,
+    ) -> Result<Vec<TimeEntryModel>> {
+        fetch_project_entries(ctx, self.id, date_range).await
+    }
+
+    /// Sum of minutes of the project's `timeEntries` within the date range.
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn total_minutes(&self, ctx: &Context<'_>, date_range: Option<DateRange>) -> Result<u64> {
+        let entries = fetch_project_entries(ctx, self.id, date_range).await?;
+        Ok(entries.iter().map(|entry| entry.minutes).sum())
+    }
+}
+
+/// Returns the given date range, or the month containing `today` when there's none.
+fn range_or_month(date_range: Option<DateRange>, today: NaiveDate) -> DateRange {
+    date_range.unwrap_or_else(|| {
+        let (first, last) = month_bounds(today);
+        DateRange {
+            from: Date(first),
+            to: Date(last),
+        }
+    })
+}
+
+async fn fetch_project_entries(
+    ctx: &Context<'_>,
+    project_id: u64,
+    date_range: Option<DateRange>,
+) -> Result<Vec<TimeEntryModel>> {
+    let client = ctx.data_unchecked::<AsyncTeamdeck>();
+    let range = range_or_month(date_range, ctx.data_unchecked::<Clock>().today());
+    range
+        .validate()
+        .map_err(|e| ValidationError::new("dateRange", e))
+        .extend()?;
+
+    let mut builder = TimeEntries::builder();
+    builder
+        .project_id(vec![project_id])
+        .expand(TimeEntriesExpand::Tags)
+        .start_date_from(range.from.0)
+        .start_date_to(range.to.0);
+    if !is_admin(ctx).await? {
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        builder.resource_id(vec![resource_id.0]);
+    }
+    let endpoint = builder.build()?;
+
+    let time_entries = paged(endpoint, Pagination::All).query_async(client).await?;
+    Ok(time_entries)
+}
+
 #[derive(Default, Debug)]
 pub struct ProjectQuery;
 
 #[Object]
 impl ProjectQuery {
+    /// Returns projects matching the filter.
+    ///
+    /// All of the projects are returned unless `page` or `perPage` is given.
+    /// Archived projects are filtered out after fetching the page,
+    /// so pagination details do not account for them.
     #[tracing::instrument(name = "Fetching all projects", skip(ctx))]
-    async fn projects(&self, ctx: &Context<'_>) -> Result<Vec<ProjectModel>> {
+    async fn projects(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<ProjectFilter>,
+        page: Option<u64>,
+        per_page: Option<u64>,
+    ) -> Result<ProjectConnection> {
+        let filter = filter.unwrap_or_default();
+        let sort = filter.sort();
+
+        let (mut items, pagination) = if page.is_some() || per_page.is_some() {
+            let client = ctx.data_unchecked::<TeamdeckApiClient>();
+            let params = ListPageParams::new(sort.to_query_param(), page, per_page);
+            let page = client.projects_page(&params).await.extend()?;
+            (page.items, page.pagination)
+        } else {
+            let client = ctx.data_unchecked::<AsyncTeamdeck>();
+            let key = format!("projects:{}", sort.to_query_param());
+            let projects =
+                with_stale_fallback(ctx, &key, fetch_sorted_projects(client, sort)).await?;
+            let pagination = PaginationInfo::single_page(projects.len() as u64);
+            (projects, pagination)
+        };
+        items.retain(|p| filter.matches(p));
+
+        Ok(ProjectConnection { items, pagination })
+    }
+
+    #[tracing::instrument(name = "Fetching project by id", skip(ctx))]
+    async fn project(&self, ctx: &Context<'_>, project_id: u64) -> Result<Option<ProjectModel>> {
         let client = ctx.data_unchecked::<AsyncTeamdeck>();
-        let endpoint = Projects::builder().build().unwrap();
+        fetch_project(client, project_id).await
+    }
+}
+
+pub(crate) async fn fetch_projects(client: &AsyncTeamdeck) -> Result<Vec<ProjectModel>> {
+    let endpoint = Projects::builder().build().unwrap();
+
+    let projects = endpoint.query_async(client).await?;
+    Ok(projects)
+}
+
+async fn fetch_sorted_projects(
+    client: &AsyncTeamdeck,
+    sort: ProjectsSortBy,
+) -> Result<Vec<ProjectModel>> {
+    let endpoint = Projects::builder().sort(sort.into()).build().unwrap();
+
+    let projects = endpoint.query_async(client).await?;
+    Ok(projects)
+}
+
+pub(crate) async fn fetch_project(
+    client: &AsyncTeamdeck,
+    project_id: u64,
+) -> Result<Option<ProjectModel>> {
+    let endpoint = Project::builder().id(project_id as usize).build().unwrap();
+
+    let project = endpoint.query_async(client).await?;
+    Ok(project)
+}
+
+/// Fetches projects with the given IDs, keyed by their IDs.
+///
+/// Projects missing from the projects list (e.g. archived ones) are fetched one by one,
+/// projects which do not exist are left out.
+pub(crate) async fn fetch_projects_by_id(
+    client: &AsyncTeamdeck,
+    project_ids: &[u64],
+) -> Result<HashMap<u64, ProjectModel>> {
+    let mut projects: HashMap<u64, ProjectModel> = fetch_projects(client)
+        .await?
+        .into_iter()
+        .filter(|p| project_ids.contains(&p.id))
+        .map(|p| (p.id, p))
+        .collect();
+
+    for project_id in project_ids {
+        if !projects.contains_key(project_id) {
+            if let Some(project) = fetch_project(client, *project_id).await? {
+                projects.insert(project.id, project);
+            }
+        }
+    }
+
+    Ok(projects)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn project(name: &str, archived: bool) -> ProjectModel {
+        ProjectModel {
+            id: 1,
+            name: name.to_string(),
+            color: "#000000".to_string(),
+            archived,
+        }
+    }
+
+    #[test]
+    fn test_display_name_active() {
+        let project = project("Tracker", false);
+        assert_eq!(project.archived_aware_name(), "Tracker");
+    }
+
+    #[test]
+    fn test_display_name_archived() {
+        let project = project("Tracker", true);
+        assert_eq!(project.archived_aware_name(), "Tracker (archived)");
+        assert_eq!(project.name, "Tracker");
+    }
+
+    #[test]
+    fn test_range_defaults_to_current_month() {
+        let today = NaiveDate::from_ymd_opt(2024, 2, 15).unwrap();
+
+        let range = range_or_month(None, today);
+        assert_eq!(range.from.0, NaiveDate::from_ymd_opt(2024, 2, 1).unwrap());
+        assert_eq!(range.to.0, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+
+        let given = DateRange {
+            from: Date(NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
+            to: Date(NaiveDate::from_ymd_opt(2024, 1, 7).unwrap()),
+        };
+        let range = range_or_month(Some(given), today);
+        assert_eq!(range.from.0, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+        assert_eq!(range.to.0, NaiveDate::from_ymd_opt(2024, 1, 7).unwrap());
+    }
+
+    #[test]
+    fn test_default_sort() {
+        let filter = ProjectFilter::default();
+        assert_eq!(filter.sort(), ProjectsSortBy::NameAsc);
+    }
+
+    #[test]
+    fn test_sort_conversion() {
+        use teamdeck::api::{projects, sort_by::SortBy};
+
+        let sort_by: SortBy<projects::ProjectsSortBy> = ProjectsSortBy::NameAsc.into();
+        assert_eq!(sort_by, SortBy::Asc(projects::ProjectsSortBy::Name));
+
+        let sort_by: SortBy<projects::ProjectsSortBy> = ProjectsSortBy::NameDesc.into();
+        assert_eq!(sort_by, SortBy::Desc(projects::ProjectsSortBy::Name));
+        assert_eq!(ProjectsSortBy::NameDesc.to_query_param(), "-name");
+    }
+
+    #[test]
+    fn test_archived_filter() {
+        let active = project("Tracker", false);
+        let archived = project("Legacy", true);
+
+        let filter = ProjectFilter {
+            archived: Some(false),
+            ..Default::default()
+        };
+        assert!(filter.matches(&active));
+        assert!(!filter.matches(&archived));
 
-        let projects = endpoint.query_async(client).await?;
-        Ok(projects)
+        let filter = ProjectFilter::default();
+        assert!(filter.matches(&active));
+        assert!(filter.matches(&archived));
     }
 }
@@ -0,0 +1,128 @@
+use async_graphql::{ObjectType, SchemaBuilder, SubscriptionType};
+
+const MAX_QUERY_DEPTH_ENV_VARIABLE: &str = "MAX_QUERY_DEPTH";
+const MAX_QUERY_COMPLEXITY_ENV_VARIABLE: &str = "MAX_QUERY_COMPLEXITY";
+const DEFAULT_MAX_QUERY_DEPTH: usize = 15;
+const DEFAULT_MAX_QUERY_COMPLEXITY: usize = 500;
+
+/// Limits of incoming queries, protecting Teamdeck from deeply nested queries
+/// which would trigger many upstream requests.
+///
+/// Queries exceeding them are rejected before execution with
+/// `Query is nested too deep.` or `Query is too complex.` errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryLimits {
+    pub max_depth: usize,
+    pub max_complexity: usize,
+}
+
+impl Default for QueryLimits {
+    fn default() -> Self {
+        QueryLimits {
+            max_depth: DEFAULT_MAX_QUERY_DEPTH,
+            max_complexity: DEFAULT_MAX_QUERY_COMPLEXITY,
+        }
+    }
+}
+
+fn limit_from_env(name: &str, default: usize) -> usize {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .unwrap_or_else(|_| panic!("Invalid {} env variable: {}", name, value)),
+        Err(_) => default,
+    }
+}
+
+impl QueryLimits {
+    /// Reads the limits from `MAX_QUERY_DEPTH` and `MAX_QUERY_COMPLEXITY` env variables,
+    /// defaulting to 15 and 500.
+    pub fn from_env() -> Self {
+        QueryLimits {
+            max_depth: limit_from_env(MAX_QUERY_DEPTH_ENV_VARIABLE, DEFAULT_MAX_QUERY_DEPTH),
+            max_complexity: limit_from_env(
+                MAX_QUERY_COMPLEXITY_ENV_VARIABLE,
+                DEFAULT_MAX_QUERY_COMPLEXITY,
+            ),
+        }
+    }
+
+    pub fn apply<Query, Mutation, Subscription>(
+        &self,
+        builder: SchemaBuilder<Query, Mutation, Subscription>,
+    ) -> SchemaBuilder<Query, Mutation, Subscription>
+    where
+        Query: ObjectType + 'static,
+        Mutation: ObjectType + 'static,
+        Subscription: SubscriptionType + 'static,
+    {
+        builder
+            .limit_depth(self.max_depth)
+            .limit_complexity(self.max_complexity)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_graphql::{EmptyMutation, EmptySubscription, Object, Schema};
+
+    struct Node;
+
+    #[Object]
+    impl Node {
+        async fn value(&self) -> u64 {
+            1
+        }
+
+        async fn child(&self) -> Node {
+            Node
+        }
+    }
+
+    fn schema(limits: QueryLimits) -> Schema<Node, EmptyMutation, EmptySubscription> {
+        limits
+            .apply(Schema::build(Node, EmptyMutation, EmptySubscription))
+            .finish()
+    }
+
+    fn limits(max_depth: usize, max_complexity: usize) -> QueryLimits {
+        QueryLimits {
+            max_depth,
+            max_complexity,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_query_within_depth_limit() {
+        let response = schema(limits(3, 100))
+            .execute("{ child { child { value } } }")
+            .await;
+        assert!(response.errors.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_query_over_depth_limit() {
+        let response = schema(limits(3, 100))
+            .execute("{ child { child { child { value } } } }")
+            .await;
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(response.errors[0].message, "Query is nested too deep.");
+    }
+
+    #[actix_web::test]
+    async fn test_query_over_complexity_limit() {
+        let schema = schema(limits(10, 3));
+        assert!(schema
+            .execute("{ value child { value } }")
+            .await
+            .errors
+            .is_empty());
+
+        let response = schema
+            .execute("{ value child { value child { value } } }")
+            .await;
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(response.errors[0].message, "Query is too complex.");
+    }
+}
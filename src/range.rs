@@ -0,0 +1,91 @@
+use crate::scalars::{Date, DateTime};
+use async_graphql::InputObject;
+use chrono::{DateTime as ChronoDateTime, Utc};
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RangeError {
+    #[error("`from` cannot be later than `to`")]
+    FromAfterTo,
+}
+
+/// Range of dates, both bounds inclusive.
+#[derive(InputObject, Debug, Clone)]
+pub struct DateRange {
+    pub from: Date,
+    pub to: Date,
+}
+
+impl DateRange {
+    pub fn validate(&self) -> Result<(), RangeError> {
+        if self.from.0 > self.to.0 {
+            return Err(RangeError::FromAfterTo);
+        }
+
+        Ok(())
+    }
+}
+
+/// Range of date times, both bounds inclusive.
+#[derive(InputObject, Debug, Clone)]
+pub struct DateTimeRange {
+    pub from: DateTime,
+    pub to: DateTime,
+}
+
+impl DateTimeRange {
+    pub fn validate(&self) -> Result<(), RangeError> {
+        if self.from.0 > self.to.0 {
+            return Err(RangeError::FromAfterTo);
+        }
+
+        Ok(())
+    }
+
+    pub fn contains(&self, datetime: &ChronoDateTime<Utc>) -> bool {
+        self.from.0 <= *datetime && *datetime <= self.to.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn date(day: u32) -> Date {
+        Date(NaiveDate::from_ymd_opt(2024, 1, day).unwrap())
+    }
+
+    fn at(value: &str) -> DateTime {
+        DateTime(ChronoDateTime::parse_from_rfc3339(value).unwrap().into())
+    }
+
+    #[test]
+    fn test_date_range_validation() {
+        let range = |from, to| DateRange {
+            from: date(from),
+            to: date(to),
+        };
+        assert_eq!(range(1, 7).validate(), Ok(()));
+        assert_eq!(range(7, 7).validate(), Ok(()));
+        assert_eq!(range(8, 7).validate(), Err(RangeError::FromAfterTo));
+    }
+
+    #[test]
+    fn test_date_time_range() {
+        let range = DateTimeRange {
+            from: at("2024-01-01T08:00:00Z"),
+            to: at("2024-01-01T16:00:00Z"),
+        };
+        assert_eq!(range.validate(), Ok(()));
+        assert!(range.contains(&at("2024-01-01T08:00:00Z").0));
+        assert!(range.contains(&at("2024-01-01T12:00:00Z").0));
+        assert!(!range.contains(&at("2024-01-01T16:00:01Z").0));
+
+        let reversed = DateTimeRange {
+            from: range.to.clone(),
+            to: range.from.clone(),
+        };
+        assert_eq!(reversed.validate(), Err(RangeError::FromAfterTo));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::auth::token::ResourceId;
+use async_graphql::{ErrorExtensions, FieldError};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+const RATE_LIMIT_BURST_ENV_VARIABLE: &str = "MUTATION_RATE_LIMIT_BURST";
+const RATE_LIMIT_PER_MINUTE_ENV_VARIABLE: &str = "MUTATION_RATE_LIMIT_PER_MINUTE";
+const DEFAULT_RATE_LIMIT_BURST: u32 = 120;
+const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 60;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum RateLimitError {
+    #[error("Too many time entries created, retry in {} seconds", .retry_after.as_secs())]
+    Exceeded { retry_after: Duration },
+}
+
+impl ErrorExtensions for RateLimitError {
+    fn extend(&self) -> FieldError {
+        self.extend_with(|err, e| match err {
+            RateLimitError::Exceeded { retry_after } => {
+                e.set("code", "RATE_LIMITED");
+                e.set("retry_after", retry_after.as_secs());
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Limits the number of time entries each resource can create, protecting the Teamdeck quota.
+///
+/// Every resource has a bucket of `MUTATION_RATE_LIMIT_BURST` tokens (120 by default),
+/// refilled with `MUTATION_RATE_LIMIT_PER_MINUTE` tokens per minute (60 by default).
+/// Creating a single entry takes one token.
+#[derive(Debug)]
+pub struct MutationRateLimiter {
+    burst: u32,
+    per_second: f64,
+    buckets: Mutex<HashMap<u64, Bucket>>,
+}
+
+fn env_or(name: &str, default: u32) -> u32 {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .ok()
+            .filter(|value| *value > 0)
+            .unwrap_or_else(|| panic!("Invalid {} env variable: {}", name, value)),
+        Err(_) => default,
+    }
+}
+
+impl MutationRateLimiter {
+    pub fn new(burst: u32, per_minute: u32) -> Self {
+        MutationRateLimiter {
+            burst,
+            per_second: per_minute as f64 / 60.0,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        MutationRateLimiter::new(
+            env_or(RATE_LIMIT_BURST_ENV_VARIABLE, DEFAULT_RATE_LIMIT_BURST),
+            env_or(
+                RATE_LIMIT_PER_MINUTE_ENV_VARIABLE,
+                DEFAULT_RATE_LIMIT_PER_MINUTE,
+            ),
+        )
+    }
+
+    /// Takes `cost` tokens from the resource's bucket, fails if there are not enough of them.
+    ///
+    /// Costs higher than the burst take the whole bucket, so large batches are not rejected forever.
+    pub fn check(&self, resource_id: ResourceId, cost: usize) -> Result<(), RateLimitError> {
+        self.check_at(resource_id, cost, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        resource_id: ResourceId,
+        cost: usize,
+        now: Instant,
+    ) -> Result<(), RateLimitError> {
+        let cost = cost.min(self.burst as usize) as f64;
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(resource_id.0).or_insert(Bucket {
+            tokens: self.burst as f64,
+            updated_at: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.per_second).min(self.burst as f64);
+        bucket.updated_at = now;
+
+        if bucket.tokens < cost {
+            let missing = cost - bucket.tokens;
+            return Err(RateLimitError::Exceeded {
+                retry_after: Duration::from_secs((missing / self.per_second).ceil() as u64),
+            });
+        }
+
+        bucket.tokens -= cost;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_burst_past_limit_is_rejected_until_refilled() {
+        let limiter = MutationRateLimiter::new(3, 60);
+        let start = Instant::now();
+        let resource = ResourceId(1);
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check_at(resource, 1, start), Ok(()));
+        }
+        assert_eq!(
+            limiter.check_at(resource, 1, start),
+            Err(RateLimitError::Exceeded {
+                retry_after: Duration::from_secs(1)
+            })
+        );
+
+        assert_eq!(
+            limiter.check_at(resource, 1, start + Duration::from_secs(1)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_resources_have_separate_buckets() {
+        let limiter = MutationRateLimiter::new(1, 60);
+        let now = Instant::now();
+
+        assert_eq!(limiter.check_at(ResourceId(1), 1, now), Ok(()));
+        assert!(limiter.check_at(ResourceId(1), 1, now).is_err());
+        assert_eq!(limiter.check_at(ResourceId(2), 1, now), Ok(()));
+    }
+
+    #[test]
+    fn test_batch_cost() {
+        let limiter = MutationRateLimiter::new(10, 60);
+        let now = Instant::now();
+        let resource = ResourceId(1);
+
+        assert_eq!(limiter.check_at(resource, 8, now), Ok(()));
+        assert_eq!(
+            limiter.check_at(resource, 5, now),
+            Err(RateLimitError::Exceeded {
+                retry_after: Duration::from_secs(3)
+            })
+        );
+        assert_eq!(limiter.check_at(resource, 2, now), Ok(()));
+    }
+
+    #[test]
+    fn test_cost_above_burst_takes_whole_bucket() {
+        let limiter = MutationRateLimiter::new(5, 60);
+        let now = Instant::now();
+
+        assert_eq!(limiter.check_at(ResourceId(1), 50, now), Ok(()));
+        assert!(limiter.check_at(ResourceId(1), 1, now).is_err());
+    }
+}
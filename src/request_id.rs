@@ -0,0 +1,100 @@
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Header carrying the ID correlating an incoming request with its calls to Teamdeck.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+static NEXT_REQUEST_NUMBER: AtomicU64 = AtomicU64::new(0);
+
+tokio::task_local! {
+    static CURRENT_REQUEST_ID: RequestId;
+}
+
+/// ID of the request being handled, taken from the `X-Request-Id` header or generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Generates an ID unique within the process, prefixed with its start time.
+    pub fn generate() -> Self {
+        let number = NEXT_REQUEST_NUMBER.fetch_add(1, Ordering::Relaxed);
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default();
+
+        RequestId(format!("{:x}-{:x}", millis, number))
+    }
+
+    /// Reuses the ID sent by the client, unless it's empty, too long or not printable ASCII.
+    pub fn from_header(value: Option<&str>) -> Self {
+        match value {
+            Some(value)
+                if !value.is_empty()
+                    && value.len() <= MAX_REQUEST_ID_LENGTH
+                    && value.chars().all(|c| c.is_ascii_graphic()) =>
+            {
+                RequestId(value.to_string())
+            }
+            _ => RequestId::generate(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Runs the future with this ID available through [`RequestId::current`].
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_REQUEST_ID.scope(self, future).await
+    }
+
+    /// Returns the ID of the request handled by the current task, if any.
+    pub fn current() -> Option<RequestId> {
+        CURRENT_REQUEST_ID.try_with(|id| id.clone()).ok()
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_header_value_is_reused() {
+        let id = RequestId::from_header(Some("abc-123"));
+        assert_eq!(id.as_str(), "abc-123");
+    }
+
+    #[test]
+    fn test_invalid_header_value_is_replaced() {
+        let long = "a".repeat(MAX_REQUEST_ID_LENGTH + 1);
+        for value in [None, Some(""), Some("with space"), Some(long.as_str())] {
+            let id = RequestId::from_header(value);
+            assert_ne!(Some(id.as_str()), value);
+            assert!(!id.as_str().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_generated_ids_are_unique() {
+        assert_ne!(RequestId::generate(), RequestId::generate());
+    }
+
+    #[actix_web::test]
+    async fn test_current_id_within_scope() {
+        assert_eq!(RequestId::current(), None);
+
+        let id = RequestId::from_header(Some("abc-123"));
+        let current = id.clone().scope(async { RequestId::current() }).await;
+        assert_eq!(current, Some(id));
+    }
+}
@@ -1,6 +1,19 @@
-use crate::auth::{guard::AccessTokenAuthGuard, token::ResourceId};
-use async_graphql::{Context, Object, Result, SimpleObject};
+use crate::auth::{
+    guard::{is_admin, AccessTokenAuthGuard, AuthError},
+    role::{Role, RoleMapping},
+    token::ResourceId,
+};
+use crate::degraded::with_stale_fallback;
+use crate::loader::ProjectLoader;
+use crate::project::ProjectModel;
+use crate::range::DateRange;
+use crate::teamdeck::api::{ListPageParams, PaginationInfo, TeamdeckApiClient};
+use crate::time_entry::{fetch_entries_in_range, TimeEntryModel};
+use crate::validation::ValidationError;
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{ComplexObject, Context, InputObject, Object, Result, ResultExt, SimpleObject};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use teamdeck::{
     api::{
         paged,
@@ -11,14 +24,115 @@ use teamdeck::{
     AsyncTeamdeck,
 };
 
+const SEARCH_RESULTS_LIMIT: usize = 25;
+
 #[derive(Serialize, Deserialize, SimpleObject, Debug, Clone)]
+#[graphql(complex)]
 pub struct ResourceModel {
     pub id: u64,
     name: String,
     active: bool,
     avatar: Option<String>,
     email: Option<String>,
-    role: Option<String>,
+    pub role: Option<String>,
+}
+
+#[ComplexObject]
+impl ResourceModel {
+    /// Role within the app, resolved from the Teamdeck `role`.
+    async fn app_role(&self, ctx: &Context<'_>) -> Role {
+        ctx.data_unchecked::<RoleMapping>()
+            .resolve(self.role.as_deref())
+    }
+
+    /// Minutes logged by the resource on entries starting within the date range.
+    ///
+    /// Available for the authorized user's own resource, or any resource for admins.
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn total_minutes(&self, ctx: &Context<'_>, range: DateRange) -> Result<u64> {
+        let entries = self.entries_in_range(ctx, &range).await?;
+        Ok(entries.iter().map(|e| e.minutes).sum())
+    }
+
+    /// Projects the resource logged time to on entries starting within the date range,
+    /// ordered by ID.
+    ///
+    /// Available for the authorized user's own resource, or any resource for admins.
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn projects_worked_on(
+        &self,
+        ctx: &Context<'_>,
+        range: DateRange,
+    ) -> Result<Vec<ProjectModel>> {
+        let entries = self.entries_in_range(ctx, &range).await?;
+        let project_ids = distinct_ids(entries.iter().map(|e| e.project_id));
+
+        let loader = ctx.data_unchecked::<DataLoader<ProjectLoader>>();
+        let mut projects = loader.load_many(project_ids.iter().copied()).await?;
+        Ok(project_ids
+            .iter()
+            .filter_map(|id| projects.remove(id))
+            .collect())
+    }
+}
+
+impl ResourceModel {
+    /// Fetches entries of the resource, if the authorized user can access them.
+    async fn entries_in_range(
+        &self,
+        ctx: &Context<'_>,
+        range: &DateRange,
+    ) -> Result<Vec<TimeEntryModel>> {
+        range
+            .validate()
+            .map_err(|e| ValidationError::new("range", e))
+            .extend()?;
+        let own_resource_id = ctx.data_unchecked::<ResourceId>().0;
+        if self.id != own_resource_id && !is_admin(ctx).await? {
+            return Err(AuthError::NotAnAdmin.into());
+        }
+
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+        fetch_entries_in_range(client, ResourceId(self.id), range).await
+    }
+}
+
+/// Returns the IDs without duplicates, in ascending order.
+pub(crate) fn distinct_ids(ids: impl Iterator<Item = u64>) -> Vec<u64> {
+    let mut ids: Vec<u64> = ids.collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+#[derive(InputObject, Default, Debug)]
+pub struct ResourceFilter {
+    /// Returns only active (`true`) or only inactive (`false`) resources.
+    ///
+    /// By default, all of the resources are returned.
+    pub active: Option<bool>,
+
+    /// Returns only resources with the given Teamdeck role (case-insensitive).
+    pub role: Option<String>,
+}
+
+impl ResourceFilter {
+    fn matches(&self, resource: &ResourceModel) -> bool {
+        self.active.map_or(true, |active| resource.active == active)
+            && self.role.as_ref().map_or(true, |role| {
+                resource
+                    .role
+                    .as_ref()
+                    .map_or(false, |r| r.eq_ignore_ascii_case(role))
+            })
+    }
+}
+
+/// Single page of resources.
+#[derive(SimpleObject, Debug)]
+pub struct ResourceConnection {
+    pub items: Vec<ResourceModel>,
+    pub pagination: PaginationInfo,
 }
 
 #[derive(Default, Debug)]
@@ -38,30 +152,216 @@ impl ResourceQuery {
         Ok(resource)
     }
 
+    /// Returns resources matching the filter, sorted by name.
+    ///
+    /// All of the resources are returned unless `page` or `perPage` is given.
+    /// The filter is applied after fetching the page,
+    /// so pagination details do not account for it.
     #[tracing::instrument(name = "Fetching all resources", skip(ctx))]
-    async fn resources(&self, ctx: &Context<'_>) -> Result<Vec<ResourceModel>> {
-        let client = ctx.data_unchecked::<AsyncTeamdeck>();
-        let endpoint = Resources::builder()
-            .sort(SortBy::Asc(ResourcesSortBy::Name))
-            .build()
-            .unwrap();
-        let resources = paged(endpoint, Pagination::All).query_async(client).await?;
-        Ok(resources)
+    async fn resources(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<ResourceFilter>,
+        page: Option<u64>,
+        per_page: Option<u64>,
+    ) -> Result<ResourceConnection> {
+        let filter = filter.unwrap_or_default();
+
+        let (mut items, pagination) = if page.is_some() || per_page.is_some() {
+            let client = ctx.data_unchecked::<TeamdeckApiClient>();
+            let params = ListPageParams::new("name".to_string(), page, per_page);
+            let page = client.resources_page(&params).await.extend()?;
+            (page.items, page.pagination)
+        } else {
+            let resources = fetch_all_resources(ctx).await?;
+            let pagination = PaginationInfo::single_page(resources.len() as u64);
+            (resources, pagination)
+        };
+        items.retain(|r| filter.matches(r));
+
+        Ok(ResourceConnection { items, pagination })
+    }
+
+    /// Returns resources whose name or email contains the query (case-insensitive), sorted by name.
+    ///
+    /// At most 25 resources are returned, none for an empty query.
+    #[tracing::instrument(name = "Searching resources", skip(ctx))]
+    async fn search_resources(
+        &self,
+        ctx: &Context<'_>,
+        query: String,
+    ) -> Result<Vec<ResourceModel>> {
+        if query.trim().is_empty() {
+            return Ok(vec![]);
+        }
+
+        let resources = fetch_all_resources(ctx).await?;
+        Ok(search(resources, &query))
     }
 
     #[tracing::instrument(name = "Fetching authorized user", skip(ctx))]
     #[graphql(guard = "AccessTokenAuthGuard::default()")]
     async fn me(&self, ctx: &Context<'_>) -> Result<Option<ResourceModel>> {
-        let resource_id = ctx.data_unchecked::<ResourceId>().0;
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
         let td = ctx.data_unchecked::<AsyncTeamdeck>();
 
-        let endpoint = Resource::builder()
-            .id(resource_id as usize)
-            .build()
-            .unwrap();
+        let key = format!("resource:{}", resource_id.0);
+        with_stale_fallback(ctx, &key, fetch_resource(td, resource_id.0)).await
+    }
+}
 
-        let resource = endpoint.query_async(td).await?;
+/// Fetches all of the resources sorted by name.
+async fn fetch_all_resources(ctx: &Context<'_>) -> Result<Vec<ResourceModel>> {
+    let client = ctx.data_unchecked::<AsyncTeamdeck>();
+    let endpoint = Resources::builder()
+        .sort(SortBy::Asc(ResourcesSortBy::Name))
+        .build()
+        .unwrap();
 
-        Ok(resource)
+    with_stale_fallback(ctx, "resources", async {
+        let resources = paged(endpoint, Pagination::All).query_async(client).await?;
+        Ok(resources)
+    })
+    .await
+}
+
+/// Returns up to `SEARCH_RESULTS_LIMIT` resources whose name or email contains the query.
+fn search(resources: Vec<ResourceModel>, query: &str) -> Vec<ResourceModel> {
+    let query = query.trim().to_lowercase();
+    let contains = |value: &str| value.to_lowercase().contains(&query);
+
+    resources
+        .into_iter()
+        .filter(|r| contains(&r.name) || r.email.as_deref().map_or(false, contains))
+        .take(SEARCH_RESULTS_LIMIT)
+        .collect()
+}
+
+pub(crate) async fn fetch_resource(
+    client: &AsyncTeamdeck,
+    resource_id: u64,
+) -> Result<Option<ResourceModel>> {
+    let endpoint = Resource::builder()
+        .id(resource_id as usize)
+        .build()
+        .unwrap();
+
+    let resource = endpoint.query_async(client).await?;
+
+    Ok(resource)
+}
+
+/// Fetches resources with the given IDs, keyed by their IDs.
+///
+/// Resources missing from the resources list are fetched one by one,
+/// resources which do not exist are left out.
+pub(crate) async fn fetch_resources_by_id(
+    client: &AsyncTeamdeck,
+    resource_ids: &[u64],
+) -> Result<HashMap<u64, ResourceModel>> {
+    let endpoint = Resources::builder().build().unwrap();
+    let resources: Vec<ResourceModel> =
+        paged(endpoint, Pagination::All).query_async(client).await?;
+
+    let mut resources: HashMap<u64, ResourceModel> = resources
+        .into_iter()
+        .filter(|r| resource_ids.contains(&r.id))
+        .map(|r| (r.id, r))
+        .collect();
+
+    for resource_id in resource_ids {
+        if !resources.contains_key(resource_id) {
+            if let Some(resource) = fetch_resource(client, *resource_id).await? {
+                resources.insert(resource.id, resource);
+            }
+        }
+    }
+
+    Ok(resources)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn resource(active: bool, role: Option<&str>) -> ResourceModel {
+        ResourceModel {
+            id: 1,
+            name: "Jane Doe".to_string(),
+            active,
+            avatar: None,
+            email: None,
+            role: role.map(str::to_string),
+        }
+    }
+
+    fn named(name: &str, email: Option<&str>) -> ResourceModel {
+        ResourceModel {
+            name: name.to_string(),
+            email: email.map(str::to_string),
+            ..resource(true, None)
+        }
+    }
+
+    #[test]
+    fn test_search_by_name_and_email() {
+        let resources = vec![
+            named("Jane Doe", Some("jane@example.com")),
+            named("John Smith", Some("jsmith@example.com")),
+            named("Mark Twain", None),
+        ];
+
+        let names = |found: Vec<ResourceModel>| -> Vec<String> {
+            found.into_iter().map(|r| r.name).collect()
+        };
+        assert_eq!(names(search(resources.clone(), "DOE")), vec!["Jane Doe"]);
+        assert_eq!(
+            names(search(resources.clone(), "jsmith")),
+            vec!["John Smith"]
+        );
+        assert_eq!(
+            names(search(resources, "example")),
+            vec!["Jane Doe", "John Smith"]
+        );
+    }
+
+    #[test]
+    fn test_distinct_ids() {
+        assert_eq!(distinct_ids(vec![3, 1, 3, 2, 1].into_iter()), vec![1, 2, 3]);
+        assert!(distinct_ids(std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn test_search_is_capped() {
+        let resources = vec![named("Jane Doe", None); 30];
+        assert_eq!(search(resources, "jane").len(), SEARCH_RESULTS_LIMIT);
+    }
+
+    #[test]
+    fn test_empty_filter_matches_all() {
+        let filter = ResourceFilter::default();
+        assert!(filter.matches(&resource(true, Some("Developer"))));
+        assert!(filter.matches(&resource(false, None)));
+    }
+
+    #[test]
+    fn test_active_filter() {
+        let filter = ResourceFilter {
+            active: Some(true),
+            ..Default::default()
+        };
+        assert!(filter.matches(&resource(true, None)));
+        assert!(!filter.matches(&resource(false, None)));
+    }
+
+    #[test]
+    fn test_role_filter() {
+        let filter = ResourceFilter {
+            role: Some("developer".to_string()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&resource(true, Some("Developer"))));
+        assert!(!filter.matches(&resource(true, Some("Manager"))));
+        assert!(!filter.matches(&resource(true, None)));
     }
 }
@@ -1,6 +1,9 @@
 use async_graphql::{InputValueError, InputValueResult, Scalar, ScalarType, Value};
-use chrono::{DateTime as ChronoDateTime, NaiveDate, NaiveTime, Utc};
+use chrono::{DateTime as ChronoDateTime, Duration as ChronoDuration, NaiveDate, NaiveTime, Utc};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
 
 /// DateTime RFC3339
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,14 +37,65 @@ impl Clone for DateTime {
 pub struct Date(pub NaiveDate);
 
 pub const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATE_FORMAT_LENGTH: usize = 10;
+const LENIENT_DATE_PARSING_ENV_VARIABLE: &str = "LENIENT_DATE_PARSING";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DateParseError {
+    #[error("expected date in YYYY-MM-DD format, found `{0}`")]
+    InvalidFormat(String),
+    #[error("expected date string in YYYY-MM-DD format, found {0}")]
+    NotAString(Value),
+}
+
+/// How strings are parsed into the `Date` scalar.
+///
+/// - `Strict` accepts only zero-padded `YYYY-MM-DD` dates.
+/// - `Lenient` additionally accepts single-digit months and days (`2024-1-5`)
+///   and datetimes, which are truncated to their date part (`2024-01-05T10:00:00Z`).
+///
+/// Lenient parsing is enabled by setting the `LENIENT_DATE_PARSING` env variable to `true`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateParsing {
+    Strict,
+    Lenient,
+}
+
+/// Parsing mode of the `Date` scalar, read from the env once, when the first date is parsed.
+static DATE_PARSING: Lazy<DateParsing> = Lazy::new(DateParsing::from_env);
+
+impl DateParsing {
+    fn from_env() -> Self {
+        match std::env::var(LENIENT_DATE_PARSING_ENV_VARIABLE) {
+            Ok(value) if value == "true" => DateParsing::Lenient,
+            _ => DateParsing::Strict,
+        }
+    }
+
+    fn parse(&self, value: &str) -> Result<NaiveDate, DateParseError> {
+        let date = match self {
+            // chrono accepts non-padded numbers, so the length has to be checked upfront
+            DateParsing::Strict if value.len() != DATE_FORMAT_LENGTH => {
+                return Err(DateParseError::InvalidFormat(value.to_string()));
+            }
+            DateParsing::Strict => value,
+            DateParsing::Lenient => value
+                .split(|c| c == 'T' || c == ' ')
+                .next()
+                .unwrap_or(value),
+        };
+
+        NaiveDate::parse_from_str(date, DATE_FORMAT)
+            .map_err(|_| DateParseError::InvalidFormat(value.to_string()))
+    }
+}
 
 #[Scalar]
 impl ScalarType for Date {
     fn parse(value: Value) -> InputValueResult<Self> {
-        if let Value::String(value) = &value {
-            Ok(Date(NaiveDate::parse_from_str(value, DATE_FORMAT)?))
-        } else {
-            Err(InputValueError::expected_type(value))
+        match value {
+            Value::String(value) => Ok(Date(DATE_PARSING.parse(&value)?)),
+            value => Err(DateParseError::NotAString(value).into()),
         }
     }
 
@@ -82,3 +136,227 @@ impl Clone for Time {
         Self(self.0)
     }
 }
+
+impl Time {
+    /// Returns the duration between this time and the `end` time of the same day.
+    ///
+    /// The duration is negative when `end` is before this time.
+    pub fn duration_to(&self, end: &Time) -> ChronoDuration {
+        end.0 - self.0
+    }
+}
+
+/// Duration in H:MM format, e.g. `1:05` or `12:30`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Duration(pub ChronoDuration);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DurationParseError {
+    #[error("expected duration in H:MM format, found `{0}`")]
+    InvalidFormat(String),
+}
+
+impl Duration {
+    pub fn from_minutes(minutes: u64) -> Self {
+        Duration(ChronoDuration::minutes(minutes as i64))
+    }
+
+    fn parse_str(value: &str) -> Result<Self, DurationParseError> {
+        let invalid = || DurationParseError::InvalidFormat(value.to_string());
+
+        let (hours, minutes) = value.split_once(':').ok_or_else(invalid)?;
+        let is_number = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        if !is_number(hours) || minutes.len() != 2 || !is_number(minutes) {
+            return Err(invalid());
+        }
+
+        let hours: i64 = hours.parse().map_err(|_| invalid())?;
+        let minutes: i64 = minutes.parse().map_err(|_| invalid())?;
+        if minutes >= 60 {
+            return Err(invalid());
+        }
+
+        Ok(Duration(ChronoDuration::minutes(hours * 60 + minutes)))
+    }
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total_minutes = self.0.num_minutes();
+        write!(f, "{}:{:02}", total_minutes / 60, total_minutes % 60)
+    }
+}
+
+#[Scalar]
+impl ScalarType for Duration {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        if let Value::String(value) = &value {
+            Ok(Duration::parse_str(value)?)
+        } else {
+            Err(InputValueError::expected_type(value))
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::String(self.to_string())
+    }
+}
+
+/// Arbitrary JSON value
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Json(pub serde_json::Value);
+
+#[Scalar]
+impl ScalarType for Json {
+    fn parse(value: Value) -> InputValueResult<Self> {
+        Ok(Json(value.into_json()?))
+    }
+
+    fn to_value(&self) -> Value {
+        Value::from_json(self.0.clone()).unwrap_or(Value::Null)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(json: serde_json::Value) {
+        let scalar = Json(json.clone());
+        let parsed = Json::parse(scalar.to_value()).unwrap();
+        assert_eq!(parsed, Json(json));
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, DATE_FORMAT).unwrap()
+    }
+
+    #[test]
+    fn test_strict_date_parsing() {
+        assert_eq!(
+            DateParsing::Strict.parse("2024-01-05"),
+            Ok(date("2024-01-05"))
+        );
+        assert!(DateParsing::Strict.parse("2024-1-5").is_err());
+        assert!(DateParsing::Strict.parse("2024-01-05T10:00:00Z").is_err());
+    }
+
+    #[test]
+    fn test_lenient_date_parsing_single_digits() {
+        assert_eq!(
+            DateParsing::Lenient.parse("2024-1-5"),
+            Ok(date("2024-01-05"))
+        );
+        assert_eq!(
+            DateParsing::Lenient.parse("2024-12-5"),
+            Ok(date("2024-12-05"))
+        );
+    }
+
+    #[test]
+    fn test_lenient_date_parsing_datetimes() {
+        assert_eq!(
+            DateParsing::Lenient.parse("2024-01-05T10:00:00Z"),
+            Ok(date("2024-01-05"))
+        );
+        assert_eq!(
+            DateParsing::Lenient.parse("2024-01-05 10:00:00"),
+            Ok(date("2024-01-05"))
+        );
+    }
+
+    #[test]
+    fn test_lenient_date_parsing_rejects_invalid_dates() {
+        assert!(DateParsing::Lenient.parse("2024-13-05").is_err());
+        assert!(DateParsing::Lenient.parse("05.01.2024").is_err());
+    }
+
+    fn date_error(value: Value) -> String {
+        Date::parse(value)
+            .unwrap_err()
+            .into_server_error(Default::default())
+            .message
+    }
+
+    #[test]
+    fn test_date_errors() {
+        assert_eq!(
+            date_error(Value::from("2023-13-40")),
+            r#"Failed to parse "Date": expected date in YYYY-MM-DD format, found `2023-13-40`"#
+        );
+        assert_eq!(
+            date_error(Value::from("")),
+            r#"Failed to parse "Date": expected date in YYYY-MM-DD format, found ``"#
+        );
+        assert_eq!(
+            date_error(Value::from(20230101)),
+            r#"Failed to parse "Date": expected date string in YYYY-MM-DD format, found 20230101"#
+        );
+    }
+
+    fn time(value: &str) -> Time {
+        Time(NaiveTime::parse_from_str(value, TIME_FORMAT).unwrap())
+    }
+
+    #[test]
+    fn test_time_duration_to() {
+        assert_eq!(
+            time("09:15").duration_to(&time("17:45")),
+            ChronoDuration::minutes(510)
+        );
+        assert_eq!(
+            time("12:00").duration_to(&time("11:30")),
+            ChronoDuration::minutes(-30)
+        );
+    }
+
+    #[test]
+    fn test_duration_to_value() {
+        assert_eq!(Duration::from_minutes(0).to_value(), Value::from("0:00"));
+        assert_eq!(Duration::from_minutes(65).to_value(), Value::from("1:05"));
+        assert_eq!(Duration::from_minutes(750).to_value(), Value::from("12:30"));
+    }
+
+    #[test]
+    fn test_duration_round_trip() {
+        for minutes in [0, 1, 59, 60, 61, 510, 1440, 6000] {
+            let duration = Duration::from_minutes(minutes);
+            assert_eq!(Duration::parse(duration.to_value()).unwrap(), duration);
+        }
+        assert_eq!(
+            Duration::parse(Value::from("08:30")).unwrap(),
+            Duration::from_minutes(510)
+        );
+    }
+
+    #[test]
+    fn test_invalid_duration() {
+        for value in ["", "1", "1:5", "1:60", ":30", "1:-5", "-1:30", "a:bc"] {
+            assert_eq!(
+                Duration::parse_str(value),
+                Err(DurationParseError::InvalidFormat(value.to_string()))
+            );
+        }
+        assert!(Duration::parse(Value::from(90)).is_err());
+    }
+
+    #[test]
+    fn test_json_object_round_trip() {
+        round_trip(json!({ "id": 1, "name": "Tracker", "nested": { "archived": false } }));
+    }
+
+    #[test]
+    fn test_json_array_round_trip() {
+        round_trip(json!([1, "two", { "three": 3 }, [4]]));
+    }
+
+    #[test]
+    fn test_json_scalars_round_trip() {
+        round_trip(json!(null));
+        round_trip(json!(true));
+        round_trip(json!(42));
+        round_trip(json!(-1.5));
+        round_trip(json!("text"));
+    }
+}
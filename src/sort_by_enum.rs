@@ -11,6 +11,19 @@ macro_rules! sort_by_enum {
                 )+
             }
 
+            impl $name {
+                /// Returns the value of the `sort` query parameter of the Teamdeck API.
+                #[allow(dead_code)]
+                pub fn to_query_param(self) -> String {
+                    match self {
+                        $(
+                            $name::[<$variant Asc>] => stringify!([<$variant:snake>]).to_string(),
+                            $name::[<$variant Desc>] => format!("-{}", stringify!([<$variant:snake>])),
+                        )+
+                    }
+                }
+            }
+
             impl From<$name> for teamdeck::api::sort_by::SortBy<$remote> {
                 fn from(val: $name) -> Self {
                     match val {
@@ -58,6 +71,12 @@ mod test {
         assert_eq!(sort_by, SortBy::Desc(ResourcesSortBy::Email));
     }
 
+    #[test]
+    fn test_query_param() {
+        assert_eq!(Test::NameAsc.to_query_param(), "name");
+        assert_eq!(Test::EmailDesc.to_query_param(), "-email");
+    }
+
     #[test]
     fn test_full_import() {
         sort_by_enum!(
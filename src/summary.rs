@@ -0,0 +1,662 @@
+use crate::auth::guard::AccessTokenAuthGuard;
+use crate::auth::token::ResourceId;
+use crate::project::{fetch_projects_by_id, ProjectModel};
+use crate::range::DateRange;
+use crate::scalars::Date;
+use crate::time_entry::{fetch_entries_in_range, format_minutes, TimeEntryModel};
+use crate::validation::ValidationError;
+use async_graphql::{ComplexObject, Context, Object, Result, ResultExt, SimpleObject};
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+use teamdeck::AsyncTeamdeck;
+
+#[derive(SimpleObject, Debug)]
+pub struct RangeTotal {
+    from: Date,
+    to: Date,
+    minutes: u64,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct TotalsComparison {
+    /// Totals of the baseline range.
+    range_a: RangeTotal,
+    /// Totals of the compared range.
+    range_b: RangeTotal,
+    /// Difference in minutes between `range_b` and `range_a`.
+    delta_minutes: i64,
+    /// Difference between `range_b` and `range_a` as a percentage of `range_a`.
+    ///
+    /// `null` when `range_a` has no logged minutes.
+    delta_percentage: Option<f64>,
+}
+
+impl TotalsComparison {
+    fn new(range_a: RangeTotal, range_b: RangeTotal) -> Self {
+        let delta_minutes = range_b.minutes as i64 - range_a.minutes as i64;
+        let delta_percentage = if range_a.minutes == 0 {
+            None
+        } else {
+            Some(delta_minutes as f64 / range_a.minutes as f64 * 100.0)
+        };
+
+        TotalsComparison {
+            range_a,
+            range_b,
+            delta_minutes,
+            delta_percentage,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, PartialEq, Eq)]
+pub struct ProjectTotal {
+    project_id: u64,
+    minutes: u64,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct WeekTotals {
+    /// ISO week number.
+    week: u32,
+    /// First day of the week within the month.
+    from: Date,
+    /// Last day of the week within the month.
+    to: Date,
+    minutes: u64,
+    projects: Vec<ProjectTotal>,
+}
+
+#[derive(SimpleObject, Debug)]
+#[graphql(complex)]
+pub struct ProjectMinutes {
+    project: ProjectModel,
+    minutes: u64,
+}
+
+#[ComplexObject]
+impl ProjectMinutes {
+    async fn formatted_duration(&self) -> String {
+        format_minutes(self.minutes)
+    }
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct DayMinutes {
+    date: Date,
+    minutes: u64,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct WeeklySummary {
+    total_minutes: u64,
+    /// Minutes logged to each project, ordered by project ID.
+    by_project: Vec<ProjectMinutes>,
+    /// Minutes logged on each day of the week, including days without entries.
+    by_day: Vec<DayMinutes>,
+}
+
+const DAYS_IN_WEEK: i64 = 7;
+
+const UNTAGGED_NAME: &str = "Untagged";
+
+#[derive(SimpleObject, Debug, PartialEq, Eq)]
+pub struct TagTotal {
+    /// ID of the tag, `null` for the untagged entries.
+    tag_id: Option<u64>,
+    name: String,
+    minutes: u64,
+}
+
+fn total_minutes(entries: &[TimeEntryModel]) -> u64 {
+    entries.iter().map(|e| e.minutes).sum()
+}
+
+/// Sums minutes of the entries by project, ordered by project ID.
+fn project_totals<'a>(entries: impl Iterator<Item = &'a TimeEntryModel>) -> Vec<ProjectTotal> {
+    let mut totals: BTreeMap<u64, u64> = BTreeMap::new();
+    for entry in entries {
+        *totals.entry(entry.project_id).or_default() += entry.minutes;
+    }
+
+    totals
+        .into_iter()
+        .map(|(project_id, minutes)| ProjectTotal {
+            project_id,
+            minutes,
+        })
+        .collect()
+}
+
+/// Sums minutes of the entries by tag, ordered by tag ID with the untagged entries last.
+///
+/// Entries with multiple tags contribute to the total of each of their tags.
+fn tag_totals(entries: &[TimeEntryModel]) -> Vec<TagTotal> {
+    let mut totals: BTreeMap<u64, TagTotal> = BTreeMap::new();
+    let mut untagged_minutes = 0;
+    for entry in entries {
+        match entry.tags.as_deref() {
+            Some(tags) if !tags.is_empty() => {
+                for tag in tags {
+                    totals
+                        .entry(tag.id)
+                        .or_insert_with(|| TagTotal {
+                            tag_id: Some(tag.id),
+                            name: tag.name.clone(),
+                            minutes: 0,
+                        })
+                        .minutes += entry.minutes;
+                }
+            }
+            _ => untagged_minutes += entry.minutes,
+        }
+    }
+
+    let mut totals: Vec<TagTotal> = totals.into_values().collect();
+    if untagged_minutes > 0 {
+        totals.push(TagTotal {
+            tag_id: None,
+            name: UNTAGGED_NAME.to_string(),
+            minutes: untagged_minutes,
+        });
+    }
+
+    totals
+}
+
+/// Returns the first and the last day of the month containing the date.
+pub(crate) fn month_bounds(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let first = date.with_day(1).unwrap();
+    let next_month = if first.month() == 12 {
+        NaiveDate::from_ymd_opt(first.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(first.year(), first.month() + 1, 1)
+    }
+    .unwrap();
+
+    (first, next_month - Duration::days(1))
+}
+
+/// Splits the date range into ISO weeks, clipping the first and the last week to the range.
+fn week_ranges(first: NaiveDate, last: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut ranges = vec![];
+    let mut from = first;
+    while from <= last {
+        let days_to_sunday = 6 - from.weekday().num_days_from_monday() as i64;
+        let to = std::cmp::min(from + Duration::days(days_to_sunday), last);
+        ranges.push((from, to));
+        from = to + Duration::days(1);
+    }
+
+    ranges
+}
+
+/// Sums minutes of the entries by start date for each day of the range, days without entries included.
+///
+/// Entries spanning multiple days are attributed entirely to their start date.
+fn daily_totals(from: NaiveDate, days: i64, entries: &[TimeEntryModel]) -> Vec<DayMinutes> {
+    (0..days)
+        .map(|day| {
+            let date = from + Duration::days(day);
+            DayMinutes {
+                date: Date(date),
+                minutes: entries
+                    .iter()
+                    .filter(|e| e.start_date.0 == date)
+                    .map(|e| e.minutes)
+                    .sum(),
+            }
+        })
+        .collect()
+}
+
+/// Pairs project totals with the projects, leaving out totals of unknown projects
+/// and projects without logged minutes.
+fn with_projects(
+    totals: Vec<ProjectTotal>,
+    projects: &HashMap<u64, ProjectModel>,
+) -> Vec<ProjectMinutes> {
+    totals
+        .into_iter()
+        .filter(|total| total.minutes > 0)
+        .filter_map(|total| {
+            Some(ProjectMinutes {
+                project: projects.get(&total.project_id)?.clone(),
+                minutes: total.minutes,
+            })
+        })
+        .collect()
+}
+
+fn weekly_breakdown(month: NaiveDate, entries: &[TimeEntryModel]) -> Vec<WeekTotals> {
+    let (first, last) = month_bounds(month);
+
+    week_ranges(first, last)
+        .into_iter()
+        .map(|(from, to)| {
+            let week_entries: Vec<&TimeEntryModel> = entries
+                .iter()
+                .filter(|e| e.start_date.0 >= from && e.start_date.0 <= to)
+                .collect();
+
+            WeekTotals {
+                week: from.iso_week().week(),
+                from: Date(from),
+                to: Date(to),
+                minutes: week_entries.iter().map(|e| e.minutes).sum(),
+                projects: project_totals(week_entries.into_iter()),
+            }
+        })
+        .collect()
+}
+
+#[derive(Default, Debug)]
+pub struct SummaryQuery;
+
+#[Object]
+impl SummaryQuery {
+    #[tracing::instrument(name = "Comparing totals of two date ranges", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn compare_totals(
+        &self,
+        ctx: &Context<'_>,
+        range_a: DateRange,
+        range_b: DateRange,
+    ) -> Result<TotalsComparison> {
+        range_a
+            .validate()
+            .map_err(|e| ValidationError::new("rangeA", e))
+            .extend()?;
+        range_b
+            .validate()
+            .map_err(|e| ValidationError::new("rangeB", e))
+            .extend()?;
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+
+        let entries_a = fetch_entries_in_range(client, resource_id, &range_a).await?;
+        let entries_b = fetch_entries_in_range(client, resource_id, &range_b).await?;
+
+        Ok(TotalsComparison::new(
+            RangeTotal {
+                from: range_a.from,
+                to: range_a.to,
+                minutes: total_minutes(&entries_a),
+            },
+            RangeTotal {
+                from: range_b.from,
+                to: range_b.to,
+                minutes: total_minutes(&entries_b),
+            },
+        ))
+    }
+
+    /// Returns minutes of the authorized user logged under each tag within the date range.
+    #[tracing::instrument(name = "Fetching tag totals", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn tag_totals(&self, ctx: &Context<'_>, date_range: DateRange) -> Result<Vec<TagTotal>> {
+        date_range
+            .validate()
+            .map_err(|e| ValidationError::new("dateRange", e))
+            .extend()?;
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+
+        let entries = fetch_entries_in_range(client, resource_id, &date_range).await?;
+
+        Ok(tag_totals(&entries))
+    }
+
+    /// Returns totals of the authorized user for each ISO week of the month containing `month` date.
+    ///
+    /// Weeks crossing the month boundaries are limited to the days of the month.
+    #[tracing::instrument(name = "Fetching weekly breakdown of a month", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn weekly_breakdown(&self, ctx: &Context<'_>, month: Date) -> Result<Vec<WeekTotals>> {
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+
+        let (first, last) = month_bounds(month.0);
+        let range = DateRange {
+            from: Date(first),
+            to: Date(last),
+        };
+        let entries = fetch_entries_in_range(client, resource_id, &range).await?;
+
+        Ok(weekly_breakdown(month.0, &entries))
+    }
+
+    /// Returns minutes of the authorized user logged to each project within the date range.
+    ///
+    /// Archived projects are included as long as time was logged to them.
+    #[tracing::instrument(name = "Fetching project totals", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn project_totals(
+        &self,
+        ctx: &Context<'_>,
+        date_range: DateRange,
+    ) -> Result<Vec<ProjectMinutes>> {
+        date_range
+            .validate()
+            .map_err(|e| ValidationError::new("dateRange", e))
+            .extend()?;
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+
+        let entries = fetch_entries_in_range(client, resource_id, &date_range).await?;
+        let totals = project_totals(entries.iter());
+        let project_ids: Vec<u64> = totals.iter().map(|t| t.project_id).collect();
+        let projects = fetch_projects_by_id(client, &project_ids).await?;
+
+        Ok(with_projects(totals, &projects))
+    }
+
+    /// Returns minutes of the authorized user logged on each day of the date range,
+    /// days without entries included.
+    ///
+    /// Entries spanning multiple days count entirely towards their start date.
+    #[tracing::instrument(name = "Fetching daily totals", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn daily_totals(&self, ctx: &Context<'_>, range: DateRange) -> Result<Vec<DayMinutes>> {
+        range
+            .validate()
+            .map_err(|e| ValidationError::new("range", e))
+            .extend()?;
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+
+        let entries = fetch_entries_in_range(client, resource_id, &range).await?;
+        let days = (range.to.0 - range.from.0).num_days() + 1;
+
+        Ok(daily_totals(range.from.0, days, &entries))
+    }
+
+    /// Returns totals of the authorized user for 7 days starting at `week_start`.
+    #[tracing::instrument(name = "Fetching weekly summary", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn weekly_summary(&self, ctx: &Context<'_>, week_start: Date) -> Result<WeeklySummary> {
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+
+        let range = DateRange {
+            from: week_start,
+            to: Date(week_start.0 + Duration::days(DAYS_IN_WEEK - 1)),
+        };
+        let entries = fetch_entries_in_range(client, resource_id, &range).await?;
+
+        let totals = project_totals(entries.iter());
+        let project_ids: Vec<u64> = totals.iter().map(|t| t.project_id).collect();
+        let projects = fetch_projects_by_id(client, &project_ids).await?;
+
+        Ok(WeeklySummary {
+            total_minutes: total_minutes(&entries),
+            by_project: with_projects(totals, &projects),
+            by_day: daily_totals(week_start.0, DAYS_IN_WEEK, &entries),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn range_total(minutes: u64) -> RangeTotal {
+        let date = Date(NaiveDate::from_ymd_opt(2022, 1, 1).unwrap());
+        RangeTotal {
+            from: date,
+            to: date,
+            minutes,
+        }
+    }
+
+    #[test]
+    fn test_compare_totals_increase() {
+        let comparison = TotalsComparison::new(range_total(120), range_total(180));
+        assert_eq!(comparison.delta_minutes, 60);
+        assert_eq!(comparison.delta_percentage, Some(50.0));
+    }
+
+    #[test]
+    fn test_compare_totals_decrease() {
+        let comparison = TotalsComparison::new(range_total(200), range_total(50));
+        assert_eq!(comparison.delta_minutes, -150);
+        assert_eq!(comparison.delta_percentage, Some(-75.0));
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn entry(project_id: u64, minutes: u64, start_date: &str) -> TimeEntryModel {
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "resource_id": 1,
+            "project_id": project_id,
+            "minutes": minutes,
+            "weekend_booking": false,
+            "holidays_booking": false,
+            "vacations_booking": false,
+            "description": null,
+            "external_id": null,
+            "start_date": start_date,
+            "end_date": start_date,
+            "creator_resource_id": 1,
+            "editor_resource_id": 1,
+            "tags": null,
+        }))
+        .unwrap()
+    }
+
+    fn tagged_entry(minutes: u64, tags: &[(u64, &str)]) -> TimeEntryModel {
+        let mut entry = entry(1, minutes, "2022-05-01");
+        entry.tags = Some(
+            tags.iter()
+                .map(|(id, name)| {
+                    serde_json::from_value(serde_json::json!({
+                        "id": id,
+                        "name": name,
+                        "icon": null,
+                        "color": null,
+                        "archived": 0,
+                    }))
+                    .unwrap()
+                })
+                .collect(),
+        );
+        entry
+    }
+
+    #[test]
+    fn test_tag_totals_multi_tag_contribution() {
+        let entries = vec![
+            tagged_entry(60, &[(1, "Development"), (2, "Meeting")]),
+            tagged_entry(30, &[(2, "Meeting")]),
+        ];
+
+        assert_eq!(
+            tag_totals(&entries),
+            vec![
+                TagTotal {
+                    tag_id: Some(1),
+                    name: "Development".to_string(),
+                    minutes: 60,
+                },
+                TagTotal {
+                    tag_id: Some(2),
+                    name: "Meeting".to_string(),
+                    minutes: 90,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tag_totals_untagged_bucket() {
+        let entries = vec![
+            tagged_entry(60, &[(1, "Development")]),
+            tagged_entry(15, &[]),
+            entry(1, 30, "2022-05-01"),
+        ];
+
+        assert_eq!(
+            tag_totals(&entries).last(),
+            Some(&TagTotal {
+                tag_id: None,
+                name: "Untagged".to_string(),
+                minutes: 45,
+            })
+        );
+    }
+
+    #[test]
+    fn test_month_bounds() {
+        assert_eq!(
+            month_bounds(date("2024-02-15")),
+            (date("2024-02-01"), date("2024-02-29"))
+        );
+        assert_eq!(
+            month_bounds(date("2022-12-31")),
+            (date("2022-12-01"), date("2022-12-31"))
+        );
+    }
+
+    #[test]
+    fn test_weekly_breakdown_partial_weeks() {
+        // May 2022 starts on Sunday and ends on Tuesday
+        let weeks = weekly_breakdown(date("2022-05-10"), &[]);
+        let ranges: Vec<(NaiveDate, NaiveDate, u32)> =
+            weeks.iter().map(|w| (w.from.0, w.to.0, w.week)).collect();
+
+        assert_eq!(
+            ranges,
+            vec![
+                (date("2022-05-01"), date("2022-05-01"), 17),
+                (date("2022-05-02"), date("2022-05-08"), 18),
+                (date("2022-05-09"), date("2022-05-15"), 19),
+                (date("2022-05-16"), date("2022-05-22"), 20),
+                (date("2022-05-23"), date("2022-05-29"), 21),
+                (date("2022-05-30"), date("2022-05-31"), 22),
+            ]
+        );
+        assert!(weeks
+            .iter()
+            .all(|w| w.minutes == 0 && w.projects.is_empty()));
+    }
+
+    #[test]
+    fn test_weekly_breakdown_project_totals() {
+        let entries = vec![
+            entry(1, 60, "2022-05-01"),
+            entry(2, 30, "2022-05-02"),
+            entry(1, 45, "2022-05-08"),
+            entry(2, 15, "2022-05-08"),
+            entry(1, 120, "2022-05-31"),
+        ];
+        let weeks = weekly_breakdown(date("2022-05-01"), &entries);
+
+        assert_eq!(weeks[0].minutes, 60);
+        assert_eq!(
+            weeks[1].projects,
+            vec![
+                ProjectTotal {
+                    project_id: 1,
+                    minutes: 45
+                },
+                ProjectTotal {
+                    project_id: 2,
+                    minutes: 45
+                },
+            ]
+        );
+        assert_eq!(weeks[2].minutes, 0);
+        assert_eq!(weeks[5].minutes, 120);
+    }
+
+    fn project(id: u64, archived: bool) -> ProjectModel {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": "Tracker",
+            "color": "#000000",
+            "archived": archived,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_with_projects_skips_empty_totals() {
+        let entries = vec![
+            entry(1, 60, "2022-05-02"),
+            entry(2, 0, "2022-05-02"),
+            entry(3, 30, "2022-05-03"),
+        ];
+        let projects: HashMap<u64, ProjectModel> =
+            vec![project(1, false), project(2, false), project(3, true)]
+                .into_iter()
+                .map(|p| (p.id, p))
+                .collect();
+
+        let totals: Vec<(u64, u64)> = with_projects(project_totals(entries.iter()), &projects)
+            .iter()
+            .map(|t| (t.project.id, t.minutes))
+            .collect();
+        assert_eq!(totals, vec![(1, 60), (3, 30)]);
+    }
+
+    #[test]
+    fn test_daily_totals_empty_week() {
+        let days = daily_totals(date("2022-05-02"), 7, &[]);
+        let days: Vec<(NaiveDate, u64)> = days.iter().map(|d| (d.date.0, d.minutes)).collect();
+
+        assert_eq!(days.len(), 7);
+        assert_eq!(days[0], (date("2022-05-02"), 0));
+        assert_eq!(days[6], (date("2022-05-08"), 0));
+        assert!(days.iter().all(|(_, minutes)| *minutes == 0));
+    }
+
+    #[test]
+    fn test_daily_totals() {
+        let entries = vec![
+            entry(1, 60, "2022-05-02"),
+            entry(2, 30, "2022-05-02"),
+            entry(1, 45, "2022-05-04"),
+        ];
+        let minutes: Vec<u64> = daily_totals(date("2022-05-02"), 7, &entries)
+            .iter()
+            .map(|d| d.minutes)
+            .collect();
+
+        assert_eq!(minutes, vec![90, 0, 45, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_daily_totals_with_gaps_and_multi_day_entries() {
+        let mut multi_day = entry(1, 120, "2022-05-05");
+        multi_day.end_date = Date(date("2022-05-06"));
+        let entries = vec![
+            entry(1, 60, "2022-05-01"),
+            entry(2, 15, "2022-05-01"),
+            multi_day,
+            entry(2, 30, "2022-05-09"),
+        ];
+        let days: Vec<(NaiveDate, u64)> = daily_totals(date("2022-05-01"), 10, &entries)
+            .iter()
+            .map(|d| (d.date.0, d.minutes))
+            .collect();
+
+        assert_eq!(days.len(), 10);
+        assert_eq!(days[0], (date("2022-05-01"), 75));
+        assert_eq!(days[4], (date("2022-05-05"), 120));
+        assert_eq!(days[5], (date("2022-05-06"), 0));
+        assert_eq!(days[8], (date("2022-05-09"), 30));
+        assert_eq!(days[9], (date("2022-05-10"), 0));
+        assert_eq!(days.iter().map(|(_, minutes)| minutes).sum::<u64>(), 225);
+    }
+
+    #[test]
+    fn test_compare_totals_zero_baseline() {
+        let comparison = TotalsComparison::new(range_total(0), range_total(90));
+        assert_eq!(comparison.delta_minutes, 90);
+        assert_eq!(comparison.delta_percentage, None);
+    }
+}
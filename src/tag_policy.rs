@@ -0,0 +1,141 @@
+use crate::validation::ValidationError;
+use async_graphql::{ErrorExtensions, FieldError};
+use thiserror::Error;
+
+const TAG_POLICY_ENV_VARIABLE: &str = "TIME_ENTRY_TAG_POLICY";
+
+/// Policy deciding how `tag_ids` passed to time entry mutations are handled.
+///
+/// The same rules are applied when creating and updating time entries:
+///
+/// - `Required` - every time entry must have at least one tag. Passing an empty list is an error,
+///   as is creating a time entry without `tag_ids`. Omitting `tag_ids` on update keeps existing tags.
+/// - `Optional` - tags can be omitted or passed as an empty list, which clears them on update.
+/// - `Ignored` - `tag_ids` are never sent to Teamdeck, existing tags are left untouched.
+///
+/// Configured with the `TIME_ENTRY_TAG_POLICY` env variable (`required`, `optional` or `ignored`),
+/// defaults to `required`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagPolicy {
+    Required,
+    Optional,
+    Ignored,
+}
+
+impl Default for TagPolicy {
+    fn default() -> Self {
+        TagPolicy::Required
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagOperation {
+    Create,
+    Update,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TagPolicyError {
+    #[error("At least one tag is required for the time entry")]
+    TagsRequired,
+}
+
+impl ErrorExtensions for TagPolicyError {
+    fn extend(&self) -> FieldError {
+        ValidationError::new("tagIds", self).extend()
+    }
+}
+
+impl TagPolicy {
+    pub fn from_env() -> Self {
+        match std::env::var(TAG_POLICY_ENV_VARIABLE) {
+            Ok(value) => Self::parse(&value).unwrap_or_else(|| {
+                panic!(
+                    "Invalid {} env variable: {}",
+                    TAG_POLICY_ENV_VARIABLE, value
+                )
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "required" => Some(TagPolicy::Required),
+            "optional" => Some(TagPolicy::Optional),
+            "ignored" => Some(TagPolicy::Ignored),
+            _ => None,
+        }
+    }
+
+    /// Returns tags which should be assigned to the time entry,
+    /// or `None` when tags of the entry should not be modified.
+    pub fn resolve(
+        &self,
+        tag_ids: Option<Vec<u64>>,
+        operation: TagOperation,
+    ) -> Result<Option<Vec<u64>>, TagPolicyError> {
+        match (self, tag_ids) {
+            (TagPolicy::Ignored, _) => Ok(None),
+            (TagPolicy::Required, Some(tags)) if tags.is_empty() => {
+                Err(TagPolicyError::TagsRequired)
+            }
+            (TagPolicy::Required, None) if operation == TagOperation::Create => {
+                Err(TagPolicyError::TagsRequired)
+            }
+            (_, tags) => Ok(tags),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OPERATIONS: [TagOperation; 2] = [TagOperation::Create, TagOperation::Update];
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(TagPolicy::parse("required"), Some(TagPolicy::Required));
+        assert_eq!(TagPolicy::parse("Optional"), Some(TagPolicy::Optional));
+        assert_eq!(TagPolicy::parse("IGNORED"), Some(TagPolicy::Ignored));
+        assert_eq!(TagPolicy::parse("sometimes"), None);
+    }
+
+    #[test]
+    fn test_required_policy() {
+        let policy = TagPolicy::Required;
+        for operation in OPERATIONS {
+            assert_eq!(policy.resolve(Some(vec![1]), operation), Ok(Some(vec![1])));
+            assert_eq!(
+                policy.resolve(Some(vec![]), operation),
+                Err(TagPolicyError::TagsRequired)
+            );
+        }
+        assert_eq!(
+            policy.resolve(None, TagOperation::Create),
+            Err(TagPolicyError::TagsRequired)
+        );
+        assert_eq!(policy.resolve(None, TagOperation::Update), Ok(None));
+    }
+
+    #[test]
+    fn test_optional_policy() {
+        let policy = TagPolicy::Optional;
+        for operation in OPERATIONS {
+            assert_eq!(policy.resolve(Some(vec![1]), operation), Ok(Some(vec![1])));
+            assert_eq!(policy.resolve(Some(vec![]), operation), Ok(Some(vec![])));
+            assert_eq!(policy.resolve(None, operation), Ok(None));
+        }
+    }
+
+    #[test]
+    fn test_ignored_policy() {
+        let policy = TagPolicy::Ignored;
+        for operation in OPERATIONS {
+            assert_eq!(policy.resolve(Some(vec![1]), operation), Ok(None));
+            assert_eq!(policy.resolve(Some(vec![]), operation), Ok(None));
+            assert_eq!(policy.resolve(None, operation), Ok(None));
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use crate::time_entry_tag::fetch_time_entry_tags;
+use crate::validation::ValidationError;
+use async_graphql::{ErrorExtensions, FieldError, Result, ResultExt};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use teamdeck::AsyncTeamdeck;
+use thiserror::Error;
+
+const VALIDATE_TAG_IDS_ENV_VARIABLE: &str = "VALIDATE_TAG_IDS";
+const KNOWN_TAGS_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum UnknownTagsError {
+    #[error("Unknown tag IDs: {}", .0.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", "))]
+    Unknown(Vec<u64>),
+}
+
+impl ErrorExtensions for UnknownTagsError {
+    fn extend(&self) -> FieldError {
+        ValidationError::new("tagIds", self).extend()
+    }
+}
+
+/// Ensures `tag_ids` of time entry mutations reference existing tags, before the entry is saved.
+///
+/// IDs of existing tags are cached for a few minutes, and fetched again when an unknown ID
+/// is found in the cached ones, so recently added tags are accepted.
+/// Disabled by setting the `VALIDATE_TAG_IDS` env variable to `false`.
+#[derive(Debug)]
+pub struct TagValidator {
+    enabled: bool,
+    known: Mutex<Option<(Instant, Arc<HashSet<u64>>)>>,
+}
+
+impl Default for TagValidator {
+    fn default() -> Self {
+        TagValidator::new(true)
+    }
+}
+
+impl TagValidator {
+    pub fn new(enabled: bool) -> Self {
+        TagValidator {
+            enabled,
+            known: Mutex::new(None),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        match std::env::var(VALIDATE_TAG_IDS_ENV_VARIABLE) {
+            Ok(value) => TagValidator::new(value != "false"),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub async fn check(&self, client: &AsyncTeamdeck, tag_ids: &[u64]) -> Result<()> {
+        if !self.enabled || tag_ids.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(known) = self.cached() {
+            if unknown_tags(&known, tag_ids).is_ok() {
+                return Ok(());
+            }
+        }
+
+        let known: HashSet<u64> = fetch_time_entry_tags(client)
+            .await?
+            .into_iter()
+            .map(|tag| tag.id)
+            .collect();
+        let known = Arc::new(known);
+        *self.known.lock().unwrap() = Some((Instant::now(), known.clone()));
+
+        unknown_tags(&known, tag_ids).extend()
+    }
+
+    fn cached(&self) -> Option<Arc<HashSet<u64>>> {
+        match &*self.known.lock().unwrap() {
+            Some((fetched_at, known)) if fetched_at.elapsed() < KNOWN_TAGS_TTL => {
+                Some(known.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Fails with the sorted IDs of the tags which are not known.
+fn unknown_tags(known: &HashSet<u64>, tag_ids: &[u64]) -> Result<(), UnknownTagsError> {
+    let mut unknown: Vec<u64> = tag_ids
+        .iter()
+        .filter(|id| !known.contains(id))
+        .copied()
+        .collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+
+    unknown.sort_unstable();
+    unknown.dedup();
+    Err(UnknownTagsError::Unknown(unknown))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_known_tags() {
+        let known: HashSet<u64> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(unknown_tags(&known, &[1, 3]), Ok(()));
+    }
+
+    #[test]
+    fn test_unknown_tags_are_listed() {
+        let known: HashSet<u64> = vec![1, 2].into_iter().collect();
+        let error = unknown_tags(&known, &[9, 1, 4, 9]).unwrap_err();
+
+        assert_eq!(error, UnknownTagsError::Unknown(vec![4, 9]));
+        assert_eq!(error.to_string(), "Unknown tag IDs: 4, 9");
+    }
+
+    #[actix_web::test]
+    async fn test_disabled_validator_accepts_any_tags() {
+        let validator = TagValidator::new(false);
+        let client = AsyncTeamdeck::new("unused".to_string());
+
+        assert!(validator.check(&client, &[404]).await.is_ok());
+    }
+}
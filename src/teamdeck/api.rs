@@ -1,17 +1,33 @@
-use crate::scalars::Date;
+use crate::project::ProjectModel;
+use crate::request_id::{RequestId, REQUEST_ID_HEADER};
+use crate::resource::ResourceModel;
 use crate::teamdeck::error::TeamdeckApiError;
-use crate::time_entry::{CreateTimeEntryInput, TimeEntryModel};
-use chrono::{NaiveDate, Utc};
+use crate::teamdeck::retry::RetryPolicy;
+use crate::time_entry::{CreateTimeEntryInput, TimeEntryFilter, TimeEntryModel};
+use crate::time_entry_tag::TimeEntryTagModel;
+use async_graphql::SimpleObject;
+use chrono::NaiveDate;
 use reqwest;
+use reqwest::header::HeaderMap;
 use reqwest::IntoUrl;
+use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fmt::Debug;
+use std::time::Duration;
 
 const API_KEY_ENV_VARIABLE: &str = "TEAMDECK_API_KEY";
 const API_KEY_HEADER_NAME: &str = "X-Api-Key";
+const BASE_URL_ENV_VARIABLE: &str = "TEAMDECK_API_URL";
+const DEFAULT_BASE_URL: &str = "https://api.teamdeck.io/v1";
+const TIMEOUT_SECONDS_ENV_VARIABLE: &str = "TEAMDECK_TIMEOUT_SECONDS";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const TIME_ENTRY_RESOURCE_TYPE: &str = "time entry";
 
 pub struct TeamdeckApiClient {
+    client: reqwest::Client,
     api_key: String,
+    base_url: String,
+    retry_policy: RetryPolicy,
 }
 
 impl Default for TeamdeckApiClient {
@@ -20,7 +36,12 @@ impl Default for TeamdeckApiClient {
     }
 }
 
-#[derive(Debug)]
+const TOTAL_COUNT_HEADER_NAME: &str = "X-Pagination-Total-Count";
+const PAGES_COUNT_HEADER_NAME: &str = "X-Pagination-Page-Count";
+const CURRENT_PAGE_HEADER_NAME: &str = "X-Pagination-Current-Page";
+const ITEMS_PER_PAGE_HEADER_NAME: &str = "X-Pagination-Per-Page";
+
+#[derive(Debug, SimpleObject)]
 pub struct PaginationInfo {
     pub total_count: u64,
     pub pages_count: u64,
@@ -28,6 +49,27 @@ pub struct PaginationInfo {
     pub items_per_page: u64,
 }
 
+impl PaginationInfo {
+    /// Reads pagination details from the headers of a Teamdeck response.
+    ///
+    /// Missing headers fall back to a single page containing all of the received items.
+    fn from_headers(headers: &HeaderMap, requested_page: u64, items_count: u64) -> Self {
+        let header = |name: &str| -> Option<u64> { headers.get(name)?.to_str().ok()?.parse().ok() };
+
+        PaginationInfo {
+            total_count: header(TOTAL_COUNT_HEADER_NAME).unwrap_or(items_count),
+            pages_count: header(PAGES_COUNT_HEADER_NAME).unwrap_or(1),
+            current_page: header(CURRENT_PAGE_HEADER_NAME).unwrap_or(requested_page),
+            items_per_page: header(ITEMS_PER_PAGE_HEADER_NAME).unwrap_or(items_count),
+        }
+    }
+
+    /// Pagination details of a response containing all of the items at once.
+    pub fn single_page(items_count: u64) -> Self {
+        Self::from_headers(&HeaderMap::new(), 0, items_count)
+    }
+}
+
 #[derive(Debug)]
 pub struct Page<S: Serialize> {
     pub items: Vec<S>,
@@ -48,6 +90,8 @@ pub struct CreateTimeEntryBody {
     pub creator_resource_id: u64,
     pub editor_resource_id: u64,
     pub tags: Vec<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -61,40 +105,250 @@ pub struct UpdateTimeEntryBody {
     pub start_date: NaiveDate,
     pub end_date: NaiveDate,
     pub editor_resource_id: u64,
+    /// Tags to set on the time entry.
+    ///
+    /// When `None`, the field is omitted from the request body,
+    /// so the tags already assigned to the entry are left intact.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<u64>>,
+    /// ID of the entry in an external system.
+    ///
+    /// When `None`, the field is omitted and the current ID is kept,
+    /// while `Some(None)` is sent as `null`, clearing it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<Option<String>>,
+}
+
+/// Parses IDs of the assigned tags from the body of the tags update response.
+fn parse_assigned_tags(body: &str, tag_ids: Vec<u64>) -> Result<Vec<u64>, TeamdeckApiError> {
+    if body.trim().is_empty() {
+        return Ok(tag_ids);
+    }
+
+    serde_json::from_str(body).map_err(|e| TeamdeckApiError::UnexpectedResponse {
+        reason: e.to_string(),
+        body: body.to_string(),
+    })
+}
+
+/// Query parameters selecting a single page of time entries.
+#[derive(Debug, Serialize)]
+pub struct TimeEntriesPageParams {
+    resource_id: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    external_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_date_from: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_date_to: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_date_from: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_date_to: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort: Option<String>,
+    expand: &'static str,
+    page: u64,
+}
+
+impl TimeEntriesPageParams {
+    pub fn from_filter(filter: &TimeEntryFilter, resource_id: u64) -> Self {
+        let join = |values: Vec<String>| values.join(",");
+
+        TimeEntriesPageParams {
+            resource_id,
+            project_id: filter
+                .project_id
+                .as_ref()
+                .map(|ids| join(ids.iter().map(|id| id.to_string()).collect())),
+            external_id: filter.external_id.clone().map(join),
+            start_date_from: filter.start_date.as_ref().map(|range| range.from.0),
+            start_date_to: filter.start_date.as_ref().map(|range| range.to.0),
+            end_date_from: filter.end_date.as_ref().map(|range| range.from.0),
+            end_date_to: filter.end_date.as_ref().map(|range| range.to.0),
+            date: filter.date.map(|date| date.0),
+            sort: filter.sort.map(|sort| sort.to_query_param()),
+            expand: "tags",
+            page: filter.page.unwrap_or(0),
+        }
+    }
+}
+
+/// Query parameters selecting a single page of a sorted list (e.g. projects or resources).
+#[derive(Debug, Serialize)]
+pub struct ListPageParams {
+    sort: String,
+    page: u64,
+    #[serde(rename = "per-page", skip_serializing_if = "Option::is_none")]
+    per_page: Option<u64>,
+}
+
+impl ListPageParams {
+    pub fn new(sort: String, page: Option<u64>, per_page: Option<u64>) -> Self {
+        ListPageParams {
+            sort,
+            page: page.unwrap_or(0),
+            per_page,
+        }
+    }
 }
 
 impl CreateTimeEntryBody {
-    pub fn from_graphql_input(input: &CreateTimeEntryInput, resource_id: u64) -> Self {
-        let date = input
-            .date
-            .unwrap_or_else(|| Date(Utc::now().date_naive()))
-            .0;
+    pub fn from_graphql_input(
+        input: &CreateTimeEntryInput,
+        resource_id: u64,
+        (start_date, end_date): (NaiveDate, NaiveDate),
+        minutes: u64,
+        tags: Vec<u64>,
+    ) -> Self {
         CreateTimeEntryBody {
             resource_id,
             project_id: input.project_id,
-            minutes: input.minutes.unwrap_or(1),
+            minutes,
             weekend_booking: input.weekend_booking,
             holidays_booking: input.holidays_booking,
             vacations_booking: input.vacations_booking,
             description: input.description.clone(),
-            start_date: date,
-            end_date: date,
+            start_date,
+            end_date,
             creator_resource_id: resource_id,
             editor_resource_id: resource_id,
-            tags: input.tag_ids.clone(),
+            tags,
+            external_id: input.external_id.clone(),
         }
     }
 }
 
+/// Builds the HTTP client shared by all requests, failing both connecting and whole requests
+/// taking longer than the timeout.
+fn http_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(timeout)
+        .timeout(timeout)
+        .build()
+        .expect("Could not build HTTP client")
+}
+
 impl TeamdeckApiClient {
     fn from_env() -> Self {
+        let timeout = match std::env::var(TIMEOUT_SECONDS_ENV_VARIABLE) {
+            Ok(value) => Duration::from_secs(value.parse().unwrap_or_else(|_| {
+                panic!(
+                    "Invalid {} env variable: {}",
+                    TIMEOUT_SECONDS_ENV_VARIABLE, value
+                )
+            })),
+            Err(_) => DEFAULT_TIMEOUT,
+        };
+
         Self {
+            client: http_client(timeout),
             api_key: std::env::var(API_KEY_ENV_VARIABLE)
                 .unwrap_or_else(|_| panic!("Missing {} env variable", API_KEY_ENV_VARIABLE)),
+            base_url: std::env::var(BASE_URL_ENV_VARIABLE)
+                .unwrap_or_else(|_| DEFAULT_BASE_URL.to_string()),
+            retry_policy: RetryPolicy::from_env(),
+        }
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// Returns the URL of the endpoint with the given path, relative to the base URL.
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Sends the request, turning unsuccessful responses into errors.
+    async fn send_checked(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, TeamdeckApiError> {
+        TeamdeckApiError::check(self.send(request).await?).await
+    }
+
+    /// Sends the request, retrying it according to the retry policy.
+    ///
+    /// The response of the last attempt is returned, whatever its status is.
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, TeamdeckApiError> {
+        let client = &self.client;
+        let request = match RequestId::current() {
+            Some(request_id) => request.header(REQUEST_ID_HEADER, request_id.as_str()),
+            None => request,
+        };
+        let request = request.build()?;
+        let policy = self.retry_policy();
+        let can_retry = policy.allows(request.method());
+
+        let mut attempt = 1;
+        loop {
+            let attempt_request = match request.try_clone() {
+                Some(attempt_request) if can_retry && attempt < policy.max_attempts => {
+                    attempt_request
+                }
+                _ => return Ok(client.execute(request).await?),
+            };
+
+            let response = client.execute(attempt_request).await?;
+            if !RetryPolicy::is_retryable(response.status()) {
+                return Ok(response);
+            }
+
+            let delay = policy.delay(attempt, response.headers());
+            tracing::warn!(
+                "Teamdeck responded with {} to attempt {} of {} {}, retrying in {:?}",
+                response.status(),
+                attempt,
+                request.method(),
+                request.url(),
+                delay
+            );
+            actix_web::rt::time::sleep(delay).await;
+            attempt += 1;
         }
     }
 
+    #[tracing::instrument(name = "Fetch time entry by ID", skip(self), err)]
+    pub async fn get_time_entry(
+        &self,
+        time_entry_id: u64,
+    ) -> Result<TimeEntryModel, TeamdeckApiError> {
+        let time_entry = self
+            .send_checked(self.get(self.url(&format!("time-entries/{}", time_entry_id))))
+            .await
+            .map_err(|e| e.for_resource(TIME_ENTRY_RESOURCE_TYPE, time_entry_id))?
+            .json()
+            .await?;
+
+        Ok(time_entry)
+    }
+
+    #[tracing::instrument(name = "Fetch tags of time entry", skip(self), err)]
+    pub async fn get_time_entry_tags(
+        &self,
+        time_entry_id: u64,
+    ) -> Result<Vec<TimeEntryTagModel>, TeamdeckApiError> {
+        let time_entry: TimeEntryModel = self
+            .send_checked(
+                self.get(self.url(&format!("time-entries/{}", time_entry_id)))
+                    .query(&[("expand", "tags")]),
+            )
+            .await
+            .map_err(|e| e.for_resource(TIME_ENTRY_RESOURCE_TYPE, time_entry_id))?
+            .json()
+            .await?;
+
+        Ok(time_entry.tags.unwrap_or_default())
+    }
+
     #[tracing::instrument(name = "Update time entry by ID", skip(self), err)]
     pub async fn update_time_entry(
         &self,
@@ -102,36 +356,113 @@ impl TeamdeckApiClient {
         body: &UpdateTimeEntryBody,
     ) -> Result<TimeEntryModel, TeamdeckApiError> {
         let updated_entry = self
-            .put(format!(
-                "https://api.teamdeck.io/v1/time-entries/{}",
-                time_entry_id
-            ))
-            .json(body)
-            .send()
-            .await?
+            .send_checked(
+                self.put(self.url(&format!("time-entries/{}", time_entry_id)))
+                    .json(body),
+            )
+            .await
+            .map_err(|e| e.for_resource(TIME_ENTRY_RESOURCE_TYPE, time_entry_id))?
             .json()
             .await?;
 
         Ok(updated_entry)
     }
 
+    /// Replaces tags of the time entry, returning IDs of the tags assigned to it.
+    ///
+    /// Empty responses are taken as confirmation that exactly the given tags were assigned.
     #[tracing::instrument(name = "Update time entry tags", skip(self), err)]
     pub async fn update_time_entry_tags(
         &self,
         time_entry_id: u64,
         tag_ids: Vec<u64>,
     ) -> Result<Vec<u64>, TeamdeckApiError> {
-        let tags = self
-            .put(format!(
-                "https://api.teamdeck.io/v1/time-entries/{time_entry_id}/tags"
-            ))
-            .json(&tag_ids)
-            .send()
-            .await?
-            .json()
+        let body = self
+            .send_checked(
+                self.put(self.url(&format!("time-entries/{time_entry_id}/tags")))
+                    .json(&tag_ids),
+            )
+            .await
+            .map_err(|e| e.for_resource(TIME_ENTRY_RESOURCE_TYPE, time_entry_id))?
+            .text()
             .await?;
 
-        Ok(tags)
+        parse_assigned_tags(&body, tag_ids)
+    }
+
+    /// Removes all tags from the time entry, returning the updated entry.
+    #[tracing::instrument(name = "Clear time entry tags", skip(self), err)]
+    pub async fn clear_time_entry_tags(
+        &self,
+        time_entry_id: u64,
+    ) -> Result<TimeEntryModel, TeamdeckApiError> {
+        self.update_time_entry_tags(time_entry_id, vec![]).await?;
+        self.get_time_entry(time_entry_id).await
+    }
+
+    #[tracing::instrument(name = "Fetch page of time entries", skip(self), err)]
+    pub async fn time_entries_page(
+        &self,
+        params: &TimeEntriesPageParams,
+    ) -> Result<Page<TimeEntryModel>, TeamdeckApiError> {
+        self.get_page(&self.url("time-entries"), params, params.page)
+            .await
+    }
+
+    #[tracing::instrument(name = "Fetch page of projects", skip(self), err)]
+    pub async fn projects_page(
+        &self,
+        params: &ListPageParams,
+    ) -> Result<Page<ProjectModel>, TeamdeckApiError> {
+        self.get_page(&self.url("projects"), params, params.page)
+            .await
+    }
+
+    #[tracing::instrument(name = "Fetch page of resources", skip(self), err)]
+    pub async fn resources_page(
+        &self,
+        params: &ListPageParams,
+    ) -> Result<Page<ResourceModel>, TeamdeckApiError> {
+        self.get_page(&self.url("resources"), params, params.page)
+            .await
+    }
+
+    async fn get_page<T: Serialize + DeserializeOwned, P: Serialize + ?Sized>(
+        &self,
+        url: &str,
+        params: &P,
+        requested_page: u64,
+    ) -> Result<Page<T>, TeamdeckApiError> {
+        let response = self.send_checked(self.get(url).query(params)).await?;
+
+        let headers = response.headers().clone();
+        let items: Vec<T> = response.json().await?;
+        let pagination = PaginationInfo::from_headers(&headers, requested_page, items.len() as u64);
+
+        Ok(Page { items, pagination })
+    }
+
+    /// Checks whether the Teamdeck API can be reached with the API key, fetching a single resource.
+    #[tracing::instrument(name = "Ping Teamdeck API", skip(self), err)]
+    pub async fn ping(&self) -> Result<(), TeamdeckApiError> {
+        self.send_checked(self.get(self.url("resources")).query(&[("per-page", 1)]))
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Delete time entry by ID", skip(self), err)]
+    pub async fn delete_time_entry(
+        &self,
+        time_entry_id: u64,
+    ) -> Result<Option<TimeEntryModel>, TeamdeckApiError> {
+        let response_body = self
+            .send_checked(self.delete(self.url(&format!("time-entries/{}", time_entry_id))))
+            .await
+            .map_err(|e| e.for_resource(TIME_ENTRY_RESOURCE_TYPE, time_entry_id))?
+            .text()
+            .await?;
+
+        parse_optional_body(&response_body)
     }
 
     #[tracing::instrument(name = "Create new time entry via Teamdeck API", skip(self), err)]
@@ -140,27 +471,426 @@ impl TeamdeckApiClient {
         body: CreateTimeEntryBody,
     ) -> Result<TimeEntryModel, TeamdeckApiError> {
         let response = self
-            .post("https://api.teamdeck.io/v1/time-entries")
-            .json(&body)
-            .send()
+            .send_checked(self.post(self.url("time-entries")).json(&body))
             .await?;
 
         let response_body = response.text().await?;
-        dbg!(&response_body);
+        #[cfg(feature = "log-response-bodies")]
+        tracing::debug!(body = %response_body, "Teamdeck responded to time entry creation");
         let time_entry = serde_json::from_str(&response_body)
             .map_err(|e| TeamdeckApiError::ServerError(e.to_string()))?;
         Ok(time_entry)
     }
 
+    /// Builds a GET request authorized with the API key.
+    ///
+    /// Like other requests built by the client, it shares its timeout and base URL,
+    /// and it's retried according to the retry policy when sent with `send`.
+    fn get<U: IntoUrl>(&self, url: U) -> reqwest::RequestBuilder {
+        self.client
+            .get(url)
+            .header(API_KEY_HEADER_NAME, &self.api_key)
+    }
+
     fn put<U: IntoUrl>(&self, url: U) -> reqwest::RequestBuilder {
-        reqwest::Client::new()
+        self.client
             .put(url)
             .header(API_KEY_HEADER_NAME, &self.api_key)
     }
 
     fn post<U: IntoUrl>(&self, url: U) -> reqwest::RequestBuilder {
-        reqwest::Client::new()
+        self.client
             .post(url)
             .header(API_KEY_HEADER_NAME, &self.api_key)
     }
+
+    fn delete<U: IntoUrl>(&self, url: U) -> reqwest::RequestBuilder {
+        self.client
+            .delete(url)
+            .header(API_KEY_HEADER_NAME, &self.api_key)
+    }
+}
+
+/// Parses the response body, treating an empty body (e.g. of `204 No Content`) as `None`.
+fn parse_optional_body<T: DeserializeOwned>(body: &str) -> Result<Option<T>, TeamdeckApiError> {
+    if body.trim().is_empty() {
+        return Ok(None);
+    }
+
+    serde_json::from_str(body)
+        .map(Some)
+        .map_err(|e| TeamdeckApiError::ServerError(e.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::StatusCode;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    const UNAVAILABLE_RESPONSE: &str =
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+    const NO_CONTENT_RESPONSE: &str = "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n";
+    const OK_RESPONSE: &str = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\nConnection: close\r\n\r\n[]";
+
+    /// Starts a server answering consecutive requests with the given responses.
+    ///
+    /// Returns the URL of the server and the counter of received requests.
+    fn mock_server(responses: Vec<&'static str>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+
+        let received = requests.clone();
+        std::thread::spawn(move || {
+            for (stream, response) in listener.incoming().zip(responses) {
+                let mut stream = stream.unwrap();
+                let mut request = [0; 4096];
+                let _ = stream.read(&mut request);
+                received.fetch_add(1, Ordering::SeqCst);
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    /// Starts a server answering consecutive requests with the given responses,
+    /// like [`mock_server`], but recording every received request.
+    fn recording_mock_server(responses: Vec<&'static str>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let received = requests.clone();
+        std::thread::spawn(move || {
+            for (stream, response) in listener.incoming().zip(responses) {
+                let mut stream = stream.unwrap();
+                received.lock().unwrap().push(read_request(&mut stream));
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    /// Reads the request head and as much of the body as its `Content-Length` says.
+    fn read_request(stream: &mut impl Read) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0; 4096];
+        loop {
+            let read = stream.read(&mut buffer).unwrap_or(0);
+            request.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&request).to_string();
+            let complete = match text.split_once("\r\n\r\n") {
+                Some((head, body)) => {
+                    let content_length = head
+                        .lines()
+                        .filter_map(|line| line.split_once(':'))
+                        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                        .and_then(|(_, value)| value.trim().parse().ok())
+                        .unwrap_or(0);
+                    body.len() >= content_length
+                }
+                None => false,
+            };
+            if complete || read == 0 {
+                return text;
+            }
+        }
+    }
+
+    fn client(retry_post: bool) -> TeamdeckApiClient {
+        client_with_base_url(DEFAULT_BASE_URL, retry_post)
+    }
+
+    fn client_with_base_url(base_url: &str, retry_post: bool) -> TeamdeckApiClient {
+        TeamdeckApiClient {
+            client: http_client(Duration::from_millis(200)),
+            api_key: "test".to_string(),
+            base_url: base_url.to_string(),
+            retry_policy: RetryPolicy {
+                max_attempts: 3,
+                base_delay: Duration::from_millis(1),
+                max_delay: Duration::from_millis(10),
+                retry_post,
+            },
+        }
+    }
+
+    #[test]
+    fn test_url() {
+        assert_eq!(
+            client(false).url("time-entries/1"),
+            "https://api.teamdeck.io/v1/time-entries/1"
+        );
+        assert_eq!(
+            client_with_base_url("http://localhost:8080/", false).url("projects"),
+            "http://localhost:8080/projects"
+        );
+    }
+
+    /// Leaks the response with the JSON body, so it can be served by the mock server.
+    fn json_response(body: &str) -> &'static str {
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        Box::leak(response.into_boxed_str())
+    }
+
+    #[actix_web::test]
+    async fn test_get_time_entry() {
+        let body = r#"{"id":7,"resource_id":1,"project_id":2,"minutes":60,"weekend_booking":false,"holidays_booking":false,"vacations_booking":false,"description":null,"external_id":null,"start_date":"2022-01-10","end_date":"2022-01-10","creator_resource_id":1,"editor_resource_id":1,"tags":null}"#;
+        let (url, _) = mock_server(vec![json_response(body)]);
+        let client = client_with_base_url(&url, false);
+
+        let time_entry = client.get_time_entry(7).await.unwrap();
+        assert_eq!(time_entry.id, 7);
+        assert_eq!(time_entry.project_id, 2);
+    }
+
+    #[actix_web::test]
+    async fn test_get_time_entry_tags() {
+        let body = r#"{"id":7,"resource_id":1,"project_id":2,"minutes":60,"weekend_booking":false,"holidays_booking":false,"vacations_booking":false,"description":null,"external_id":null,"start_date":"2022-01-10","end_date":"2022-01-10","creator_resource_id":1,"editor_resource_id":1,"tags":[{"id":3,"name":"meeting","icon":null,"color":null,"archived":0}]}"#;
+        let (url, _) = mock_server(vec![json_response(body)]);
+        let client = client_with_base_url(&url, false);
+
+        let tags = client.get_time_entry_tags(7).await.unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].id, 3);
+    }
+
+    #[actix_web::test]
+    async fn test_update_time_entry_tags_with_empty_response() {
+        let (url, _) = mock_server(vec![
+            "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+        let client = client_with_base_url(&url, false);
+
+        let tags = client.update_time_entry_tags(7, vec![1, 2]).await.unwrap();
+        assert_eq!(tags, vec![1, 2]);
+    }
+
+    #[actix_web::test]
+    async fn test_clear_time_entry_tags() {
+        let body = r#"{"id":7,"resource_id":1,"project_id":2,"minutes":60,"weekend_booking":false,"holidays_booking":false,"vacations_booking":false,"description":null,"external_id":null,"start_date":"2022-01-10","end_date":"2022-01-10","creator_resource_id":1,"editor_resource_id":1,"tags":[]}"#;
+        let (url, requests) = recording_mock_server(vec![
+            "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            json_response(body),
+        ]);
+        let client = client_with_base_url(&url, false);
+
+        let time_entry = client.clear_time_entry_tags(7).await.unwrap();
+        assert_eq!(time_entry.id, 7);
+        assert_eq!(time_entry.tags.map(|tags| tags.len()), Some(0));
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("PUT /time-entries/7/tags "));
+        assert!(requests[0].ends_with("\r\n\r\n[]"));
+        assert!(requests[1].starts_with("GET /time-entries/7 "));
+    }
+
+    #[test]
+    fn test_parse_assigned_tags() {
+        assert_eq!(parse_assigned_tags("[3]", vec![1]).unwrap(), vec![3]);
+        assert_eq!(parse_assigned_tags(" ", vec![1]).unwrap(), vec![1]);
+
+        match parse_assigned_tags(r#"{"tags": [1]}"#, vec![1]) {
+            Err(TeamdeckApiError::UnexpectedResponse { body, .. }) => {
+                assert_eq!(body, r#"{"tags": [1]}"#)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_get_missing_time_entry() {
+        let (url, _) = mock_server(vec![
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        ]);
+        let client = client_with_base_url(&url, false);
+
+        assert!(matches!(
+            client.get_time_entry(7).await,
+            Err(TeamdeckApiError::NotFound { resource_id: 7, .. })
+        ));
+    }
+
+    #[actix_web::test]
+    async fn test_delete_time_entry_from_base_url() {
+        let (url, requests) = mock_server(vec![NO_CONTENT_RESPONSE]);
+        let client = client_with_base_url(&url, false);
+
+        let deleted = client.delete_time_entry(1).await.unwrap();
+        assert!(deleted.is_none());
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_request_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (_stream, _) = listener.accept().unwrap();
+            std::thread::sleep(Duration::from_secs(2));
+        });
+        let client = client_with_base_url(&url, false);
+
+        let error = client.delete_time_entry(1).await.unwrap_err();
+        assert!(matches!(error, TeamdeckApiError::Timeout));
+    }
+
+    #[actix_web::test]
+    async fn test_request_retried_until_success() {
+        let (url, requests) = mock_server(vec![
+            UNAVAILABLE_RESPONSE,
+            UNAVAILABLE_RESPONSE,
+            OK_RESPONSE,
+        ]);
+        let client = client(false);
+
+        let response = client.send(client.put(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_retries_limited_by_max_attempts() {
+        let (url, requests) = mock_server(vec![UNAVAILABLE_RESPONSE; 3]);
+        let client = client(false);
+
+        let response = client.send(client.delete(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[actix_web::test]
+    async fn test_post_not_retried_unless_enabled() {
+        let (url, requests) = mock_server(vec![UNAVAILABLE_RESPONSE, OK_RESPONSE]);
+        let client = client(false);
+
+        let response = client.send(client.post(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_web::test]
+    async fn test_post_retried_when_enabled() {
+        let (url, requests) = mock_server(vec![UNAVAILABLE_RESPONSE, OK_RESPONSE]);
+        let client = client(true);
+
+        let response = client.send(client.post(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    fn update_body_with_external_id(external_id: Option<Option<String>>) -> UpdateTimeEntryBody {
+        UpdateTimeEntryBody {
+            external_id,
+            ..update_body(None)
+        }
+    }
+
+    #[test]
+    fn test_update_body_external_id() {
+        let kept = serde_json::to_value(update_body_with_external_id(None)).unwrap();
+        assert!(kept.get("external_id").is_none());
+
+        let cleared = serde_json::to_value(update_body_with_external_id(Some(None))).unwrap();
+        assert_eq!(cleared.get("external_id"), Some(&serde_json::Value::Null));
+
+        let set = serde_json::to_value(update_body_with_external_id(Some(Some(
+            "jira-123".to_string(),
+        ))))
+        .unwrap();
+        assert_eq!(set["external_id"], "jira-123");
+    }
+
+    fn update_body(tags: Option<Vec<u64>>) -> UpdateTimeEntryBody {
+        UpdateTimeEntryBody {
+            project_id: 1,
+            minutes: 60,
+            weekend_booking: None,
+            holidays_booking: None,
+            vacations_booking: None,
+            description: None,
+            start_date: NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+            end_date: NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+            editor_resource_id: 1,
+            tags,
+            external_id: None,
+        }
+    }
+
+    #[test]
+    fn test_update_body_without_tags_omits_field() {
+        let json = serde_json::to_value(update_body(None)).unwrap();
+        assert!(json.get("tags").is_none());
+    }
+
+    #[test]
+    fn test_pagination_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TOTAL_COUNT_HEADER_NAME, "42".parse().unwrap());
+        headers.insert(PAGES_COUNT_HEADER_NAME, "5".parse().unwrap());
+        headers.insert(CURRENT_PAGE_HEADER_NAME, "2".parse().unwrap());
+        headers.insert(ITEMS_PER_PAGE_HEADER_NAME, "10".parse().unwrap());
+
+        let pagination = PaginationInfo::from_headers(&headers, 2, 10);
+        assert_eq!(pagination.total_count, 42);
+        assert_eq!(pagination.pages_count, 5);
+        assert_eq!(pagination.current_page, 2);
+        assert_eq!(pagination.items_per_page, 10);
+    }
+
+    #[test]
+    fn test_pagination_without_headers() {
+        let pagination = PaginationInfo::from_headers(&HeaderMap::new(), 0, 7);
+        assert_eq!(pagination.total_count, 7);
+        assert_eq!(pagination.pages_count, 1);
+        assert_eq!(pagination.current_page, 0);
+        assert_eq!(pagination.items_per_page, 7);
+    }
+
+    #[test]
+    fn test_list_page_params() {
+        let params = ListPageParams::new("-name".to_string(), Some(3), Some(20));
+        let json = serde_json::to_value(params).unwrap();
+        assert_eq!(json["sort"], "-name");
+        assert_eq!(json["page"], 3);
+        assert_eq!(json["per-page"], 20);
+
+        let params = ListPageParams::new("name".to_string(), None, None);
+        let json = serde_json::to_value(params).unwrap();
+        assert_eq!(json["page"], 0);
+        assert!(json.get("per-page").is_none());
+    }
+
+    #[test]
+    fn test_parse_empty_body() {
+        let parsed: Option<serde_json::Value> = parse_optional_body("").unwrap();
+        assert_eq!(parsed, None);
+        let parsed: Option<serde_json::Value> = parse_optional_body(" \n").unwrap();
+        assert_eq!(parsed, None);
+    }
+
+    #[test]
+    fn test_parse_json_body() {
+        let parsed: Option<serde_json::Value> = parse_optional_body(r#"{"id": 1}"#).unwrap();
+        assert_eq!(parsed, Some(serde_json::json!({ "id": 1 })));
+        assert!(parse_optional_body::<serde_json::Value>("not json").is_err());
+    }
+
+    #[test]
+    fn test_update_body_with_tags_sends_field() {
+        let json = serde_json::to_value(update_body(Some(vec![1, 2]))).unwrap();
+        assert_eq!(json["tags"], serde_json::json!([1, 2]));
+    }
 }
@@ -1,5 +1,8 @@
-use async_graphql::{ErrorExtensions, FieldError};
-use serde::Deserialize;
+use crate::teamdeck::retry::retry_after;
+use async_graphql::{ErrorExtensions, FieldError, Value};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Debug, Error, Deserialize)]
@@ -10,20 +13,96 @@ pub enum TeamdeckApiError {
         resource_id: u64,
     },
 
+    #[error("Teamdeck rejected the API key")]
+    Unauthorized,
+
+    #[error("Teamdeck API key is not allowed to perform the operation")]
+    Forbidden,
+
+    #[error("Teamdeck rejected the request as invalid")]
+    Validation(Vec<ValidationError>),
+
+    #[error("Teamdeck rate limit exceeded")]
+    RateLimited { retry_after: Option<u64> },
+
+    #[error("Teamdeck did not respond in time")]
+    Timeout,
+
     #[error("ServerError")]
     ServerError(String),
+
+    #[error("Teamdeck responded with unexpected body: {reason}")]
+    UnexpectedResponse { reason: String, body: String },
+}
+
+/// Single error of a request rejected by Teamdeck with `422 Unprocessable Entity`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationError {
+    #[serde(default)]
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl TeamdeckApiError {
+    /// Builds the error from the status, headers and body of an unsuccessful Teamdeck response.
+    pub fn from_status(status: StatusCode, headers: &HeaderMap, body: &str) -> Self {
+        match status {
+            StatusCode::NOT_FOUND => TeamdeckApiError::NotFound {
+                resource_type: "unknown".to_string(),
+                resource_id: 0,
+            },
+            StatusCode::UNAUTHORIZED => TeamdeckApiError::Unauthorized,
+            StatusCode::FORBIDDEN => TeamdeckApiError::Forbidden,
+            StatusCode::UNPROCESSABLE_ENTITY => {
+                TeamdeckApiError::Validation(parse_validation_errors(body))
+            }
+            StatusCode::TOO_MANY_REQUESTS => TeamdeckApiError::RateLimited {
+                retry_after: retry_after(headers).map(|delay| delay.as_secs()),
+            },
+            _ => TeamdeckApiError::ServerError(status.as_str().to_string()),
+        }
+    }
+
+    /// Fills in the details of `NotFound` error, which the response itself does not carry.
+    pub fn for_resource(self, resource_type: &str, resource_id: u64) -> Self {
+        match self {
+            TeamdeckApiError::NotFound { .. } => TeamdeckApiError::NotFound {
+                resource_type: resource_type.to_string(),
+                resource_id,
+            },
+            error => error,
+        }
+    }
+
+    /// Returns the response when it is successful, the error described by it otherwise.
+    pub async fn check(response: reqwest::Response) -> Result<reqwest::Response, Self> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let headers = response.headers().clone();
+        let body = response.text().await.unwrap_or_default();
+        Err(Self::from_status(status, &headers, &body))
+    }
+}
+
+/// Parses the list of field errors, falling back to a single error with the whole body as message.
+fn parse_validation_errors(body: &str) -> Vec<ValidationError> {
+    serde_json::from_str(body).unwrap_or_else(|_| {
+        vec![ValidationError {
+            field: None,
+            message: body.to_string(),
+        }]
+    })
 }
 
 impl From<reqwest::Error> for TeamdeckApiError {
     fn from(error: reqwest::Error) -> Self {
-        if let Some(status) = error.status() {
-            match status.as_u16() {
-                404 => TeamdeckApiError::NotFound {
-                    resource_type: "unknown".to_string(),
-                    resource_id: 0,
-                },
-                _ => TeamdeckApiError::ServerError(status.as_str().to_string()),
-            }
+        if error.is_timeout() {
+            TeamdeckApiError::Timeout
+        } else if let Some(status) = error.status() {
+            TeamdeckApiError::from_status(status, &HeaderMap::new(), "")
         } else {
             TeamdeckApiError::ServerError(error.to_string())
         }
@@ -41,7 +120,144 @@ impl ErrorExtensions for TeamdeckApiError {
                 e.set("resource_type", resource_type.as_str());
                 e.set("resource_id", *resource_id);
             }
+            TeamdeckApiError::Unauthorized => e.set("code", "UPSTREAM_UNAUTHORIZED"),
+            TeamdeckApiError::Forbidden => e.set("code", "UPSTREAM_FORBIDDEN"),
+            TeamdeckApiError::Validation(errors) => {
+                e.set("code", "VALIDATION_ERROR");
+                e.set(
+                    "errors",
+                    Value::from_json(serde_json::to_value(errors).unwrap_or_default())
+                        .unwrap_or(Value::Null),
+                );
+            }
+            TeamdeckApiError::RateLimited { retry_after } => {
+                e.set("code", "RATE_LIMITED");
+                if let Some(retry_after) = retry_after {
+                    e.set("retry_after", *retry_after);
+                }
+            }
+            TeamdeckApiError::Timeout => e.set("code", "TIMEOUT"),
             TeamdeckApiError::ServerError(reason) => e.set("reason", reason.to_string()),
+            TeamdeckApiError::UnexpectedResponse { reason, body } => {
+                e.set("code", "UNEXPECTED_RESPONSE");
+                e.set("reason", reason.as_str());
+                e.set("body", body.as_str());
+            }
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::header::RETRY_AFTER;
+
+    fn error(status: StatusCode, body: &str) -> TeamdeckApiError {
+        TeamdeckApiError::from_status(status, &HeaderMap::new(), body)
+    }
+
+    fn code(error: TeamdeckApiError) -> Option<Value> {
+        error
+            .extend()
+            .extensions
+            .and_then(|extensions| extensions.get("code").cloned())
+    }
+
+    #[test]
+    fn test_unauthorized_and_forbidden() {
+        let unauthorized = error(StatusCode::UNAUTHORIZED, "");
+        assert!(matches!(unauthorized, TeamdeckApiError::Unauthorized));
+        assert_eq!(
+            code(unauthorized),
+            Some(Value::from("UPSTREAM_UNAUTHORIZED"))
+        );
+
+        let forbidden = error(StatusCode::FORBIDDEN, "");
+        assert!(matches!(forbidden, TeamdeckApiError::Forbidden));
+        assert_eq!(code(forbidden), Some(Value::from("UPSTREAM_FORBIDDEN")));
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let body = r#"[{"field": "minutes", "message": "Minutes must be greater than 0."}]"#;
+        let validation = error(StatusCode::UNPROCESSABLE_ENTITY, body);
+
+        match &validation {
+            TeamdeckApiError::Validation(errors) => assert_eq!(
+                errors,
+                &vec![ValidationError {
+                    field: Some("minutes".to_string()),
+                    message: "Minutes must be greater than 0.".to_string(),
+                }]
+            ),
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(code(validation), Some(Value::from("VALIDATION_ERROR")));
+    }
+
+    #[test]
+    fn test_unparsable_validation_errors() {
+        match error(StatusCode::UNPROCESSABLE_ENTITY, "Invalid data") {
+            TeamdeckApiError::Validation(errors) => {
+                assert_eq!(errors[0].field, None);
+                assert_eq!(errors[0].message, "Invalid data");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rate_limited() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "30".parse().unwrap());
+        let rate_limited =
+            TeamdeckApiError::from_status(StatusCode::TOO_MANY_REQUESTS, &headers, "");
+
+        assert!(matches!(
+            rate_limited,
+            TeamdeckApiError::RateLimited {
+                retry_after: Some(30)
+            }
+        ));
+        assert_eq!(code(rate_limited), Some(Value::from("RATE_LIMITED")));
+    }
+
+    #[test]
+    fn test_not_found_for_resource() {
+        match error(StatusCode::NOT_FOUND, "").for_resource("time entry", 42) {
+            TeamdeckApiError::NotFound {
+                resource_type,
+                resource_id,
+            } => {
+                assert_eq!(resource_type, "time entry");
+                assert_eq!(resource_id, 42);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        assert!(matches!(
+            error(StatusCode::FORBIDDEN, "").for_resource("time entry", 42),
+            TeamdeckApiError::Forbidden
+        ));
+    }
+
+    #[test]
+    fn test_timeout_code() {
+        assert_eq!(
+            code(TeamdeckApiError::Timeout),
+            Some(Value::from("TIMEOUT"))
+        );
+    }
+
+    #[test]
+    fn test_other_statuses() {
+        assert!(matches!(
+            error(StatusCode::NOT_FOUND, ""),
+            TeamdeckApiError::NotFound { .. }
+        ));
+        assert!(matches!(
+            error(StatusCode::BAD_GATEWAY, ""),
+            TeamdeckApiError::ServerError(reason) if reason == "502"
+        ));
+    }
+}
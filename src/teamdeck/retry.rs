@@ -0,0 +1,131 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Method, StatusCode};
+use std::time::Duration;
+
+const MAX_ATTEMPTS_ENV_VARIABLE: &str = "TEAMDECK_MAX_ATTEMPTS";
+const RETRY_POST_ENV_VARIABLE: &str = "TEAMDECK_RETRY_POST";
+
+/// How requests to Teamdeck failing with `429 Too Many Requests` or `5xx` statuses are retried.
+///
+/// Delays grow exponentially from `base_delay`, unless Teamdeck tells how long to wait
+/// with the `Retry-After` header. Delays never exceed `max_delay`.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts of a single request, the first one included.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Whether `POST` requests are retried as well.
+    ///
+    /// Retrying them may create duplicates when Teamdeck fails after processing the request.
+    pub retry_post: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(10),
+            retry_post: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+
+        if let Ok(value) = std::env::var(MAX_ATTEMPTS_ENV_VARIABLE) {
+            policy.max_attempts = value.parse().unwrap_or_else(|_| {
+                panic!(
+                    "Invalid {} env variable: {}",
+                    MAX_ATTEMPTS_ENV_VARIABLE, value
+                )
+            });
+        }
+        if let Ok(value) = std::env::var(RETRY_POST_ENV_VARIABLE) {
+            policy.retry_post = value == "true";
+        }
+
+        policy
+    }
+
+    /// Returns whether requests with the given method can be retried at all.
+    pub fn allows(&self, method: &Method) -> bool {
+        *method != Method::POST || self.retry_post
+    }
+
+    pub fn is_retryable(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    /// Returns how long to wait before the next attempt, `attempt` being the failed one (starting at 1).
+    pub fn delay(&self, attempt: u32, headers: &HeaderMap) -> Duration {
+        let delay = retry_after(headers).unwrap_or_else(|| {
+            self.base_delay
+                .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        });
+
+        delay.min(self.max_delay)
+    }
+}
+
+/// Reads the `Retry-After` header given in seconds.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exponential_delay() {
+        let policy = RetryPolicy::default();
+        let headers = HeaderMap::new();
+
+        assert_eq!(policy.delay(1, &headers), Duration::from_millis(200));
+        assert_eq!(policy.delay(2, &headers), Duration::from_millis(400));
+        assert_eq!(policy.delay(3, &headers), Duration::from_millis(800));
+        assert_eq!(policy.delay(20, &headers), policy.max_delay);
+    }
+
+    #[test]
+    fn test_retry_after_header() {
+        let policy = RetryPolicy::default();
+        let mut headers = HeaderMap::new();
+
+        headers.insert(RETRY_AFTER, "2".parse().unwrap());
+        assert_eq!(policy.delay(1, &headers), Duration::from_secs(2));
+
+        headers.insert(RETRY_AFTER, "3600".parse().unwrap());
+        assert_eq!(policy.delay(1, &headers), policy.max_delay);
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(RetryPolicy::is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(RetryPolicy::is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(!RetryPolicy::is_retryable(StatusCode::NOT_FOUND));
+        assert!(!RetryPolicy::is_retryable(StatusCode::OK));
+    }
+
+    #[test]
+    fn test_post_retries_are_opt_in() {
+        let mut policy = RetryPolicy::default();
+        assert!(policy.allows(&Method::PUT));
+        assert!(policy.allows(&Method::DELETE));
+        assert!(!policy.allows(&Method::POST));
+
+        policy.retry_post = true;
+        assert!(policy.allows(&Method::POST));
+    }
+}
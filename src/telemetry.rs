@@ -1,8 +1,24 @@
-use tracing::{subscriber::set_global_default, Subscriber};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::{Error, HttpMessage};
+use opentelemetry::sdk::trace::{self, Sampler, Tracer};
+use opentelemetry::sdk::Resource;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use teamdeck_tracker_api::request_id::{RequestId, REQUEST_ID_HEADER};
+use tracing::{subscriber::set_global_default, Span, Subscriber};
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, EnvFilter, Registry};
 
+const OTLP_ENDPOINT_ENV_VARIABLE: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+const OTLP_SERVICE_NAME_ENV_VARIABLE: &str = "OTEL_SERVICE_NAME";
+const OTLP_SAMPLING_RATIO_ENV_VARIABLE: &str = "OTEL_TRACES_SAMPLING_RATIO";
+const DEFAULT_OTLP_SAMPLING_RATIO: f64 = 1.0;
+
 pub fn get_logs_subscriber<Sink>(
     name: String,
     env_filter: String,
@@ -13,16 +29,103 @@ where
 {
     let env_filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter));
+    let otlp_layer = otlp_layer_from_env(&name);
     let formatting_layer = BunyanFormattingLayer::new(name, sink);
 
     Registry::default()
         .with(env_filter)
+        .with(otlp_layer)
         .with(JsonStorageLayer)
         .with(formatting_layer)
 }
 
+/// Exports spans to an OTLP collector (e.g. Jaeger or Tempo) when `OTEL_EXPORTER_OTLP_ENDPOINT` is set.
+///
+/// Must be called within the Tokio runtime, which runs the batch exporter.
+fn otlp_layer_from_env<S>(default_service_name: &str) -> Option<OpenTelemetryLayer<S, Tracer>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let endpoint = std::env::var(OTLP_ENDPOINT_ENV_VARIABLE).ok()?;
+    let service_name = std::env::var(OTLP_SERVICE_NAME_ENV_VARIABLE)
+        .unwrap_or_else(|_| default_service_name.to_string());
+    let sampling_ratio = match std::env::var(OTLP_SAMPLING_RATIO_ENV_VARIABLE) {
+        Ok(value) => value
+            .parse::<f64>()
+            .ok()
+            .filter(|ratio| (0.0..=1.0).contains(ratio))
+            .unwrap_or_else(|| {
+                panic!(
+                    "Invalid {} env variable: {}",
+                    OTLP_SAMPLING_RATIO_ENV_VARIABLE, value
+                )
+            }),
+        Err(_) => DEFAULT_OTLP_SAMPLING_RATIO,
+    };
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            trace::config()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+                    sampling_ratio,
+                ))))
+                .with_resource(Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    service_name,
+                )])),
+        )
+        .install_batch(opentelemetry::runtime::Tokio)
+        .expect("Failed to install OTLP exporter");
+
+    Some(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Flushes spans not yet exported to the OTLP collector, if any.
+pub fn shutdown_telemetry() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
 pub fn init_logs_subscriber(subscriber: impl Subscriber + Send + Sync) {
     LogTracer::init().expect("Failed to set logger");
 
     set_global_default(subscriber).expect("Failed to set subscriber");
 }
+
+/// Root span of a request, with `request_id` taken from the `X-Request-Id` header or generated.
+///
+/// The ID is stored in the request extensions, so it can be echoed back in the response.
+pub struct RequestIdRootSpanBuilder;
+
+impl RootSpanBuilder for RequestIdRootSpanBuilder {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        let request_id = RequestId::from_header(
+            request
+                .headers()
+                .get(REQUEST_ID_HEADER)
+                .and_then(|value| value.to_str().ok()),
+        );
+        let span = tracing::info_span!(
+            "HTTP request",
+            http.method = %request.method(),
+            http.target = %request.uri(),
+            http.status_code = tracing::field::Empty,
+            otel.status_code = tracing::field::Empty,
+            exception.message = tracing::field::Empty,
+            exception.details = tracing::field::Empty,
+            request_id = %request_id,
+        );
+        request.extensions_mut().insert(request_id);
+
+        span
+    }
+
+    fn on_request_end<B: MessageBody>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
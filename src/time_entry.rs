@@ -1,39 +1,138 @@
-use crate::auth::guard::AccessTokenAuthGuard;
+use crate::auth::guard::{is_admin, AccessTokenAuthGuard, AuthError};
 use crate::auth::token::ResourceId;
-use crate::project::ProjectModel;
-use crate::resource::ResourceModel;
-use crate::scalars::Date;
+use crate::batch::{partition_results, BatchItemError, BatchLimit};
+use crate::clock::Clock;
+use crate::day_totals::DayTotalsCache;
+use crate::degraded::with_stale_fallback;
+use crate::idempotency::{IdempotencyKeys, Idempotent};
+use crate::loader::{ProjectLoader, ResourceLoader};
+use crate::project::{fetch_projects_by_id, ProjectModel};
+use crate::range::{DateRange, RangeError};
+use crate::rate_limit::MutationRateLimiter;
+use crate::resource::{distinct_ids, fetch_resource, ResourceModel};
+use crate::scalars::{Date, DateTime, Duration, Json, Time};
 use crate::sort_by_enum::sort_by_enum;
-use crate::teamdeck::api::{CreateTimeEntryBody, TeamdeckApiClient, UpdateTimeEntryBody};
+use crate::tag_policy::{TagOperation, TagPolicy};
+use crate::tag_validation::TagValidator;
+use crate::teamdeck::api::{
+    CreateTimeEntryBody, PaginationInfo, TeamdeckApiClient, TimeEntriesPageParams,
+    UpdateTimeEntryBody,
+};
 use crate::time_entry_tag::TimeEntryTagModel;
-use async_graphql::{ComplexObject, Context, InputObject, Object, Result, ResultExt, SimpleObject};
-use chrono::Duration;
-use serde::{Deserialize, Serialize};
-use teamdeck::api::projects::Project;
-use teamdeck::api::resources::Resource;
-use teamdeck::api::time_entries::{TimeEntries, TimeEntriesExpand, TimeEntry};
+use crate::timer::{Timer, Timers};
+use crate::validation::ValidationError;
+use crate::warnings::{Warning, Warnings};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::futures_util::stream::{self, StreamExt};
+use async_graphql::{
+    ComplexObject, Context, ErrorExtensions, FieldError, InputObject, MaybeUndefined, Object,
+    Result, ResultExt, SimpleObject,
+};
+use chrono::{
+    DateTime as ChronoDateTime, Duration as ChronoDuration, NaiveDate, NaiveDateTime, Utc,
+};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use teamdeck::api::time_entries::{TimeEntries, TimeEntriesBuilder, TimeEntriesExpand, TimeEntry};
 use teamdeck::api::{paged, AsyncQuery, Pagination};
 use teamdeck::AsyncTeamdeck;
 use thiserror::Error;
 use tracing::error;
 
-#[derive(Serialize, Deserialize, SimpleObject, Debug)]
+#[derive(Serialize, Deserialize, SimpleObject, Debug, Clone)]
 #[graphql(complex)]
 pub struct TimeEntryModel {
-    id: u64,
-    resource_id: u64,
-    project_id: u64,
-    minutes: u64,
+    pub id: u64,
+    pub resource_id: u64,
+    pub project_id: u64,
+    pub minutes: u64,
     weekend_booking: bool,
     holidays_booking: bool,
     vacations_booking: bool,
-    description: Option<String>,
+    pub description: Option<String>,
     external_id: Option<String>,
-    start_date: Date,
-    end_date: Date,
+    pub start_date: Date,
+    pub end_date: Date,
     creator_resource_id: Option<u64>,
     editor_resource_id: Option<u64>,
-    tags: Option<Vec<TimeEntryTagModel>>,
+    /// Tags of the entry, `None` when Teamdeck did not expand them in the response.
+    #[graphql(skip)]
+    pub tags: Option<Vec<TimeEntryTagModel>>,
+    /// Creation time of the entry, if provided by Teamdeck.
+    #[graphql(skip)]
+    #[serde(default, deserialize_with = "optional_datetime")]
+    created_at: Option<DateTime>,
+    /// Raw Teamdeck payload of the time entry.
+    ///
+    /// Available only for admins, when requested with `timeEntry(raw: true)`.
+    #[serde(skip)]
+    raw: Option<Json>,
+    #[graphql(skip)]
+    #[serde(skip)]
+    expanded_project: Option<ProjectModel>,
+    #[graphql(skip)]
+    #[serde(skip)]
+    expanded_resource: Option<ResourceModel>,
+}
+
+/// Kind of work booked with the time entry.
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BookingType {
+    Regular,
+    Weekend,
+    Holiday,
+    Vacation,
+}
+
+/// How time entries are matched against multiple tags.
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TagMatch {
+    /// Entry has at least one of the tags.
+    Any,
+    /// Entry has all of the tags.
+    All,
+}
+
+/// How `assignTags` combines the given tags with the existing tags of the entries.
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TagAssignmentMode {
+    /// Existing tags are replaced with the given ones.
+    Replace,
+    /// Given tags are added to the existing ones.
+    Add,
+}
+
+impl TagAssignmentMode {
+    /// Returns tags of the entry after the assignment, existing tags first.
+    fn assigned_tags(self, existing: Vec<u64>, tag_ids: &[u64]) -> Vec<u64> {
+        match self {
+            TagAssignmentMode::Replace => tag_ids.to_vec(),
+            TagAssignmentMode::Add => {
+                let mut tags = existing;
+                for tag_id in tag_ids {
+                    if !tags.contains(tag_id) {
+                        tags.push(*tag_id);
+                    }
+                }
+                tags
+            }
+        }
+    }
+}
+
+impl Default for TagMatch {
+    fn default() -> Self {
+        TagMatch::Any
+    }
+}
+
+/// Related data of the time entries which can be fetched eagerly, in a single batch.
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum EntryExpand {
+    Tags,
+    Project,
+    Resource,
 }
 
 sort_by_enum!(
@@ -59,74 +158,536 @@ pub struct TimeEntryFilter {
     /// Sorts results by the given field in the given direction.
     ///
     /// Default: `StartDate` descending
-    sort: Option<TimeEntriesSortBy>,
+    pub sort: Option<TimeEntriesSortBy>,
 
     /// The page number to return.
     /// Pages start at 0.
     ///
     /// If not specified, all pages are returned.
-    page: Option<u64>,
+    pub page: Option<u64>,
 
     /// The ID of project(s) to filter by.
     ///
     /// Multiple IDs can be passed. By default, all of the entries are returned.
-    project_id: Option<Vec<u64>>,
+    pub project_id: Option<Vec<u64>>,
 
     /// The external ID to filter by
     ///
     /// Multiple IDs can be passed. By default, all of the entries are returned.
-    external_id: Option<Vec<String>>,
+    pub external_id: Option<Vec<String>>,
+
+    /// The prefix of the external ID to filter by, e.g. `jira-PROJ-`.
+    ///
+    /// Entries without external ID are never matched.
+    pub external_id_prefix: Option<String>,
+
+    /// The ID of tag(s) to filter by.
+    ///
+    /// Entries are matched according to `tag_match`. Entries without tags are never matched.
+    pub tag_id: Option<Vec<u64>>,
+
+    /// How entries are matched against `tag_id`.
+    ///
+    /// Default: `Any`
+    pub tag_match: Option<TagMatch>,
+
+    /// The range of the time entry minutes to filter by, both bounds inclusive.
+    pub minutes: Option<MinutesRange>,
+
+    /// Text which the time entry description should contain, case-insensitive.
+    ///
+    /// Entries without description are never matched by a non-empty text.
+    pub description_contains: Option<String>,
+
     /// The date range of the time entry start date to filter by.
     ///
     /// Cannot be used together with `date`.
-    start_date: Option<TimeEntryDateRange>,
+    pub start_date: Option<DateRange>,
     /// The date range of the time entry end date to filter by.
     ///
     /// Cannot be used together with `date`.
-    end_date: Option<TimeEntryDateRange>,
+    pub end_date: Option<DateRange>,
 
     /// The date of the time entry to filter by.
     ///
     /// Cannot be used together with `start_date` or `end_date`.
-    date: Option<Date>,
+    pub date: Option<Date>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+enum TimeEntryFilterError {
+    #[error("`date` cannot be used together with `start_date` or `end_date`")]
+    DateWithRange,
+    #[error("`minutes.min` cannot be greater than `minutes.max`")]
+    InvalidMinutesRange,
+    #[error("Invalid `{0}`: {1}")]
+    InvalidRange(&'static str, RangeError),
+}
+
+impl ErrorExtensions for TimeEntryFilterError {
+    fn extend(&self) -> FieldError {
+        let field = match self {
+            TimeEntryFilterError::DateWithRange => "date",
+            TimeEntryFilterError::InvalidMinutesRange => "minutes",
+            TimeEntryFilterError::InvalidRange(field, _) => *field,
+        };
+        ValidationError::new(field, self).extend()
+    }
+}
+
+impl TimeEntryFilter {
+    /// Checks the entry against the criteria which Teamdeck cannot filter by,
+    /// so they are applied to the already fetched entries.
+    fn matches(&self, entry: &TimeEntryModel) -> bool {
+        if let Some(prefix) = &self.external_id_prefix {
+            if !entry.has_external_id_prefix(prefix) {
+                return false;
+            }
+        }
+
+        if let Some(tag_ids) = &self.tag_id {
+            if !entry.has_tags(tag_ids, self.tag_match.unwrap_or_default()) {
+                return false;
+            }
+        }
+
+        if let Some(minutes) = &self.minutes {
+            if !minutes.contains(entry.minutes) {
+                return false;
+            }
+        }
+
+        if let Some(text) = &self.description_contains {
+            if !entry.description_contains(text) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn validate(&self) -> Result<(), TimeEntryFilterError> {
+        if self.date.is_some() && (self.start_date.is_some() || self.end_date.is_some()) {
+            return Err(TimeEntryFilterError::DateWithRange);
+        }
+
+        if let Some(MinutesRange {
+            min: Some(min),
+            max: Some(max),
+        }) = self.minutes
+        {
+            if min > max {
+                return Err(TimeEntryFilterError::InvalidMinutesRange);
+            }
+        }
+
+        if let Some(range) = &self.start_date {
+            range
+                .validate()
+                .map_err(|e| TimeEntryFilterError::InvalidRange("startDate", e))?;
+        }
+        if let Some(range) = &self.end_date {
+            range
+                .validate()
+                .map_err(|e| TimeEntryFilterError::InvalidRange("endDate", e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Single page of time entries.
+#[derive(SimpleObject, Debug)]
+pub struct TimeEntryConnection {
+    pub items: Vec<TimeEntryModel>,
+    pub pagination: PaginationInfo,
 }
 
 #[derive(InputObject, Debug)]
-pub struct TimeEntryDateRange {
-    from: Date,
-    to: Date,
+pub struct MinutesRange {
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+impl MinutesRange {
+    fn contains(&self, minutes: u64) -> bool {
+        self.min.map_or(true, |min| minutes >= min) && self.max.map_or(true, |max| minutes <= max)
+    }
 }
 
 #[ComplexObject]
 impl TimeEntryModel {
     async fn project(&self, ctx: &Context<'_>) -> Result<Option<ProjectModel>> {
-        let client = ctx.data_unchecked::<AsyncTeamdeck>();
-        let endpoint = Project::builder()
-            .id(self.project_id as usize)
-            .build()
-            .unwrap();
+        if let Some(project) = &self.expanded_project {
+            return Ok(Some(project.clone()));
+        }
 
-        let project = endpoint.query_async(client).await?;
-        Ok(project)
+        let loader = ctx.data_unchecked::<DataLoader<ProjectLoader>>();
+        loader.load_one(self.project_id).await
     }
 
     async fn resource(&self, ctx: &Context<'_>) -> Result<Option<ResourceModel>> {
-        let client = ctx.data_unchecked::<AsyncTeamdeck>();
-        let endpoint = Resource::builder()
-            .id(self.resource_id as usize)
-            .build()
-            .unwrap();
+        if let Some(resource) = &self.expanded_resource {
+            return Ok(Some(resource.clone()));
+        }
+
+        let loader = ctx.data_unchecked::<DataLoader<ResourceLoader>>();
+        loader.load_one(self.resource_id).await
+    }
+
+    /// Tags of the entry, fetched from Teamdeck if the entry was loaded without them.
+    async fn tags(&self, ctx: &Context<'_>) -> Result<Vec<TimeEntryTagModel>> {
+        if let Some(tags) = &self.tags {
+            return Ok(tags.clone());
+        }
 
-        let resource = endpoint.query_async(client).await?;
-        Ok(resource)
+        let client = ctx.data_unchecked::<TeamdeckApiClient>();
+        Ok(client.get_time_entry_tags(self.id).await?)
     }
 
     async fn formatted_duration(&self) -> Result<String> {
-        let duration = Duration::minutes(self.minutes as i64);
-        let duration_in_seconds = duration.num_seconds();
-        let minutes = (duration_in_seconds / 60) % 60;
-        let hours = (duration_in_seconds / 60) / 60;
-        Ok(format!("{}:{:02}", hours, minutes))
+        Ok(format_minutes(self.minutes))
+    }
+
+    /// Logged time as a `Duration`, an alternative to `minutes`.
+    async fn duration(&self) -> Duration {
+        Duration::from_minutes(self.minutes)
+    }
+
+    /// Fraction (0 to 1) of the resource's total minutes logged on the entry's start date.
+    async fn share_of_day(&self, ctx: &Context<'_>) -> Result<f64> {
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+        let day_total = match ctx.data_opt::<DayTotalsCache>() {
+            Some(cache) => cache.get(client, self.resource_id, self.start_date).await?,
+            None => {
+                DayTotalsCache::default()
+                    .get(client, self.resource_id, self.start_date)
+                    .await?
+            }
+        };
+
+        Ok(day_total.share_of(self.minutes))
+    }
+
+    /// Kind of the booking, derived from the booking flags.
+    ///
+    /// When multiple flags are set, `Vacation` takes precedence over `Holiday`,
+    /// which takes precedence over `Weekend`.
+    async fn booking_type(&self) -> BookingType {
+        self.classify_booking()
+    }
+
+    /// How long ago the entry was created, e.g. `3 days ago`.
+    ///
+    /// `null` when Teamdeck did not provide the creation time of the entry.
+    async fn created_relative(&self, ctx: &Context<'_>) -> Option<String> {
+        let clock = ctx.data_unchecked::<Clock>();
+        self.created_at
+            .as_ref()
+            .map(|created_at| format_relative(created_at.0, clock.now()))
+    }
+}
+
+/// Deserializes RFC3339 or `YYYY-MM-DD HH:MM:SS` (UTC) datetime, ignoring unknown formats.
+fn optional_datetime<'de, D>(deserializer: D) -> Result<Option<DateTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.and_then(|value| {
+        ChronoDateTime::parse_from_rfc3339(&value)
+            .map(|datetime| datetime.with_timezone(&Utc))
+            .or_else(|_| {
+                NaiveDateTime::parse_from_str(&value, "%Y-%m-%d %H:%M:%S")
+                    .map(|datetime| ChronoDateTime::from_utc(datetime, Utc))
+            })
+            .ok()
+            .map(DateTime)
+    }))
+}
+
+/// Formats the time elapsed between `then` and `now` in a human readable form.
+fn format_relative(then: ChronoDateTime<Utc>, now: ChronoDateTime<Utc>) -> String {
+    let elapsed = now - then;
+    let (value, unit) = if elapsed.num_days() > 0 {
+        (elapsed.num_days(), "day")
+    } else if elapsed.num_hours() > 0 {
+        (elapsed.num_hours(), "hour")
+    } else if elapsed.num_minutes() > 0 {
+        (elapsed.num_minutes(), "minute")
+    } else {
+        return "just now".to_string();
+    };
+
+    if value == 1 {
+        format!("1 {} ago", unit)
+    } else {
+        format!("{} {}s ago", value, unit)
+    }
+}
+
+/// Formats minutes as `H:MM` string.
+pub(crate) fn format_minutes(minutes: u64) -> String {
+    Duration::from_minutes(minutes).to_string()
+}
+
+/// Narrows down the time entries request to the dates selected with the filter.
+fn apply_date_filters(builder: &mut TimeEntriesBuilder, filter: &TimeEntryFilter) {
+    if let Some(start_date) = &filter.start_date {
+        builder
+            .start_date_from(start_date.from.0)
+            .start_date_to(start_date.to.0);
+    }
+
+    if let Some(end_date) = &filter.end_date {
+        builder
+            .end_date_from(end_date.from.0)
+            .end_date_to(end_date.to.0);
+    }
+
+    if let Some(date) = filter.date {
+        builder.date(date.0);
+    }
+}
+
+/// Fetches all time entries of the resource with start date within the given range.
+pub(crate) async fn fetch_entries_in_range(
+    client: &AsyncTeamdeck,
+    resource_id: ResourceId,
+    range: &DateRange,
+) -> Result<Vec<TimeEntryModel>> {
+    let endpoint = TimeEntries::builder()
+        .resource_id(vec![resource_id.0])
+        .expand(TimeEntriesExpand::Tags)
+        .start_date_from(range.from.0)
+        .start_date_to(range.to.0)
+        .build()?;
+
+    let time_entries = paged(endpoint, Pagination::All).query_async(client).await?;
+    Ok(time_entries)
+}
+
+/// Fetches all time entries of the resource logged on the given date.
+pub(crate) async fn fetch_entries_on_date(
+    client: &AsyncTeamdeck,
+    resource_id: ResourceId,
+    date: Date,
+) -> Result<Vec<TimeEntryModel>> {
+    let endpoint = TimeEntries::builder()
+        .resource_id(vec![resource_id.0])
+        .expand(TimeEntriesExpand::Tags)
+        .date(date.0)
+        .build()?;
+
+    let time_entries = paged(endpoint, Pagination::All).query_async(client).await?;
+    Ok(time_entries)
+}
+
+/// Assigns fetched projects to the entries, so that resolving `project` does not query Teamdeck again.
+fn attach_projects(entries: &mut [TimeEntryModel], projects: &HashMap<u64, ProjectModel>) {
+    for entry in entries.iter_mut() {
+        entry.expanded_project = projects.get(&entry.project_id).cloned();
+    }
+}
+
+/// Fetches projects of the entries in a single batch with `fetch` and assigns them to the entries,
+/// unless `project` is not expanded.
+async fn expand_projects<F, Fut>(
+    entries: &mut [TimeEntryModel],
+    expand: &[EntryExpand],
+    fetch: F,
+) -> Result<()>
+where
+    F: FnOnce(Vec<u64>) -> Fut,
+    Fut: Future<Output = Result<HashMap<u64, ProjectModel>>>,
+{
+    if !expand.contains(&EntryExpand::Project) || entries.is_empty() {
+        return Ok(());
+    }
+
+    let project_ids = distinct_ids(entries.iter().map(|entry| entry.project_id));
+    let projects = fetch(project_ids).await?;
+    attach_projects(entries, &projects);
+    Ok(())
+}
+
+/// Assigns fetched resources to the entries, so that resolving `resource` does not query Teamdeck again.
+fn attach_resources(entries: &mut [TimeEntryModel], resources: &[ResourceModel]) {
+    for entry in entries.iter_mut() {
+        entry.expanded_resource = resources
+            .iter()
+            .find(|r| r.id == entry.resource_id)
+            .cloned();
+    }
+}
+
+impl TimeEntryModel {
+    fn ensure_creator(&self, resource_id: u64) -> Result<(), UpdateTimeEntryError> {
+        if self.resource_id != resource_id {
+            Err(UpdateTimeEntryError::NotACreator)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Number of days the entry spans, including its start and end dates.
+    fn days(&self) -> u64 {
+        ((self.end_date.0 - self.start_date.0).num_days() + 1).max(1) as u64
+    }
+
+    /// Builds the request updating only minutes of the entry, preserving its other data.
+    fn update_body_with_minutes(
+        &self,
+        minutes: u64,
+        editor_resource_id: u64,
+    ) -> UpdateTimeEntryBody {
+        UpdateTimeEntryBody {
+            project_id: self.project_id,
+            minutes,
+            weekend_booking: Some(self.weekend_booking),
+            holidays_booking: Some(self.holidays_booking),
+            vacations_booking: Some(self.vacations_booking),
+            description: self.description.clone(),
+            start_date: self.start_date.0,
+            end_date: self.end_date.0,
+            editor_resource_id,
+            tags: None,
+            external_id: None,
+        }
+    }
+
+    fn classify_booking(&self) -> BookingType {
+        if self.vacations_booking {
+            BookingType::Vacation
+        } else if self.holidays_booking {
+            BookingType::Holiday
+        } else if self.weekend_booking {
+            BookingType::Weekend
+        } else {
+            BookingType::Regular
+        }
+    }
+
+    fn has_external_id_prefix(&self, prefix: &str) -> bool {
+        self.external_id
+            .as_ref()
+            .map_or(false, |external_id| external_id.starts_with(prefix))
+    }
+
+    fn description_contains(&self, text: &str) -> bool {
+        if text.is_empty() {
+            return true;
+        }
+
+        self.description.as_ref().map_or(false, |description| {
+            description.to_lowercase().contains(&text.to_lowercase())
+        })
+    }
+
+    fn is_owned_by(&self, resource_id: ResourceId) -> bool {
+        self.resource_id == resource_id.0
+    }
+
+    /// Whether the entry is identical to the `source` entry moved by the offset.
+    fn is_copy_of(&self, source: &TimeEntryModel, offset: ChronoDuration) -> bool {
+        self.project_id == source.project_id
+            && self.minutes == source.minutes
+            && self.description == source.description
+            && self.start_date.0 == source.start_date.0 + offset
+            && self.end_date.0 == source.end_date.0 + offset
+    }
+
+    fn tag_ids(&self) -> Vec<u64> {
+        self.tags.iter().flatten().map(|tag| tag.id).collect()
+    }
+
+    /// Validates the copy of the entry moved by the offset the same way as a created entry.
+    fn check_copy(
+        &self,
+        offset: ChronoDuration,
+        today: NaiveDate,
+        grace_days: i64,
+        allow_future: bool,
+    ) -> Result<()> {
+        if !allow_future {
+            check_not_in_future(
+                self.start_date.0 + offset,
+                today,
+                grace_days,
+                "targetWeekStart",
+            )
+            .extend()?;
+        }
+        check_minutes(self.minutes).extend()?;
+        Ok(())
+    }
+
+    /// Builds the request creating a copy of the entry moved by the offset.
+    fn copy_body(&self, offset: ChronoDuration, tags: Vec<u64>) -> CreateTimeEntryBody {
+        CreateTimeEntryBody {
+            resource_id: self.resource_id,
+            project_id: self.project_id,
+            minutes: self.minutes,
+            weekend_booking: Some(self.weekend_booking),
+            holidays_booking: Some(self.holidays_booking),
+            vacations_booking: Some(self.vacations_booking),
+            description: self.description.clone(),
+            start_date: self.start_date.0 + offset,
+            end_date: self.end_date.0 + offset,
+            creator_resource_id: self.resource_id,
+            editor_resource_id: self.resource_id,
+            tags,
+            // the copy is not the entry synced from the external system
+            external_id: None,
+        }
+    }
+
+    fn has_tags(&self, tag_ids: &[u64], tag_match: TagMatch) -> bool {
+        let tags = match &self.tags {
+            Some(tags) if !tags.is_empty() => tags,
+            _ => return false,
+        };
+        let has_tag = |tag_id: &u64| tags.iter().any(|tag| tag.id == *tag_id);
+
+        match tag_match {
+            TagMatch::Any => tag_ids.iter().any(has_tag),
+            TagMatch::All => tag_ids.iter().all(has_tag),
+        }
+    }
+
+    fn with_raw(mut self, raw: serde_json::Value) -> Self {
+        self.raw = Some(Json(raw));
+        self
+    }
+}
+
+/// Returns the time entry with Teamdeck payload fetched by `fetch`, as visible to the user.
+///
+/// Entries of other resources are `None` for non-admins. The raw payload is included
+/// when `raw` is requested, which fails for non-admins before anything is fetched.
+pub(crate) async fn visible_time_entry(
+    ctx: &Context<'_>,
+    raw: bool,
+    fetch: impl Future<Output = Result<Option<serde_json::Value>>>,
+) -> Result<Option<TimeEntryModel>> {
+    if raw && !is_admin(ctx).await? {
+        return Err(AuthError::NotAnAdmin.into());
+    }
+
+    let payload = match fetch.await? {
+        Some(payload) => payload,
+        None => return Ok(None),
+    };
+    let time_entry: TimeEntryModel = serde_json::from_value(payload.clone())?;
+
+    let resource_id = *ctx.data_unchecked::<ResourceId>();
+    if !time_entry.is_owned_by(resource_id) && !is_admin(ctx).await? {
+        return Ok(None);
+    }
+
+    if raw {
+        Ok(Some(time_entry.with_raw(payload)))
+    } else {
+        Ok(Some(time_entry))
     }
 }
 
@@ -135,145 +696,842 @@ pub struct TimeEntryQuery;
 
 #[Object]
 impl TimeEntryQuery {
+    /// Time entry with the given ID, `null` when it does not exist.
+    ///
+    /// Entries of other resources are available only for admins, for other users they are `null`.
+    #[tracing::instrument(name = "Fetching time entry by id", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn time_entry(
+        &self,
+        ctx: &Context<'_>,
+        time_entry_id: u64,
+        #[graphql(desc = "Includes raw Teamdeck payload of the entry, available only for admins")]
+        raw: Option<bool>,
+    ) -> Result<Option<TimeEntryModel>> {
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+        let endpoint = TimeEntry::builder().id(time_entry_id as usize).build()?;
+
+        visible_time_entry(ctx, raw.unwrap_or(false), async move {
+            let payload: Option<serde_json::Value> = endpoint.query_async(client).await?;
+            Ok(payload)
+        })
+        .await
+    }
+
     #[tracing::instrument(name = "Fetching all time entries for resource", skip(ctx))]
     #[graphql(guard = "AccessTokenAuthGuard::default()")]
     async fn time_entries(
         &self,
         ctx: &Context<'_>,
         filter: TimeEntryFilter,
+        #[graphql(
+            desc = "Related data fetched eagerly in a single batch, defaults to `[TAGS]`",
+            default_with = "vec![EntryExpand::Tags]"
+        )]
+        expand: Vec<EntryExpand>,
     ) -> Result<Vec<TimeEntryModel>> {
+        filter.validate().extend()?;
         let resource_id = *ctx.data_unchecked::<ResourceId>();
         let client = ctx.data_unchecked::<AsyncTeamdeck>();
+        let cache_key = format!("time_entries:{}:{:?}:{:?}", resource_id.0, filter, expand);
 
         let mut builder = TimeEntries::builder();
-        builder
-            .resource_id(vec![resource_id.0])
-            .expand(TimeEntriesExpand::Tags);
-        if let Some(project) = filter.project_id {
+        builder.resource_id(vec![resource_id.0]);
+        // tags are needed to filter entries by them
+        if expand.contains(&EntryExpand::Tags) || filter.tag_id.is_some() {
+            builder.expand(TimeEntriesExpand::Tags);
+        }
+        if let Some(project) = filter.project_id.clone() {
             builder.project_id(project);
         }
 
-        if let Some(external_id) = filter.external_id {
+        if let Some(external_id) = filter.external_id.clone() {
             builder.external_id(external_id);
         }
 
-        if let Some(start_date) = filter.start_date {
-            builder
-                .start_date_from(start_date.from.0)
-                .start_date_to(start_date.to.0);
-        }
-
-        if let Some(end_date) = filter.end_date {
-            builder
-                .start_date_from(end_date.from.0)
-                .start_date_to(end_date.to.0);
-        }
+        apply_date_filters(&mut builder, &filter);
 
         if let Some(sort) = filter.sort {
             builder.sort(sort.into());
         }
 
-        if let Some(date) = filter.date {
-            builder.date(date.0);
-        }
-
         let endpoint = builder.build()?;
 
-        let time_entries: Vec<TimeEntryModel> =
-            paged(endpoint, Pagination::All).query_async(client).await?;
+        let mut time_entries: Vec<TimeEntryModel> = with_stale_fallback(ctx, &cache_key, async {
+            let time_entries = paged(endpoint, Pagination::All).query_async(client).await?;
+            Ok(time_entries)
+        })
+        .await?;
+
+        time_entries.retain(|entry| filter.matches(entry));
+
+        expand_projects(&mut time_entries, &expand, |project_ids| async move {
+            fetch_projects_by_id(client, &project_ids).await
+        })
+        .await?;
+
+        if expand.contains(&EntryExpand::Resource) && !time_entries.is_empty() {
+            let resources: Vec<ResourceModel> = fetch_resource(client, resource_id.0)
+                .await?
+                .into_iter()
+                .collect();
+            attach_resources(&mut time_entries, &resources);
+        }
 
         Ok(time_entries)
     }
+
+    /// Returns a single page of the authorized user's time entries along with pagination details.
+    ///
+    /// The page is selected with `filter.page`, defaulting to the first one.
+    /// Entries are filtered by `filter.external_id_prefix`, `filter.tag_id`, `filter.minutes`
+    /// and `filter.description_contains` after fetching the page,
+    /// so pagination details do not account for them.
+    #[tracing::instrument(name = "Fetching page of time entries for resource", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn time_entries_page(
+        &self,
+        ctx: &Context<'_>,
+        filter: TimeEntryFilter,
+    ) -> Result<TimeEntryConnection> {
+        filter.validate().extend()?;
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let client = ctx.data_unchecked::<TeamdeckApiClient>();
+
+        let params = TimeEntriesPageParams::from_filter(&filter, resource_id.0);
+        let page = client.time_entries_page(&params).await.extend()?;
+
+        let mut items = page.items;
+        items.retain(|entry| filter.matches(entry));
+
+        Ok(TimeEntryConnection {
+            items,
+            pagination: page.pagination,
+        })
+    }
 }
 
 #[derive(Default, Debug)]
 pub struct TimeEntryMutation;
 
-#[derive(InputObject, Debug, Serialize, Deserialize)]
-pub struct CreateTimeEntryInput {
-    pub project_id: u64,
-    pub weekend_booking: Option<bool>,
-    pub holidays_booking: Option<bool>,
-    pub vacations_booking: Option<bool>,
-    pub description: Option<String>,
-    pub minutes: Option<u64>,
-    pub date: Option<Date>,
-    #[graphql(validator(min_items = 1))]
-    pub tag_ids: Vec<u64>,
+/// Rejects tags which do not exist in Teamdeck, unless validation of tags is disabled.
+async fn check_tags_exist(ctx: &Context<'_>, tag_ids: Option<&[u64]>) -> Result<()> {
+    match tag_ids {
+        Some(tag_ids) => {
+            let client = ctx.data_unchecked::<AsyncTeamdeck>();
+            ctx.data_unchecked::<TagValidator>()
+                .check(client, tag_ids)
+                .await
+        }
+        None => Ok(()),
+    }
 }
 
-#[derive(InputObject, Debug, Serialize, Deserialize)]
-pub struct UpdateTimeEntryInput {
-    pub project_id: Option<u64>,
-    pub minutes: Option<u64>,
-    pub weekend_booking: Option<bool>,
-    pub holidays_booking: Option<bool>,
-    pub vacations_booking: Option<bool>,
-    pub description: Option<String>,
-    pub start_date: Option<Date>,
-    pub end_date: Option<Date>,
-    #[graphql(validator(min_items = 1))]
-    pub tag_ids: Option<Vec<u64>>,
+/// Assigns the tags to the time entry, reporting a failure as a `TAGS_NOT_UPDATED` warning.
+///
+/// The entry itself is kept, so the mutation still succeeds when only its tags could not be set.
+async fn update_tags_or_warn(ctx: &Context<'_>, time_entry_id: u64, tag_ids: Vec<u64>) {
+    let client = ctx.data_unchecked::<TeamdeckApiClient>();
+    if let Err(e) = client.update_time_entry_tags(time_entry_id, tag_ids).await {
+        error!(
+            "Failed to update tags of time entry {}: {:?}",
+            time_entry_id, e
+        );
+        if let Some(warnings) = ctx.data_opt::<Warnings>() {
+            warnings.push(Warning {
+                code: "TAGS_NOT_UPDATED",
+                message: e.to_string(),
+                time_entry_id: Some(time_entry_id),
+            });
+        }
+    }
 }
 
-#[derive(Debug, Error)]
-enum UpdateTimeEntryError {
-    #[error("You must be creator of the time entry to update it")]
-    NotACreator,
-}
+/// Creates the time entry, assigns the tags to it and returns the entry as stored in Teamdeck.
+pub(crate) async fn create_entry_with_tags(
+    ctx: &Context<'_>,
+    request_body: CreateTimeEntryBody,
+    tag_ids: Option<Vec<u64>>,
+) -> Result<TimeEntryModel> {
+    let client = ctx.data_unchecked::<TeamdeckApiClient>();
+    let created_entry = client.add_time_entry(request_body).await.extend()?;
 
-#[Object]
-impl TimeEntryMutation {
-    #[tracing::instrument(name = "Create time entry for authorized user", skip(ctx))]
-    #[graphql(guard = "AccessTokenAuthGuard::default()")]
-    async fn create_time_entry(
-        &self,
-        ctx: &Context<'_>,
-        time_entry: CreateTimeEntryInput,
-    ) -> Result<TimeEntryModel> {
-        let client = ctx.data_unchecked::<TeamdeckApiClient>();
-        let resource_id = *ctx.data_unchecked::<ResourceId>();
+    if let Some(tag_ids) = tag_ids {
+        update_tags_or_warn(ctx, created_entry.id, tag_ids).await;
+    }
 
-        let request_body = CreateTimeEntryBody::from_graphql_input(&time_entry, resource_id.into());
-        let created_entry = client.add_time_entry(request_body).await.extend()?;
+    let created_entry = client.get_time_entry(created_entry.id).await.extend()?;
 
-        let tag_ids = time_entry.tag_ids;
-        let _ = client
-            .update_time_entry_tags(created_entry.id, tag_ids)
-            .await
-            .extend()
-            .map_err(|e| error!("{:?}", e));
+    Ok(created_entry)
+}
 
-        let client = ctx.data_unchecked::<AsyncTeamdeck>();
-        let endpoint = TimeEntry::builder()
-            .id(created_entry.id as usize)
-            .build()
-            .unwrap();
+/// Creates the time entry of the authorized user from the mutation input.
+///
+/// When the input has an `idempotency_key` already used by the user,
+/// the entry created with it is returned instead.
+async fn create_time_entry_from_input(
+    ctx: &Context<'_>,
+    time_entry: &CreateTimeEntryInput,
+) -> Result<TimeEntryModel> {
+    let key = match &time_entry.idempotency_key {
+        Some(key) => key,
+        None => return create_new_time_entry(ctx, time_entry).await,
+    };
 
-        let created_entry = endpoint.query_async(client).await?;
+    let resource_id = *ctx.data_unchecked::<ResourceId>();
+    let created = ctx
+        .data_unchecked::<IdempotencyKeys>()
+        .create_once(
+            resource_id.0,
+            key,
+            || create_new_time_entry(ctx, time_entry),
+            |entry: &TimeEntryModel| entry.id,
+        )
+        .await?;
 
-        Ok(created_entry)
+    match created {
+        Idempotent::Created(entry) => Ok(entry),
+        Idempotent::Replayed(time_entry_id) => {
+            let client = ctx.data_unchecked::<TeamdeckApiClient>();
+            client.get_time_entry(time_entry_id).await.extend()
+        }
     }
+}
 
-    #[tracing::instrument(name = "Update time entry", skip(ctx))]
-    #[graphql(guard = "AccessTokenAuthGuard::default()")]
-    async fn update_time_entry(
-        &self,
-        ctx: &Context<'_>,
-        time_entry_id: u64,
-        update_data: UpdateTimeEntryInput,
-    ) -> Result<TimeEntryModel> {
-        let client = ctx.data_unchecked::<TeamdeckApiClient>();
+async fn create_new_time_entry(
+    ctx: &Context<'_>,
+    time_entry: &CreateTimeEntryInput,
+) -> Result<TimeEntryModel> {
+    let resource_id = *ctx.data_unchecked::<ResourceId>();
+    let tag_policy = ctx.data_unchecked::<TagPolicy>();
+
+    let tag_ids = tag_policy
+        .resolve(time_entry.tag_ids.clone(), TagOperation::Create)
+        .extend()?;
+    let minutes = minutes_from_clock_times(
+        time_entry.minutes,
+        time_entry.start_time.as_ref(),
+        time_entry.end_time.as_ref(),
+    )
+    .extend()?;
+
+    let clock = ctx.data_unchecked::<Clock>();
+    let (start_date, end_date) = time_entry
+        .dates(clock.today(), future_grace_days())
+        .extend()?;
+
+    let timer = ctx
+        .data_unchecked::<Timers>()
+        .latest_started_on(resource_id.0, start_date);
+    let default_minutes = ctx.data_unchecked::<DefaultMinutes>().0;
+    let minutes =
+        resolve_minutes(minutes, timer.as_ref(), clock.now(), default_minutes).extend()?;
+    check_tags_exist(ctx, tag_ids.as_deref()).await?;
+
+    let request_body = CreateTimeEntryBody::from_graphql_input(
+        time_entry,
+        resource_id.into(),
+        (start_date, end_date),
+        minutes,
+        tag_ids.clone().unwrap_or_default(),
+    );
+    create_entry_with_tags(ctx, request_body, tag_ids).await
+}
+
+/// Number of time entries created concurrently by `create_time_entries`.
+const CREATE_TIME_ENTRIES_CONCURRENCY: usize = 4;
+
+#[derive(SimpleObject, Debug)]
+pub struct CreateTimeEntriesResult {
+    /// Created time entries, in the order of the input list.
+    created: Vec<TimeEntryModel>,
+    errors: Vec<BatchItemError>,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct CopyWeekResult {
+    /// Created copies, ordered by start date of the copied entries.
+    created: Vec<TimeEntryModel>,
+    /// Number of entries not copied, as identical ones were already logged in the target week.
+    skipped: u64,
+    /// Errors of entries which could not be copied, indexed by position of the copied entry
+    /// among entries of the source week ordered by start date.
+    errors: Vec<BatchItemError>,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct AdjustMinutesResult {
+    /// Entries which minutes have changed, ordered by their start date.
+    updated: Vec<TimeEntryModel>,
+    /// Errors of entries which minutes could not be adjusted, indexed by position of the entry
+    /// among the matching entries ordered by start date.
+    errors: Vec<BatchItemError>,
+}
+
+#[derive(SimpleObject, Debug)]
+pub struct AssignTagsResult {
+    /// Entries with the tags assigned, in the order of the input list.
+    updated: Vec<TimeEntryModel>,
+    /// Errors of entries which tags could not be assigned, indexed by position in the input list.
+    errors: Vec<BatchItemError>,
+}
+
+/// Returns range of the week starting on the given date.
+fn week_range(week_start: NaiveDate) -> DateRange {
+    DateRange {
+        from: Date(week_start),
+        to: Date(week_start + ChronoDuration::days(6)),
+    }
+}
+
+/// Plans copies of the source entries moved by the offset, along with their index among
+/// the source entries, skipping those identical to any of the existing entries.
+///
+/// Returns the planned copies and the number of skipped entries.
+fn plan_week_copy<'a>(
+    source: &'a [TimeEntryModel],
+    existing: &[TimeEntryModel],
+    offset: ChronoDuration,
+) -> (Vec<(usize, &'a TimeEntryModel)>, u64) {
+    let mut copies = vec![];
+    let mut skipped = 0;
+    for (index, entry) in source.iter().enumerate() {
+        if existing
+            .iter()
+            .any(|existing| existing.is_copy_of(entry, offset))
+        {
+            skipped += 1;
+        } else {
+            copies.push((index, entry));
+        }
+    }
+
+    (copies, skipped)
+}
+
+/// Copies the source entries moved by the offset, skipping those identical to any of
+/// the existing entries.
+///
+/// The copies are checked against the `BatchLimit` and the rate limit before any is created.
+async fn copy_entries(
+    ctx: &Context<'_>,
+    source: &[TimeEntryModel],
+    existing: &[TimeEntryModel],
+    offset: ChronoDuration,
+    allow_future: bool,
+) -> Result<CopyWeekResult> {
+    let resource_id = *ctx.data_unchecked::<ResourceId>();
+    let (copies, skipped) = plan_week_copy(source, existing, offset);
+    ctx.data_unchecked::<BatchLimit>().check(&copies).extend()?;
+    ctx.data_unchecked::<MutationRateLimiter>()
+        .check(resource_id, copies.len())
+        .extend()?;
+
+    let results =
+        stream::iter(copies)
+            .map(|(index, entry)| async move {
+                (index, copy_entry(ctx, entry, offset, allow_future).await)
+            })
+            .buffer_unordered(CREATE_TIME_ENTRIES_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+    let (created, errors) = partition_results(results);
+
+    Ok(CopyWeekResult {
+        created,
+        skipped,
+        errors,
+    })
+}
+
+/// Assigns the tags to the entry owned by the authorized user.
+async fn assign_entry_tags(
+    ctx: &Context<'_>,
+    time_entry_id: u64,
+    tag_ids: &[u64],
+    mode: TagAssignmentMode,
+) -> Result<TimeEntryModel> {
+    let client = ctx.data_unchecked::<TeamdeckApiClient>();
+    let resource_id = *ctx.data_unchecked::<ResourceId>();
+
+    let time_entry = client.get_time_entry(time_entry_id).await.extend()?;
+    time_entry.ensure_creator(resource_id.0)?;
+
+    let existing = match mode {
+        TagAssignmentMode::Add => client
+            .get_time_entry_tags(time_entry_id)
+            .await
+            .extend()?
+            .into_iter()
+            .map(|tag| tag.id)
+            .collect(),
+        TagAssignmentMode::Replace => vec![],
+    };
+    client
+        .update_time_entry_tags(time_entry_id, mode.assigned_tags(existing, tag_ids))
+        .await
+        .extend()?;
+
+    client.get_time_entry(time_entry_id).await.extend()
+}
+
+/// Creates a copy of the time entry moved by the offset, with tags handled by the `TagPolicy`.
+async fn copy_entry(
+    ctx: &Context<'_>,
+    entry: &TimeEntryModel,
+    offset: ChronoDuration,
+    allow_future: bool,
+) -> Result<TimeEntryModel> {
+    let clock = ctx.data_unchecked::<Clock>();
+    entry.check_copy(offset, clock.today(), future_grace_days(), allow_future)?;
+
+    let tag_ids = ctx
+        .data_unchecked::<TagPolicy>()
+        .resolve(Some(entry.tag_ids()), TagOperation::Create)
+        .extend()?;
+    let request_body = entry.copy_body(offset, tag_ids.clone().unwrap_or_default());
+    create_entry_with_tags(ctx, request_body, tag_ids).await
+}
+
+#[derive(InputObject, Debug, Serialize, Deserialize)]
+pub struct CreateTimeEntryInput {
+    pub project_id: u64,
+    pub weekend_booking: Option<bool>,
+    pub holidays_booking: Option<bool>,
+    pub vacations_booking: Option<bool>,
+    pub description: Option<String>,
+    pub minutes: Option<u64>,
+    /// Time the work started at, used with `end_time` to compute `minutes`.
+    ///
+    /// Cannot be used together with `minutes`.
+    pub start_time: Option<Time>,
+    /// Time the work ended at, must be later than `start_time` of the same day.
+    pub end_time: Option<Time>,
+    /// Date of a single-day entry, today by default.
+    pub date: Option<Date>,
+    /// First day of a multi-day entry, `date` by default.
+    pub start_date: Option<Date>,
+    /// Last day of a multi-day entry, `start_date` by default.
+    pub end_date: Option<Date>,
+    /// Tags of the time entry, handled according to the configured `TagPolicy`.
+    pub tag_ids: Option<Vec<u64>>,
+    /// ID of the entry in an external system it's synced with.
+    pub external_id: Option<String>,
+    /// Allows logging time on future dates, which are rejected by default.
+    pub allow_future: Option<bool>,
+    /// Key identifying the request, e.g. a random UUID generated by the client.
+    ///
+    /// Repeating the request with the same key within 10 minutes returns the entry
+    /// created by the first request, instead of creating a duplicate.
+    /// While the first request is still in progress, the repeated one fails
+    /// with an `IDEMPOTENCY_KEY_IN_PROGRESS` error.
+    pub idempotency_key: Option<String>,
+}
+
+impl CreateTimeEntryInput {
+    /// Returns the start and end dates of the created entry.
+    ///
+    /// Unless `allow_future` is set, the start date cannot be later than `grace_days` after today.
+    fn dates(
+        &self,
+        today: NaiveDate,
+        grace_days: i64,
+    ) -> Result<(NaiveDate, NaiveDate), EntryDatesError> {
+        let start_date = self.start_date.or(self.date).map_or(today, |date| date.0);
+        let end_date = self.end_date.map_or(start_date, |date| date.0);
+
+        if !self.allow_future.unwrap_or(false) {
+            let field = if self.start_date.is_some() {
+                "startDate"
+            } else {
+                "date"
+            };
+            check_not_in_future(start_date, today, grace_days, field)?;
+        }
+        check_entry_dates(start_date, end_date)?;
+        Ok((start_date, end_date))
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+enum EntryDatesError {
+    #[error("`end_date` cannot be earlier than `start_date`")]
+    EndBeforeStart,
+    #[error("Date {date} is in the future, set `allow_future` to log time on it")]
+    InFuture {
+        field: &'static str,
+        date: NaiveDate,
+    },
+}
+
+impl ErrorExtensions for EntryDatesError {
+    fn extend(&self) -> FieldError {
+        let field = match self {
+            EntryDatesError::EndBeforeStart => "endDate",
+            EntryDatesError::InFuture { field, .. } => *field,
+        };
+        ValidationError::new(field, self).extend()
+    }
+}
+
+const FUTURE_GRACE_DAYS_ENV_VARIABLE: &str = "FUTURE_TIME_ENTRY_GRACE_DAYS";
+
+/// Number of days after today on which time entries can be created without `allow_future`,
+/// configured with the `FUTURE_TIME_ENTRY_GRACE_DAYS` env variable (1 by default,
+/// so that users in timezones ahead of the server can log their today's time).
+fn future_grace_days() -> i64 {
+    std::env::var(FUTURE_GRACE_DAYS_ENV_VARIABLE)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(1)
+}
+
+/// Rejects start dates later than `grace_days` after today, reporting them on the `field`.
+fn check_not_in_future(
+    start_date: NaiveDate,
+    today: NaiveDate,
+    grace_days: i64,
+    field: &'static str,
+) -> Result<(), EntryDatesError> {
+    if start_date > today + ChronoDuration::days(grace_days) {
+        return Err(EntryDatesError::InFuture {
+            field,
+            date: start_date,
+        });
+    }
+
+    Ok(())
+}
+
+fn check_entry_dates(start_date: NaiveDate, end_date: NaiveDate) -> Result<(), EntryDatesError> {
+    if end_date < start_date {
+        return Err(EntryDatesError::EndBeforeStart);
+    }
+
+    Ok(())
+}
+
+#[derive(InputObject, Debug, Serialize, Deserialize)]
+pub struct UpdateTimeEntryInput {
+    pub project_id: Option<u64>,
+    pub minutes: Option<u64>,
+    pub weekend_booking: Option<bool>,
+    pub holidays_booking: Option<bool>,
+    pub vacations_booking: Option<bool>,
+    pub description: Option<String>,
+    pub start_date: Option<Date>,
+    pub end_date: Option<Date>,
+    /// Time the work started at, used with `end_time` to compute `minutes`.
+    ///
+    /// Cannot be used together with `minutes`.
+    pub start_time: Option<Time>,
+    /// Time the work ended at, must be later than `start_time` of the same day.
+    pub end_time: Option<Time>,
+    /// Tags of the time entry, handled according to the configured `TagPolicy`.
+    ///
+    /// Omitting the field leaves tags of the entry untouched,
+    /// while an empty list clears them (unless tags are required by the policy).
+    pub tag_ids: Option<Vec<u64>>,
+    /// ID of the entry in an external system it's synced with.
+    ///
+    /// Omitting the field keeps the current ID, while `null` clears it.
+    #[serde(default)]
+    pub external_id: MaybeUndefined<String>,
+}
+
+/// Converts the `external_id` input to the change of the ID sent to Teamdeck,
+/// `None` meaning the ID is kept.
+fn external_id_change(external_id: MaybeUndefined<String>) -> Option<Option<String>> {
+    match external_id {
+        MaybeUndefined::Undefined => None,
+        MaybeUndefined::Null => Some(None),
+        MaybeUndefined::Value(external_id) => Some(Some(external_id)),
+    }
+}
+
+#[derive(Debug, Error)]
+enum UpdateTimeEntryError {
+    #[error("You must be creator of the time entry to update it")]
+    NotACreator,
+}
+
+#[derive(Debug, Error)]
+enum DeleteTimeEntryError {
+    #[error("You must be creator of the time entry to delete it")]
+    NotACreator,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+enum ClockTimeError {
+    #[error("Both `start_time` and `end_time` must be provided")]
+    MissingBound,
+    #[error("`end_time` must be later than `start_time`")]
+    EndBeforeStart,
+    #[error("`minutes` cannot be used together with `start_time` and `end_time`")]
+    ConflictingMinutes,
+}
+
+impl ErrorExtensions for ClockTimeError {
+    fn extend(&self) -> FieldError {
+        let field = match self {
+            ClockTimeError::MissingBound => "startTime",
+            ClockTimeError::EndBeforeStart => "endTime",
+            ClockTimeError::ConflictingMinutes => "minutes",
+        };
+        ValidationError::new(field, self).extend()
+    }
+}
+
+const MIN_ENTRY_MINUTES: u64 = 1;
+const MAX_ENTRY_MINUTES: u64 = 24 * 60;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+enum AdjustMinutesError {
+    #[error("Either `delta` or `factor` must be provided")]
+    MissingAdjustment,
+    #[error("`delta` and `factor` cannot be used together")]
+    ConflictingAdjustments,
+    #[error("`factor` must be a positive number")]
+    InvalidFactor,
+}
+
+impl ErrorExtensions for AdjustMinutesError {
+    fn extend(&self) -> FieldError {
+        let field = match self {
+            AdjustMinutesError::MissingAdjustment | AdjustMinutesError::ConflictingAdjustments => {
+                "delta"
+            }
+            AdjustMinutesError::InvalidFactor => "factor",
+        };
+        ValidationError::new(field, self).extend()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MinutesAdjustment {
+    Delta(i64),
+    Factor(f64),
+}
+
+impl MinutesAdjustment {
+    fn new(delta: Option<i64>, factor: Option<f64>) -> Result<Self, AdjustMinutesError> {
+        match (delta, factor) {
+            (Some(delta), None) => Ok(MinutesAdjustment::Delta(delta)),
+            (None, Some(factor)) if factor.is_finite() && factor > 0.0 => {
+                Ok(MinutesAdjustment::Factor(factor))
+            }
+            (None, Some(_)) => Err(AdjustMinutesError::InvalidFactor),
+            (Some(_), Some(_)) => Err(AdjustMinutesError::ConflictingAdjustments),
+            (None, None) => Err(AdjustMinutesError::MissingAdjustment),
+        }
+    }
+
+    /// Returns adjusted minutes, clamped to the valid range of minutes of an entry
+    /// spanning the given number of days.
+    fn apply(&self, minutes: u64, days: u64) -> u64 {
+        let adjusted = match self {
+            MinutesAdjustment::Delta(delta) => (minutes as i64).saturating_add(*delta),
+            // casting saturates, so huge products are clamped like other too large values
+            MinutesAdjustment::Factor(factor) => (minutes as f64 * factor).round() as i64,
+        };
+
+        adjusted.clamp(
+            MIN_ENTRY_MINUTES as i64,
+            (MAX_ENTRY_MINUTES * days.max(1)) as i64,
+        ) as u64
+    }
+}
+
+const DEFAULT_MINUTES_ENV_VARIABLE: &str = "DEFAULT_TIME_ENTRY_MINUTES";
+
+/// Minutes of created time entries when they cannot be determined otherwise,
+/// configured with the `DEFAULT_TIME_ENTRY_MINUTES` env variable.
+///
+/// Without the variable, creating such entries fails instead of logging placeholder minutes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DefaultMinutes(pub Option<u64>);
+
+impl DefaultMinutes {
+    pub fn from_env() -> Self {
+        match std::env::var(DEFAULT_MINUTES_ENV_VARIABLE) {
+            Ok(value) => DefaultMinutes(Some(value.parse().unwrap_or_else(|_| {
+                panic!(
+                    "Invalid {} env variable: {}",
+                    DEFAULT_MINUTES_ENV_VARIABLE, value
+                )
+            }))),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+enum MinutesError {
+    #[error("`minutes` (or `start_time` and `end_time`) must be provided")]
+    Missing,
+    #[error("`minutes` must be greater than 0")]
+    Zero,
+}
+
+impl ErrorExtensions for MinutesError {
+    fn extend(&self) -> FieldError {
+        ValidationError::new("minutes", self).extend()
+    }
+}
+
+fn check_minutes(minutes: u64) -> Result<u64, MinutesError> {
+    if minutes == 0 {
+        return Err(MinutesError::Zero);
+    }
+
+    Ok(minutes)
+}
+
+/// Resolves minutes of a created time entry, in order of precedence:
+///
+/// 1. minutes given explicitly, or computed from `start_time` and `end_time`,
+/// 2. elapsed minutes of the latest timer of the resource started on the entry date,
+/// 3. the configured default.
+fn resolve_minutes(
+    minutes: Option<u64>,
+    timer: Option<&Timer>,
+    now: ChronoDateTime<Utc>,
+    default: Option<u64>,
+) -> Result<u64, MinutesError> {
+    let minutes = minutes
+        .or_else(|| timer.map(|timer| timer.elapsed_minutes_at(now)))
+        .or(default)
+        .ok_or(MinutesError::Missing)?;
+
+    check_minutes(minutes)
+}
+
+/// Returns minutes computed from the clock times,
+/// or the given `minutes` when no clock times were provided.
+fn minutes_from_clock_times(
+    minutes: Option<u64>,
+    start_time: Option<&Time>,
+    end_time: Option<&Time>,
+) -> Result<Option<u64>, ClockTimeError> {
+    match (start_time, end_time) {
+        (None, None) => Ok(minutes),
+        (Some(start_time), Some(end_time)) => {
+            if minutes.is_some() {
+                return Err(ClockTimeError::ConflictingMinutes);
+            }
+
+            let duration = start_time.duration_to(end_time);
+            if duration.num_minutes() <= 0 {
+                Err(ClockTimeError::EndBeforeStart)
+            } else {
+                Ok(Some(duration.num_minutes() as u64))
+            }
+        }
+        _ => Err(ClockTimeError::MissingBound),
+    }
+}
+
+#[Object]
+impl TimeEntryMutation {
+    #[tracing::instrument(name = "Create time entry for authorized user", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn create_time_entry(
+        &self,
+        ctx: &Context<'_>,
+        time_entry: CreateTimeEntryInput,
+    ) -> Result<TimeEntryModel> {
         let resource_id = *ctx.data_unchecked::<ResourceId>();
-        let resource_id = resource_id.0;
+        ctx.data_unchecked::<MutationRateLimiter>()
+            .check(resource_id, 1)
+            .extend()?;
+
+        create_time_entry_from_input(ctx, &time_entry).await
+    }
+
+    /// Creates multiple time entries for the authorized user.
+    ///
+    /// Failure of a single entry does not prevent creating the other ones,
+    /// errors are reported along with the index of the failed entry.
+    #[tracing::instrument(name = "Create time entries for authorized user", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn create_time_entries(
+        &self,
+        ctx: &Context<'_>,
+        time_entries: Vec<CreateTimeEntryInput>,
+    ) -> Result<CreateTimeEntriesResult> {
+        ctx.data_unchecked::<BatchLimit>()
+            .check(&time_entries)
+            .extend()?;
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        ctx.data_unchecked::<MutationRateLimiter>()
+            .check(resource_id, time_entries.len())
+            .extend()?;
+
+        let results = stream::iter(time_entries.iter().enumerate())
+            .map(|(index, time_entry)| async move {
+                (index, create_time_entry_from_input(ctx, time_entry).await)
+            })
+            .buffer_unordered(CREATE_TIME_ENTRIES_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        let (created, errors) = partition_results(results);
+
+        Ok(CreateTimeEntriesResult { created, errors })
+    }
 
+    /// Copies time entries of the authorized user logged in the week starting on
+    /// `source_week_start` to the corresponding days of the week starting on `target_week_start`.
+    ///
+    /// Copies are validated like created entries, failure of a single entry (e.g. one which
+    /// copy would be in the future) does not prevent copying the other ones.
+    #[tracing::instrument(name = "Copy week of time entries for authorized user", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn copy_week(
+        &self,
+        ctx: &Context<'_>,
+        source_week_start: Date,
+        target_week_start: Date,
+        #[graphql(
+            desc = "Whether to skip entries identical to ones already logged on the target day",
+            default = true
+        )]
+        skip_existing: bool,
+        #[graphql(
+            desc = "Whether to copy entries to future dates, which are rejected by default",
+            default = false
+        )]
+        allow_future: bool,
+    ) -> Result<CopyWeekResult> {
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
         let td = ctx.data_unchecked::<AsyncTeamdeck>();
-        let endpoint = TimeEntry::builder()
-            .id(time_entry_id as usize)
-            .build()
-            .unwrap();
 
-        let time_entry: TimeEntryModel = endpoint.query_async(td).await?;
+        let mut source =
+            fetch_entries_in_range(td, resource_id, &week_range(source_week_start.0)).await?;
+        source.sort_by_key(|entry| (entry.start_date.0, entry.id));
+        let existing = if skip_existing {
+            fetch_entries_in_range(td, resource_id, &week_range(target_week_start.0)).await?
+        } else {
+            vec![]
+        };
+
+        let offset = target_week_start.0 - source_week_start.0;
+        copy_entries(ctx, &source, &existing, offset, allow_future).await
+    }
+
+    #[tracing::instrument(name = "Update time entry", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn update_time_entry(
+        &self,
+        ctx: &Context<'_>,
+        time_entry_id: u64,
+        update_data: UpdateTimeEntryInput,
+    ) -> Result<TimeEntryModel> {
+        let client = ctx.data_unchecked::<TeamdeckApiClient>();
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let resource_id = resource_id.0;
+        let tag_policy = ctx.data_unchecked::<TagPolicy>();
+
+        let time_entry = client.get_time_entry(time_entry_id).await.extend()?;
 
         if time_entry.resource_id != resource_id {
             Err(UpdateTimeEntryError::NotACreator.into())
@@ -287,8 +1545,22 @@ impl TimeEntryMutation {
                 description,
                 start_date,
                 end_date,
+                start_time,
+                end_time,
                 tag_ids,
+                external_id,
             } = update_data;
+            let tag_ids = tag_policy.resolve(tag_ids, TagOperation::Update).extend()?;
+            let minutes = minutes_from_clock_times(minutes, start_time.as_ref(), end_time.as_ref())
+                .extend()?
+                .map(check_minutes)
+                .transpose()
+                .extend()?;
+            let start_date = start_date.map_or(time_entry.start_date.0, |d| d.0);
+            let end_date = end_date.map_or(time_entry.end_date.0, |d| d.0);
+            check_entry_dates(start_date, end_date).extend()?;
+            check_tags_exist(ctx, tag_ids.as_deref()).await?;
+
             let mut updated_entry = client
                 .update_time_entry(
                     time_entry_id,
@@ -299,28 +1571,1042 @@ impl TimeEntryMutation {
                         holidays_booking,
                         vacations_booking,
                         description,
-                        start_date: start_date.map(|d| d.0).unwrap_or(time_entry.start_date.0),
-                        end_date: end_date.map(|d| d.0).unwrap_or(time_entry.end_date.0),
+                        start_date,
+                        end_date,
                         editor_resource_id: resource_id,
                         tags: tag_ids.clone(),
+                        external_id: external_id_change(external_id),
                     },
                 )
                 .await
                 .extend()?;
 
             if let Some(tags) = tag_ids {
-                if !tags.is_empty() {
-                    let _ = client
-                        .update_time_entry_tags(time_entry_id, tags)
-                        .await
-                        .extend()
-                        .map_err(|e| error!("{:?}", e));
-
-                    updated_entry = endpoint.query_async(td).await?;
-                }
+                update_tags_or_warn(ctx, time_entry_id, tags).await;
+
+                updated_entry = client.get_time_entry(time_entry_id).await.extend()?;
             }
 
             Ok(updated_entry)
         }
     }
+
+    #[tracing::instrument(name = "Clear time entry tags", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn clear_time_entry_tags(
+        &self,
+        ctx: &Context<'_>,
+        time_entry_id: u64,
+    ) -> Result<TimeEntryModel> {
+        let client = ctx.data_unchecked::<TeamdeckApiClient>();
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+
+        let time_entry = client.get_time_entry(time_entry_id).await.extend()?;
+        time_entry.ensure_creator(resource_id.0)?;
+
+        client.clear_time_entry_tags(time_entry_id).await.extend()
+    }
+
+    /// Assigns the tags to multiple entries of the authorized user, e.g. to categorize
+    /// a week of entries at once.
+    ///
+    /// Failure of a single entry (e.g. one not owned by the user) does not prevent
+    /// assigning tags to the other ones. Nothing is changed when tags are ignored by the `TagPolicy`.
+    #[tracing::instrument(name = "Assign tags to time entries", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn assign_tags(
+        &self,
+        ctx: &Context<'_>,
+        time_entry_ids: Vec<u64>,
+        tag_ids: Vec<u64>,
+        mode: TagAssignmentMode,
+    ) -> Result<AssignTagsResult> {
+        ctx.data_unchecked::<BatchLimit>()
+            .check(&time_entry_ids)
+            .extend()?;
+        let tag_ids = match ctx
+            .data_unchecked::<TagPolicy>()
+            .resolve(Some(tag_ids), TagOperation::Update)
+            .extend()?
+        {
+            Some(tag_ids) => tag_ids,
+            None => {
+                return Ok(AssignTagsResult {
+                    updated: vec![],
+                    errors: vec![],
+                })
+            }
+        };
+        check_tags_exist(ctx, Some(&tag_ids)).await?;
+
+        let tag_ids = &tag_ids;
+        let results = stream::iter(time_entry_ids.into_iter().enumerate())
+            .map(|(index, time_entry_id)| async move {
+                (
+                    index,
+                    assign_entry_tags(ctx, time_entry_id, tag_ids, mode).await,
+                )
+            })
+            .buffer_unordered(CREATE_TIME_ENTRIES_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        let (updated, errors) = partition_results(results);
+
+        Ok(AssignTagsResult { updated, errors })
+    }
+
+    /// Adjusts minutes of the authorized user's entries starting within the date range
+    /// by an absolute `delta` or a multiplicative `factor`.
+    ///
+    /// Adjusted minutes are clamped to the range of 1 to 1440 minutes per day spanned by the entry.
+    /// Failure of a single entry does not prevent adjusting the other ones.
+    #[tracing::instrument(name = "Adjust minutes of time entries", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn adjust_minutes(
+        &self,
+        ctx: &Context<'_>,
+        date_range: DateRange,
+        project_id: Option<u64>,
+        delta: Option<i64>,
+        factor: Option<f64>,
+    ) -> Result<AdjustMinutesResult> {
+        date_range
+            .validate()
+            .map_err(|e| ValidationError::new("dateRange", e))
+            .extend()?;
+        let adjustment = MinutesAdjustment::new(delta, factor).extend()?;
+        let client = ctx.data_unchecked::<TeamdeckApiClient>();
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+        let td = ctx.data_unchecked::<AsyncTeamdeck>();
+
+        let mut entries = fetch_entries_in_range(td, resource_id, &date_range).await?;
+        if let Some(project_id) = project_id {
+            entries.retain(|e| e.project_id == project_id);
+        }
+        ctx.data_unchecked::<BatchLimit>()
+            .check(&entries)
+            .extend()?;
+
+        entries.sort_by_key(|e| e.start_date.0);
+
+        let changes = entries.iter().enumerate().filter_map(|(index, entry)| {
+            let minutes = adjustment.apply(entry.minutes, entry.days());
+            (minutes != entry.minutes).then(|| (index, entry, minutes))
+        });
+        let results = stream::iter(changes)
+            .map(|(index, entry, minutes)| async move {
+                let body = entry.update_body_with_minutes(minutes, resource_id.0);
+                (
+                    index,
+                    client.update_time_entry(entry.id, &body).await.extend(),
+                )
+            })
+            .buffer_unordered(CREATE_TIME_ENTRIES_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+        let (updated, errors) = partition_results(results);
+
+        Ok(AdjustMinutesResult { updated, errors })
+    }
+
+    /// Deletes the time entry of the authorized user and returns its ID.
+    #[tracing::instrument(name = "Delete time entry", skip(ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn delete_time_entry(&self, ctx: &Context<'_>, time_entry_id: u64) -> Result<u64> {
+        let client = ctx.data_unchecked::<TeamdeckApiClient>();
+        let resource_id = *ctx.data_unchecked::<ResourceId>();
+
+        let time_entry = client.get_time_entry(time_entry_id).await.extend()?;
+        if time_entry.resource_id != resource_id.0 {
+            return Err(DeleteTimeEntryError::NotACreator.into());
+        }
+
+        client.delete_time_entry(time_entry_id).await.extend()?;
+        Ok(time_entry_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::auth::role::Role;
+    use crate::auth::token::AccessToken;
+    use async_graphql::{EmptySubscription, ObjectType, Request, Schema, Value};
+    use serde_json::json;
+
+    fn entry(id: u64, project_id: u64) -> TimeEntryModel {
+        serde_json::from_value(json!({
+            "id": id,
+            "resource_id": 1,
+            "project_id": project_id,
+            "minutes": 60,
+            "weekend_booking": false,
+            "holidays_booking": false,
+            "vacations_booking": false,
+            "description": null,
+            "external_id": null,
+            "start_date": "2022-01-10",
+            "end_date": "2022-01-10",
+            "creator_resource_id": 1,
+            "editor_resource_id": 1,
+            "tags": null,
+        }))
+        .unwrap()
+    }
+
+    fn project(id: u64) -> ProjectModel {
+        serde_json::from_value(json!({
+            "id": id,
+            "name": "Tracker",
+            "color": "#000000",
+            "archived": false,
+        }))
+        .unwrap()
+    }
+
+    fn time(value: &str) -> Time {
+        Time(chrono::NaiveTime::parse_from_str(value, "%H:%M").unwrap())
+    }
+
+    fn date(day: u32) -> Date {
+        Date(NaiveDate::from_ymd_opt(2022, 1, day).unwrap())
+    }
+
+    fn entry_on(id: u64, day: u32, description: Option<&str>) -> TimeEntryModel {
+        TimeEntryModel {
+            start_date: date(day),
+            end_date: date(day),
+            description: description.map(|d| d.to_string()),
+            ..entry(id, 1)
+        }
+    }
+
+    #[test]
+    fn test_entry_ownership() {
+        let entry = entry(1, 1);
+        assert!(entry.is_owned_by(ResourceId(1)));
+        assert!(!entry.is_owned_by(ResourceId(2)));
+    }
+
+    #[test]
+    fn test_copy_body_preserves_day_of_week() {
+        let source = entry_on(1, 12, Some("Review"));
+        let body = source.copy_body(ChronoDuration::days(7), vec![3]);
+
+        assert_eq!(body.start_date, date(19).0);
+        assert_eq!(body.end_date, date(19).0);
+        assert_eq!(body.minutes, 60);
+        assert_eq!(body.description.as_deref(), Some("Review"));
+        assert_eq!(body.tags, vec![3]);
+    }
+
+    #[test]
+    fn test_copy_is_validated_like_created_entry() {
+        let today = date(12).0;
+        let source = entry_on(1, 12, None);
+
+        assert!(source
+            .check_copy(ChronoDuration::days(-7), today, 1, false)
+            .is_ok());
+        let error = source
+            .check_copy(ChronoDuration::days(7), today, 1, false)
+            .unwrap_err();
+        assert_eq!(
+            error.extensions.unwrap().get("field"),
+            Some(&async_graphql::Value::from("targetWeekStart"))
+        );
+        assert!(source
+            .check_copy(ChronoDuration::days(7), today, 1, true)
+            .is_ok());
+
+        let mut empty = entry_on(2, 12, None);
+        empty.minutes = 0;
+        let error = empty
+            .check_copy(ChronoDuration::days(-7), today, 1, false)
+            .unwrap_err();
+        assert_eq!(
+            error.extensions.unwrap().get("field"),
+            Some(&async_graphql::Value::from("minutes"))
+        );
+    }
+
+    #[test]
+    fn test_plan_week_copy_skips_identical_entries() {
+        let source = vec![
+            entry_on(1, 10, Some("Review")),
+            entry_on(2, 11, None),
+            entry_on(3, 12, None),
+        ];
+        let existing = vec![
+            entry_on(4, 17, Some("Review")),
+            // same day as copy of the entry 2, but different description
+            entry_on(5, 18, Some("Planning")),
+            // identical to the entry 3, but on another day
+            entry_on(6, 20, None),
+        ];
+
+        let (copies, skipped) = plan_week_copy(&source, &existing, ChronoDuration::days(7));
+        let copied: Vec<(usize, u64)> = copies.iter().map(|(i, e)| (*i, e.id)).collect();
+        assert_eq!(copied, vec![(1, 2), (2, 3)]);
+        assert_eq!(skipped, 1);
+    }
+
+    struct CopyEntriesMutation(Vec<TimeEntryModel>);
+
+    #[Object]
+    impl CopyEntriesMutation {
+        async fn copy_entries(&self, ctx: &Context<'_>) -> Result<CopyWeekResult> {
+            copy_entries(ctx, &self.0, &[], ChronoDuration::days(7), false).await
+        }
+    }
+
+    /// Executes the request of a member in a schema limiting batches to a single item.
+    ///
+    /// The schema has neither Teamdeck clients nor the rate limiter,
+    /// so a request which gets past the batch limit panics.
+    async fn execute_with_single_item_batches<Mutation>(
+        mutation: Mutation,
+        request: &str,
+    ) -> async_graphql::Response
+    where
+        Mutation: ObjectType + 'static,
+    {
+        let schema = Schema::build(TimeEntryQuery, mutation, EmptySubscription)
+            .data(BatchLimit(1))
+            .finish();
+        let request = Request::new(request)
+            .data(AccessToken::for_resource(ResourceId(1), Role::Member))
+            .data(ResourceId(1));
+
+        schema.execute(request).await
+    }
+
+    fn error_code(response: &async_graphql::Response) -> Option<Value> {
+        response
+            .errors
+            .first()?
+            .extensions
+            .as_ref()?
+            .get("code")
+            .cloned()
+    }
+
+    #[actix_web::test]
+    async fn test_create_time_entries_over_limit_is_rejected() {
+        let response = execute_with_single_item_batches(
+            TimeEntryMutation,
+            "mutation { createTimeEntries(timeEntries: [{ projectId: 1, minutes: 60 }, { projectId: 2, minutes: 30 }]) { created { id } } }",
+        )
+        .await;
+
+        assert_eq!(error_code(&response), Some(Value::from("BATCH_TOO_LARGE")));
+    }
+
+    #[actix_web::test]
+    async fn test_copy_week_over_limit_is_rejected() {
+        let response = execute_with_single_item_batches(
+            CopyEntriesMutation(vec![entry_on(1, 10, None), entry_on(2, 11, None)]),
+            "mutation { copyEntries { created { id } skipped } }",
+        )
+        .await;
+
+        assert_eq!(error_code(&response), Some(Value::from("BATCH_TOO_LARGE")));
+    }
+
+    #[test]
+    fn test_week_range() {
+        let range = week_range(date(10).0);
+        assert_eq!(range.from.0, date(10).0);
+        assert_eq!(range.to.0, date(16).0);
+    }
+
+    fn filter() -> TimeEntryFilter {
+        TimeEntryFilter {
+            sort: None,
+            page: None,
+            project_id: None,
+            external_id: None,
+            external_id_prefix: None,
+            tag_id: None,
+            tag_match: None,
+            minutes: None,
+            description_contains: None,
+            start_date: None,
+            end_date: None,
+            date: None,
+        }
+    }
+
+    fn range() -> Option<DateRange> {
+        Some(DateRange {
+            from: date(3),
+            to: date(7),
+        })
+    }
+
+    #[test]
+    fn test_filter_rejects_date_with_start_date() {
+        let filter = TimeEntryFilter {
+            date: Some(date(5)),
+            start_date: range(),
+            ..filter()
+        };
+        assert_eq!(filter.validate(), Err(TimeEntryFilterError::DateWithRange));
+    }
+
+    #[test]
+    fn test_filter_rejects_date_with_end_date() {
+        let filter = TimeEntryFilter {
+            date: Some(date(5)),
+            end_date: range(),
+            ..filter()
+        };
+        assert_eq!(filter.validate(), Err(TimeEntryFilterError::DateWithRange));
+    }
+
+    #[test]
+    fn test_filter_accepts_single_date_filters() {
+        let date_only = TimeEntryFilter {
+            date: Some(date(5)),
+            ..filter()
+        };
+        let start_date_only = TimeEntryFilter {
+            start_date: range(),
+            ..filter()
+        };
+        let end_date_only = TimeEntryFilter {
+            end_date: range(),
+            ..filter()
+        };
+        let both_ranges = TimeEntryFilter {
+            start_date: range(),
+            end_date: range(),
+            ..filter()
+        };
+
+        assert_eq!(date_only.validate(), Ok(()));
+        assert_eq!(start_date_only.validate(), Ok(()));
+        assert_eq!(end_date_only.validate(), Ok(()));
+        assert_eq!(both_ranges.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_filter_rejects_inverted_date_range() {
+        let filter = TimeEntryFilter {
+            start_date: Some(DateRange {
+                from: date(7),
+                to: date(3),
+            }),
+            ..filter()
+        };
+        assert_eq!(
+            filter.validate(),
+            Err(TimeEntryFilterError::InvalidRange(
+                "startDate",
+                RangeError::FromAfterTo
+            ))
+        );
+    }
+
+    #[test]
+    fn test_filter_error_extensions() {
+        let error = TimeEntryFilterError::InvalidRange("endDate", RangeError::FromAfterTo).extend();
+        let extensions = error.extensions.unwrap();
+
+        assert_eq!(
+            extensions.get("code"),
+            Some(&async_graphql::Value::from("VALIDATION"))
+        );
+        assert_eq!(
+            extensions.get("field"),
+            Some(&async_graphql::Value::from("endDate"))
+        );
+    }
+
+    #[test]
+    fn test_filter_by_minutes_range() {
+        let filter = TimeEntryFilter {
+            minutes: Some(MinutesRange {
+                min: Some(30),
+                max: Some(90),
+            }),
+            ..filter()
+        };
+        let with_minutes = |minutes| TimeEntryModel {
+            minutes,
+            ..entry(1, 1)
+        };
+
+        assert!(!filter.matches(&with_minutes(1)));
+        assert!(filter.matches(&with_minutes(30)));
+        assert!(filter.matches(&with_minutes(90)));
+        assert!(!filter.matches(&with_minutes(91)));
+    }
+
+    #[test]
+    fn test_filter_by_open_minutes_range() {
+        let range = MinutesRange {
+            min: None,
+            max: Some(1),
+        };
+        assert!(range.contains(1));
+        assert!(!range.contains(2));
+    }
+
+    #[test]
+    fn test_filter_rejects_inverted_minutes_range() {
+        let filter = TimeEntryFilter {
+            minutes: Some(MinutesRange {
+                min: Some(60),
+                max: Some(30),
+            }),
+            ..filter()
+        };
+        assert_eq!(
+            filter.validate(),
+            Err(TimeEntryFilterError::InvalidMinutesRange)
+        );
+    }
+
+    #[test]
+    fn test_end_date_filter_sets_end_date_bounds() {
+        let filter = TimeEntryFilter {
+            end_date: range(),
+            ..filter()
+        };
+
+        let mut builder = TimeEntries::builder();
+        builder.resource_id(vec![1]);
+        apply_date_filters(&mut builder, &filter);
+        let endpoint = format!("{:?}", builder.build().unwrap());
+
+        assert!(endpoint.contains("end_date_from: Some(2022-01-03)"));
+        assert!(endpoint.contains("end_date_to: Some(2022-01-07)"));
+        assert!(endpoint.contains("start_date_from: None"));
+        assert!(endpoint.contains("start_date_to: None"));
+    }
+
+    #[test]
+    fn test_minutes_from_clock_times() {
+        let minutes = minutes_from_clock_times(None, Some(&time("08:30")), Some(&time("10:00")));
+        assert_eq!(minutes, Ok(Some(90)));
+    }
+
+    #[test]
+    fn test_minutes_without_clock_times() {
+        assert_eq!(minutes_from_clock_times(Some(15), None, None), Ok(Some(15)));
+        assert_eq!(minutes_from_clock_times(None, None, None), Ok(None));
+    }
+
+    fn running_timer(started_at: &str) -> Timer {
+        serde_json::from_value(json!({
+            "id": 1,
+            "resource_id": 1,
+            "started_at": started_at,
+            "ended_at": null,
+            "description": null,
+            "project_id": 1,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_resolve_minutes_explicit() {
+        let timer = running_timer("2022-01-10T10:00:00Z");
+        let now = at("2022-01-10T12:00:00Z");
+        assert_eq!(
+            resolve_minutes(Some(15), Some(&timer), now, Some(1)),
+            Ok(15)
+        );
+    }
+
+    #[test]
+    fn test_resolve_minutes_from_timer() {
+        let timer = running_timer("2022-01-10T10:00:00Z");
+        let now = at("2022-01-10T11:45:30Z");
+        assert_eq!(resolve_minutes(None, Some(&timer), now, Some(1)), Ok(105));
+    }
+
+    #[test]
+    fn test_resolve_minutes_fallback() {
+        let now = at("2022-01-10T12:00:00Z");
+        assert_eq!(resolve_minutes(None, None, now, Some(1)), Ok(1));
+        assert_eq!(resolve_minutes(None, None, now, Some(30)), Ok(30));
+    }
+
+    #[test]
+    fn test_resolve_minutes_missing() {
+        let now = at("2022-01-10T12:00:00Z");
+        assert_eq!(
+            resolve_minutes(None, None, now, None),
+            Err(MinutesError::Missing)
+        );
+    }
+
+    #[test]
+    fn test_resolve_minutes_rejects_zero() {
+        let now = at("2022-01-10T12:00:00Z");
+        assert_eq!(
+            resolve_minutes(Some(0), None, now, Some(30)),
+            Err(MinutesError::Zero)
+        );
+
+        let timer = running_timer("2022-01-10T11:59:45Z");
+        assert_eq!(
+            resolve_minutes(None, Some(&timer), now, None),
+            Err(MinutesError::Zero)
+        );
+    }
+
+    fn create_input(
+        date: Option<Date>,
+        start_date: Option<Date>,
+        end_date: Option<Date>,
+    ) -> CreateTimeEntryInput {
+        CreateTimeEntryInput {
+            project_id: 1,
+            weekend_booking: None,
+            holidays_booking: None,
+            vacations_booking: None,
+            description: None,
+            minutes: Some(60),
+            start_time: None,
+            end_time: None,
+            date,
+            start_date,
+            end_date,
+            tag_ids: None,
+            external_id: None,
+            allow_future: None,
+            idempotency_key: None,
+        }
+    }
+
+    #[test]
+    fn test_create_body_external_id() {
+        let input = CreateTimeEntryInput {
+            external_id: Some("jira-123".to_string()),
+            ..create_input(None, None, None)
+        };
+        let body = CreateTimeEntryBody::from_graphql_input(
+            &input,
+            1,
+            (date(10).0, date(10).0),
+            60,
+            vec![],
+        );
+        assert_eq!(body.external_id.as_deref(), Some("jira-123"));
+
+        let body = CreateTimeEntryBody::from_graphql_input(
+            &create_input(None, None, None),
+            1,
+            (date(10).0, date(10).0),
+            60,
+            vec![],
+        );
+        assert_eq!(body.external_id, None);
+    }
+
+    #[test]
+    fn test_external_id_change() {
+        assert_eq!(external_id_change(MaybeUndefined::Undefined), None);
+        assert_eq!(external_id_change(MaybeUndefined::Null), Some(None));
+        assert_eq!(
+            external_id_change(MaybeUndefined::Value("jira-123".to_string())),
+            Some(Some("jira-123".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_single_day_entry_dates() {
+        let today = date(10).0;
+        assert_eq!(
+            create_input(None, None, None).dates(today, 0),
+            Ok((today, today))
+        );
+        assert_eq!(
+            create_input(Some(date(5)), None, None).dates(today, 0),
+            Ok((date(5).0, date(5).0))
+        );
+    }
+
+    #[test]
+    fn test_multi_day_entry_dates() {
+        let today = date(10).0;
+        assert_eq!(
+            create_input(None, Some(date(3)), Some(date(7))).dates(today, 0),
+            Ok((date(3).0, date(7).0))
+        );
+        assert_eq!(
+            create_input(Some(date(3)), None, Some(date(7))).dates(today, 0),
+            Ok((date(3).0, date(7).0))
+        );
+    }
+
+    #[test]
+    fn test_future_entry_dates() {
+        let today = date(10).0;
+        assert_eq!(
+            create_input(Some(date(12)), None, None).dates(today, 1),
+            Err(EntryDatesError::InFuture {
+                field: "date",
+                date: date(12).0
+            })
+        );
+        assert_eq!(
+            create_input(None, Some(date(12)), Some(date(13))).dates(today, 1),
+            Err(EntryDatesError::InFuture {
+                field: "startDate",
+                date: date(12).0
+            })
+        );
+    }
+
+    #[test]
+    fn test_future_entry_dates_within_grace_days() {
+        let today = date(10).0;
+        assert_eq!(
+            create_input(Some(date(11)), None, None).dates(today, 1),
+            Ok((date(11).0, date(11).0))
+        );
+        // backdating is always allowed
+        assert_eq!(
+            create_input(Some(date(1)), None, None).dates(today, 0),
+            Ok((date(1).0, date(1).0))
+        );
+    }
+
+    #[test]
+    fn test_future_entry_dates_allowed() {
+        let input = CreateTimeEntryInput {
+            allow_future: Some(true),
+            ..create_input(Some(date(20)), None, None)
+        };
+        assert_eq!(input.dates(date(10).0, 1), Ok((date(20).0, date(20).0)));
+    }
+
+    #[test]
+    fn test_inverted_entry_dates() {
+        let today = date(10).0;
+        assert_eq!(
+            create_input(None, Some(date(7)), Some(date(3))).dates(today, 0),
+            Err(EntryDatesError::EndBeforeStart)
+        );
+        assert_eq!(
+            create_input(None, None, Some(date(3))).dates(today, 0),
+            Err(EntryDatesError::EndBeforeStart)
+        );
+    }
+
+    #[test]
+    fn test_clock_times_end_before_start() {
+        let minutes = minutes_from_clock_times(None, Some(&time("22:00")), Some(&time("01:00")));
+        assert_eq!(minutes, Err(ClockTimeError::EndBeforeStart));
+
+        let minutes = minutes_from_clock_times(None, Some(&time("10:00")), Some(&time("10:00")));
+        assert_eq!(minutes, Err(ClockTimeError::EndBeforeStart));
+    }
+
+    #[test]
+    fn test_clock_times_missing_bound() {
+        let minutes = minutes_from_clock_times(None, Some(&time("10:00")), None);
+        assert_eq!(minutes, Err(ClockTimeError::MissingBound));
+    }
+
+    #[test]
+    fn test_clock_times_conflicting_minutes() {
+        let minutes =
+            minutes_from_clock_times(Some(60), Some(&time("10:00")), Some(&time("11:00")));
+        assert_eq!(minutes, Err(ClockTimeError::ConflictingMinutes));
+    }
+
+    fn at(value: &str) -> ChronoDateTime<Utc> {
+        ChronoDateTime::parse_from_rfc3339(value).unwrap().into()
+    }
+
+    #[test]
+    fn test_format_relative() {
+        let now = Clock::fixed(at("2022-01-10T12:00:00Z")).now();
+        assert_eq!(format_relative(at("2022-01-10T11:59:30Z"), now), "just now");
+        assert_eq!(
+            format_relative(at("2022-01-10T11:59:00Z"), now),
+            "1 minute ago"
+        );
+        assert_eq!(
+            format_relative(at("2022-01-10T09:30:00Z"), now),
+            "2 hours ago"
+        );
+        assert_eq!(
+            format_relative(at("2022-01-07T08:00:00Z"), now),
+            "3 days ago"
+        );
+    }
+
+    #[test]
+    fn test_created_at_formats() {
+        let mut payload = serde_json::to_value(entry(1, 1)).unwrap();
+        payload["created_at"] = json!("2022-01-10 12:00:00");
+        let entry: TimeEntryModel = serde_json::from_value(payload.clone()).unwrap();
+        assert_eq!(entry.created_at.unwrap().0, at("2022-01-10T12:00:00Z"));
+
+        payload["created_at"] = json!("yesterday");
+        let entry: TimeEntryModel = serde_json::from_value(payload).unwrap();
+        assert!(entry.created_at.is_none());
+    }
+
+    #[test]
+    fn test_format_relative_future_time() {
+        let now = Clock::fixed(at("2022-01-10T12:00:00Z")).now();
+        assert_eq!(format_relative(at("2022-01-11T12:00:00Z"), now), "just now");
+    }
+
+    fn tagged_entry(tag_ids: &[u64]) -> TimeEntryModel {
+        let mut entry = entry(1, 1);
+        entry.tags = Some(
+            tag_ids
+                .iter()
+                .map(|id| {
+                    serde_json::from_value(json!({
+                        "id": id,
+                        "name": format!("Tag {}", id),
+                        "icon": null,
+                        "color": null,
+                        "archived": 0,
+                    }))
+                    .unwrap()
+                })
+                .collect(),
+        );
+        entry
+    }
+
+    #[test]
+    fn test_tag_match_any() {
+        let entry = tagged_entry(&[1, 2]);
+        assert!(entry.has_tags(&[1], TagMatch::Any));
+        assert!(entry.has_tags(&[2, 3], TagMatch::Any));
+        assert!(!entry.has_tags(&[3, 4], TagMatch::Any));
+    }
+
+    #[test]
+    fn test_tag_match_all() {
+        let entry = tagged_entry(&[1, 2]);
+        assert!(entry.has_tags(&[1, 2], TagMatch::All));
+        assert!(!entry.has_tags(&[2, 3], TagMatch::All));
+    }
+
+    #[test]
+    fn test_tag_assignment_modes() {
+        assert_eq!(
+            TagAssignmentMode::Replace.assigned_tags(vec![1, 2], &[3]),
+            vec![3]
+        );
+        assert_eq!(
+            TagAssignmentMode::Add.assigned_tags(vec![1, 2], &[2, 3]),
+            vec![1, 2, 3]
+        );
+        assert_eq!(TagAssignmentMode::Add.assigned_tags(vec![], &[4]), vec![4]);
+    }
+
+    #[test]
+    fn test_tag_match_without_tags() {
+        assert!(!tagged_entry(&[]).has_tags(&[1], TagMatch::Any));
+        assert!(!entry(1, 1).has_tags(&[1], TagMatch::All));
+    }
+
+    #[test]
+    fn test_description_contains() {
+        let with_description = |description: Option<&str>| TimeEntryModel {
+            description: description.map(str::to_string),
+            ..entry(1, 1)
+        };
+
+        assert!(with_description(Some("Code Review of PR")).description_contains("code review"));
+        assert!(with_description(Some("code review")).description_contains("REVIEW"));
+        assert!(!with_description(Some("Planning")).description_contains("review"));
+        assert!(!with_description(None).description_contains("review"));
+        assert!(with_description(None).description_contains(""));
+    }
+
+    #[test]
+    fn test_external_id_prefix() {
+        let mut entry = entry(1, 1);
+        entry.external_id = Some("jira-PROJ-123".to_string());
+
+        assert!(entry.has_external_id_prefix("jira-PROJ-"));
+        assert!(entry.has_external_id_prefix(""));
+        assert!(!entry.has_external_id_prefix("jira-OTHER-"));
+    }
+
+    #[test]
+    fn test_external_id_prefix_without_external_id() {
+        let entry = entry(1, 1);
+        assert!(!entry.has_external_id_prefix("jira-"));
+        assert!(!entry.has_external_id_prefix(""));
+    }
+
+    fn booking(weekend: bool, holidays: bool, vacations: bool) -> BookingType {
+        let mut entry = entry(1, 1);
+        entry.weekend_booking = weekend;
+        entry.holidays_booking = holidays;
+        entry.vacations_booking = vacations;
+        entry.classify_booking()
+    }
+
+    #[test]
+    fn test_booking_type_single_flags() {
+        assert_eq!(booking(false, false, false), BookingType::Regular);
+        assert_eq!(booking(true, false, false), BookingType::Weekend);
+        assert_eq!(booking(false, true, false), BookingType::Holiday);
+        assert_eq!(booking(false, false, true), BookingType::Vacation);
+    }
+
+    #[test]
+    fn test_booking_type_precedence() {
+        assert_eq!(booking(true, true, false), BookingType::Holiday);
+        assert_eq!(booking(true, false, true), BookingType::Vacation);
+        assert_eq!(booking(false, true, true), BookingType::Vacation);
+        assert_eq!(booking(true, true, true), BookingType::Vacation);
+    }
+
+    #[test]
+    fn test_adjust_minutes_by_delta() {
+        let adjustment = MinutesAdjustment::new(Some(15), None).unwrap();
+        assert_eq!(adjustment.apply(30, 1), 45);
+
+        let adjustment = MinutesAdjustment::new(Some(-10), None).unwrap();
+        assert_eq!(adjustment.apply(30, 1), 20);
+    }
+
+    #[test]
+    fn test_adjust_minutes_by_factor() {
+        let adjustment = MinutesAdjustment::new(None, Some(1.5)).unwrap();
+        assert_eq!(adjustment.apply(30, 1), 45);
+
+        let adjustment = MinutesAdjustment::new(None, Some(0.5)).unwrap();
+        assert_eq!(adjustment.apply(45, 1), 23);
+    }
+
+    #[test]
+    fn test_adjust_minutes_clamping() {
+        let adjustment = MinutesAdjustment::new(Some(-60), None).unwrap();
+        assert_eq!(adjustment.apply(30, 1), 1);
+
+        let adjustment = MinutesAdjustment::new(None, Some(10.0)).unwrap();
+        assert_eq!(adjustment.apply(600, 1), 1440);
+    }
+
+    #[test]
+    fn test_adjust_minutes_clamping_of_multi_day_entry() {
+        let mut entry = entry(1, 1);
+        entry.end_date = Date(NaiveDate::from_ymd_opt(2022, 1, 12).unwrap());
+        assert_eq!(entry.days(), 3);
+
+        let adjustment = MinutesAdjustment::new(None, Some(2.0)).unwrap();
+        assert_eq!(adjustment.apply(1800, entry.days()), 3600);
+        assert_eq!(adjustment.apply(3000, entry.days()), 4320);
+    }
+
+    #[test]
+    fn test_adjust_minutes_invalid_arguments() {
+        assert_eq!(
+            MinutesAdjustment::new(Some(15), Some(1.5)),
+            Err(AdjustMinutesError::ConflictingAdjustments)
+        );
+        assert_eq!(
+            MinutesAdjustment::new(None, None),
+            Err(AdjustMinutesError::MissingAdjustment)
+        );
+        assert_eq!(
+            MinutesAdjustment::new(None, Some(-1.0)),
+            Err(AdjustMinutesError::InvalidFactor)
+        );
+        for factor in [0.0, f64::INFINITY, f64::NAN] {
+            assert_eq!(
+                MinutesAdjustment::new(None, Some(factor)),
+                Err(AdjustMinutesError::InvalidFactor)
+            );
+        }
+    }
+
+    #[test]
+    fn test_adjust_minutes_by_extreme_values() {
+        let adjustment = MinutesAdjustment::new(Some(i64::MAX), None).unwrap();
+        assert_eq!(adjustment.apply(30, 1), 1440);
+
+        let adjustment = MinutesAdjustment::new(Some(i64::MIN), None).unwrap();
+        assert_eq!(adjustment.apply(30, 1), 1);
+
+        let adjustment = MinutesAdjustment::new(None, Some(f64::MAX)).unwrap();
+        assert_eq!(adjustment.apply(30, 1), 1440);
+    }
+
+    #[test]
+    fn test_ensure_creator() {
+        let entry = entry(1, 1);
+        assert!(entry.ensure_creator(1).is_ok());
+        assert!(matches!(
+            entry.ensure_creator(2),
+            Err(UpdateTimeEntryError::NotACreator)
+        ));
+    }
+
+    #[test]
+    fn test_entries_are_not_expanded_by_default() {
+        let entry = entry(1, 1);
+        assert!(entry.expanded_project.is_none());
+        assert!(entry.expanded_resource.is_none());
+    }
+
+    #[test]
+    fn test_attach_projects() {
+        let mut entries = vec![entry(1, 1), entry(2, 2), entry(3, 3)];
+        let projects = HashMap::from([(1, project(1)), (2, project(2))]);
+        attach_projects(&mut entries, &projects);
+
+        let project_ids: Vec<Option<u64>> = entries
+            .iter()
+            .map(|e| e.expanded_project.as_ref().map(|p| p.id))
+            .collect();
+        assert_eq!(project_ids, vec![Some(1), Some(2), None]);
+    }
+
+    #[actix_web::test]
+    async fn test_expanding_projects_fetches_them_in_one_batch() {
+        let mut entries = vec![entry(1, 2), entry(2, 1), entry(3, 2)];
+        let mut fetched = vec![];
+
+        expand_projects(&mut entries, &[EntryExpand::Project], |project_ids| {
+            fetched.push(project_ids.clone());
+            async move {
+                Ok(project_ids
+                    .into_iter()
+                    .map(|id| (id, project(id)))
+                    .collect())
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(fetched, vec![vec![1, 2]]);
+        assert!(entries
+            .iter()
+            .all(|e| e.expanded_project.as_ref().map(|p| p.id) == Some(e.project_id)));
+    }
+
+    #[actix_web::test]
+    async fn test_projects_are_not_fetched_unless_expanded() {
+        let mut entries = vec![entry(1, 1)];
+        let mut fetched = 0;
+
+        expand_projects(&mut entries, &[EntryExpand::Tags], |_| {
+            fetched += 1;
+            async { Ok(HashMap::new()) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(fetched, 0);
+        assert!(entries[0].expanded_project.is_none());
+    }
 }
@@ -1,3 +1,4 @@
+use crate::degraded::with_stale_fallback;
 use async_graphql::{Context, Object, Result, SimpleObject};
 use serde::{de::Unexpected, Deserialize, Deserializer, Serialize};
 use teamdeck::{
@@ -9,10 +10,10 @@ use teamdeck::{
     AsyncTeamdeck,
 };
 
-#[derive(Serialize, Deserialize, SimpleObject, Debug)]
+#[derive(Serialize, Deserialize, SimpleObject, Debug, Clone)]
 pub struct TimeEntryTagModel {
-    id: u64,
-    name: String,
+    pub id: u64,
+    pub name: String,
     icon: Option<String>,
     color: Option<String>,
     #[serde(deserialize_with = "bool_from_int")]
@@ -37,16 +38,44 @@ impl TimeEntryTagQuery {
         Ok(tag)
     }
 
+    /// Returns time entry tags sorted by name.
+    ///
+    /// When `archived` is given, only tags with matching archived state are returned,
+    /// e.g. `archived: false` returns only active tags.
     #[tracing::instrument(name = "Fetching all time entry tags", skip(ctx))]
-    async fn time_entry_tags(&self, ctx: &Context<'_>) -> Result<Vec<TimeEntryTagModel>> {
+    async fn time_entry_tags(
+        &self,
+        ctx: &Context<'_>,
+        archived: Option<bool>,
+    ) -> Result<Vec<TimeEntryTagModel>> {
         let client = ctx.data_unchecked::<AsyncTeamdeck>();
-        let endpoint = TimeEntryTags::builder().build()?;
-
-        let tags = paged(endpoint, Pagination::All).query_async(client).await?;
-        Ok(tags)
+        let tags =
+            with_stale_fallback(ctx, "time_entry_tags", fetch_time_entry_tags(client)).await?;
+        Ok(filter_and_sort_tags(tags, archived))
     }
 }
 
+fn filter_and_sort_tags(
+    tags: Vec<TimeEntryTagModel>,
+    archived: Option<bool>,
+) -> Vec<TimeEntryTagModel> {
+    let mut tags: Vec<_> = tags
+        .into_iter()
+        .filter(|tag| archived.map_or(true, |archived| tag.archived == archived))
+        .collect();
+    tags.sort_by_cached_key(|tag| tag.name.to_lowercase());
+    tags
+}
+
+pub(crate) async fn fetch_time_entry_tags(
+    client: &AsyncTeamdeck,
+) -> Result<Vec<TimeEntryTagModel>> {
+    let endpoint = TimeEntryTags::builder().build()?;
+
+    let tags = paged(endpoint, Pagination::All).query_async(client).await?;
+    Ok(tags)
+}
+
 fn bool_from_int<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
     D: Deserializer<'de>,
@@ -60,3 +89,49 @@ where
         )),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tag(id: u64, name: &str, archived: bool) -> TimeEntryTagModel {
+        TimeEntryTagModel {
+            id,
+            name: name.to_string(),
+            icon: None,
+            color: None,
+            archived,
+        }
+    }
+
+    fn names(tags: &[TimeEntryTagModel]) -> Vec<&str> {
+        tags.iter().map(|tag| tag.name.as_str()).collect()
+    }
+
+    fn tags() -> Vec<TimeEntryTagModel> {
+        vec![
+            tag(1, "meeting", false),
+            tag(2, "Bugfix", true),
+            tag(3, "Code review", false),
+            tag(4, "admin", true),
+        ]
+    }
+
+    #[test]
+    fn test_tags_are_sorted_by_name() {
+        let tags = filter_and_sort_tags(tags(), None);
+        assert_eq!(
+            names(&tags),
+            vec!["admin", "Bugfix", "Code review", "meeting"]
+        );
+    }
+
+    #[test]
+    fn test_tags_are_filtered_by_archived_state() {
+        let active = filter_and_sort_tags(tags(), Some(false));
+        assert_eq!(names(&active), vec!["Code review", "meeting"]);
+
+        let archived = filter_and_sort_tags(tags(), Some(true));
+        assert_eq!(names(&archived), vec!["admin", "Bugfix"]);
+    }
+}
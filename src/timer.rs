@@ -1,9 +1,34 @@
+mod store;
+
+use crate::auth::guard::{is_admin, AccessTokenAuthGuard, AuthError};
+use crate::auth::token::ResourceId;
+use crate::clock::Clock;
+use crate::loader::ProjectLoader;
+use crate::project::ProjectModel;
+use crate::resource::{fetch_resource, ResourceModel};
 use crate::scalars::DateTime;
+use crate::tag_policy::{TagOperation, TagPolicy};
+use crate::teamdeck::api::CreateTimeEntryBody;
+use crate::time_entry::{create_entry_with_tags, format_minutes, TimeEntryModel};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::futures_util::lock::Mutex as AsyncMutex;
+use async_graphql::futures_util::stream::{self, Stream};
 use async_graphql::*;
-use chrono::Utc;
+use chrono::{DateTime as ChronoDateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use teamdeck::AsyncTeamdeck;
+use thiserror::Error;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::error;
+
+use store::InMemoryTimerStore;
+pub use store::{store_from_env, TimerStore};
 
-#[derive(SimpleObject, Clone)]
+#[derive(SimpleObject, Clone, Debug, Serialize, Deserialize)]
+#[graphql(complex)]
 pub struct Timer {
     id: u64,
     resource_id: u64,
@@ -14,16 +39,84 @@ pub struct Timer {
 }
 
 impl Timer {
-    pub fn from_input(input: CreateTimerInput) -> Timer {
+    pub fn from_input(input: CreateTimerInput, resource_id: u64) -> Timer {
         Timer {
             id: 0,
-            resource_id: input.resource_id,
+            resource_id,
             started_at: DateTime(Utc::now()),
             ended_at: None,
             description: input.description,
             project_id: input.project_id,
         }
     }
+
+    /// Builds the request creating a time entry covering the timer's elapsed time.
+    fn to_time_entry_body(&self, tags: Vec<u64>) -> CreateTimeEntryBody {
+        let date = self.started_at.0.date_naive();
+        let ended_at = self.ended_at.as_ref().map_or_else(Utc::now, |e| e.0);
+
+        CreateTimeEntryBody {
+            resource_id: self.resource_id,
+            project_id: self.project_id,
+            minutes: self.elapsed_minutes_at(ended_at),
+            weekend_booking: None,
+            holidays_booking: None,
+            vacations_booking: None,
+            description: self.description.clone(),
+            start_date: date,
+            end_date: date,
+            creator_resource_id: self.resource_id,
+            editor_resource_id: self.resource_id,
+            tags,
+            external_id: None,
+        }
+    }
+
+    /// Minutes elapsed from the start of the timer until it was stopped,
+    /// or until `now` when it's still running.
+    pub fn elapsed_minutes_at(&self, now: ChronoDateTime<Utc>) -> u64 {
+        let end = self.ended_at.as_ref().map_or(now, |ended_at| ended_at.0);
+        (end - self.started_at.0).num_minutes().max(0) as u64
+    }
+}
+
+#[ComplexObject]
+impl Timer {
+    /// Whole minutes elapsed from the start of the timer until it was stopped, or until now.
+    async fn elapsed_minutes(&self, ctx: &Context<'_>) -> u64 {
+        self.elapsed_minutes_at(ctx.data_unchecked::<Clock>().now())
+    }
+
+    async fn formatted_duration(&self, ctx: &Context<'_>) -> String {
+        format_minutes(self.elapsed_minutes_at(ctx.data_unchecked::<Clock>().now()))
+    }
+
+    async fn project(&self, ctx: &Context<'_>) -> Result<Option<ProjectModel>> {
+        let loader = ctx.data_unchecked::<DataLoader<ProjectLoader>>();
+        loader.load_one(self.project_id).await
+    }
+
+    async fn resource(&self, ctx: &Context<'_>) -> Result<Option<ResourceModel>> {
+        let client = ctx.data_unchecked::<AsyncTeamdeck>();
+        fetch_resource(client, self.resource_id).await
+    }
+}
+
+/// Returns ID of the resource whose timers are accessed.
+///
+/// It's the authorized user, unless an admin explicitly asks for timers of another resource.
+async fn accessed_resource_id(ctx: &Context<'_>, resource_id: Option<u64>) -> Result<u64> {
+    let own_resource_id = ctx.data_unchecked::<ResourceId>().0;
+    match resource_id {
+        Some(resource_id) if resource_id != own_resource_id => {
+            if is_admin(ctx).await? {
+                Ok(resource_id)
+            } else {
+                Err(AuthError::NotAnAdmin.into())
+            }
+        }
+        _ => Ok(own_resource_id),
+    }
 }
 
 #[derive(Default, Debug)]
@@ -32,17 +125,27 @@ pub struct TimerQuery;
 #[Object]
 impl TimerQuery {
     #[tracing::instrument(name = "Find current timer", skip(self, ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
     async fn current_timer<'ctx>(
         &'ctx self,
         ctx: &Context<'ctx>,
-        resource_id: u64,
+        #[graphql(desc = "Resource to find the timer of, available only for admins")]
+        resource_id: Option<u64>,
     ) -> Result<Option<Timer>> {
+        let resource_id = accessed_resource_id(ctx, resource_id).await?;
         let timers = ctx.data_unchecked::<Timers>();
         Ok(timers.get_by_resource_id(resource_id).last().cloned())
     }
 
     #[tracing::instrument(name = "Find all timers for resource", skip(self, ctx))]
-    async fn timers<'ctx>(&'ctx self, ctx: &Context<'ctx>, resource_id: u64) -> Result<Vec<Timer>> {
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn timers<'ctx>(
+        &'ctx self,
+        ctx: &Context<'ctx>,
+        #[graphql(desc = "Resource to find timers of, available only for admins")]
+        resource_id: Option<u64>,
+    ) -> Result<Vec<Timer>> {
+        let resource_id = accessed_resource_id(ctx, resource_id).await?;
         let timers = ctx.data_unchecked::<Timers>();
         Ok(timers.get_by_resource_id(resource_id))
     }
@@ -50,7 +153,10 @@ impl TimerQuery {
 
 #[derive(InputObject, Debug)]
 pub struct CreateTimerInput {
-    resource_id: u64,
+    /// Resource to start the timer for, available only for admins.
+    ///
+    /// Defaults to the authorized user.
+    resource_id: Option<u64>,
     project_id: u64,
     description: Option<String>,
 }
@@ -61,27 +167,217 @@ pub struct TimerMutation;
 #[Object]
 impl TimerMutation {
     #[tracing::instrument(name = "Starting new timer", skip(self, ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
     async fn start_timer(&self, ctx: &Context<'_>, input: CreateTimerInput) -> Result<Timer> {
-        let timer = Timer::from_input(input);
+        let resource_id = accessed_resource_id(ctx, input.resource_id).await?;
         let timers = ctx.data_unchecked::<Timers>();
-        timers.add(&timer);
+
+        let mut timer = Timer::from_input(input, resource_id);
+        timer.id = timers.start(&timer).await?;
+        Ok(timer)
+    }
+
+    /// Stops the timer, keeping it in the timers of the resource without logging any time.
+    #[tracing::instrument(name = "Stopping timer", skip(self, ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn stop_timer(&self, ctx: &Context<'_>, timer_id: u64) -> Result<Timer> {
+        let timers = ctx.data_unchecked::<Timers>();
+        let timer = timers.get(timer_id).ok_or(TimerError::NotFound(timer_id))?;
+        accessed_resource_id(ctx, Some(timer.resource_id)).await?;
+
+        let lock = timers.resource_lock(timer.resource_id);
+        let _guard = lock.lock().await;
+
+        let timer = timers.stop(timer_id, DateTime(Utc::now()))?;
+        Ok(timer)
+    }
+
+    /// Discards the timer entirely, e.g. when it was started by mistake, and returns it.
+    ///
+    /// Unlike `stopTimer`, the timer is no longer listed in the timers of the resource.
+    #[tracing::instrument(name = "Cancelling timer", skip(self, ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn cancel_timer(&self, ctx: &Context<'_>, timer_id: u64) -> Result<Timer> {
+        let timers = ctx.data_unchecked::<Timers>();
+        let timer = timers.get(timer_id).ok_or(TimerError::NotFound(timer_id))?;
+        accessed_resource_id(ctx, Some(timer.resource_id)).await?;
+
+        let lock = timers.resource_lock(timer.resource_id);
+        let _guard = lock.lock().await;
+
+        if !timers.remove(timer_id) {
+            return Err(TimerError::NotFound(timer_id).into());
+        }
+        Ok(timer)
+    }
+
+    /// Changes project and/or description of the running timer, keeping the omitted ones.
+    #[tracing::instrument(name = "Updating timer", skip(self, ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn update_timer(
+        &self,
+        ctx: &Context<'_>,
+        timer_id: u64,
+        project_id: Option<u64>,
+        description: Option<String>,
+    ) -> Result<Timer> {
+        let timers = ctx.data_unchecked::<Timers>();
+        let timer = timers.get(timer_id).ok_or(TimerError::NotFound(timer_id))?;
+        accessed_resource_id(ctx, Some(timer.resource_id)).await?;
+
+        let lock = timers.resource_lock(timer.resource_id);
+        let _guard = lock.lock().await;
+
+        let timer = timers.update(timer_id, project_id, description)?;
         Ok(timer)
     }
+
+    /// Stops the timer and logs its elapsed time as a Teamdeck time entry.
+    ///
+    /// The timer keeps running when the time entry could not be created, so the request can be retried.
+    #[tracing::instrument(name = "Stopping timer and logging time entry", skip(self, ctx))]
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn stop_timer_and_log(
+        &self,
+        ctx: &Context<'_>,
+        timer_id: u64,
+        tag_ids: Vec<u64>,
+    ) -> Result<TimeEntryModel> {
+        let timers = ctx.data_unchecked::<Timers>();
+        let timer = timers.get(timer_id).ok_or(TimerError::NotFound(timer_id))?;
+        accessed_resource_id(ctx, Some(timer.resource_id)).await?;
+
+        let tag_ids = ctx
+            .data_unchecked::<TagPolicy>()
+            .resolve(Some(tag_ids), TagOperation::Create)
+            .extend()?;
+
+        timers
+            .stop_and_log(timer_id, DateTime(Utc::now()), |timer| {
+                let request_body = timer.to_time_entry_body(tag_ids.clone().unwrap_or_default());
+                create_entry_with_tags(ctx, request_body, tag_ids)
+            })
+            .await
+    }
+}
+
+#[derive(Default)]
+pub struct TimerSubscription;
+
+#[Subscription]
+impl TimerSubscription {
+    /// Emits the timer of the resource each time it's started, stopped or updated.
+    #[graphql(guard = "AccessTokenAuthGuard::default()")]
+    async fn timer_updates(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Resource to watch the timers of, available only for admins")]
+        resource_id: Option<u64>,
+    ) -> Result<impl Stream<Item = Timer>> {
+        let resource_id = accessed_resource_id(ctx, resource_id).await?;
+        let receiver = ctx.data_unchecked::<Timers>().subscribe();
+        Ok(timer_events(receiver, resource_id))
+    }
+}
+
+/// Turns the timer changes into a stream of the changed timers of the resource.
+///
+/// Events missed by a lagging receiver are skipped, as only the latest state of a timer matters.
+fn timer_events(
+    receiver: broadcast::Receiver<Timer>,
+    resource_id: u64,
+) -> impl Stream<Item = Timer> {
+    stream::unfold(receiver, move |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(timer) if timer.resource_id == resource_id => return Some((timer, receiver)),
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TimerError {
+    #[error("Timer with ID {0} does not exist")]
+    NotFound(u64),
+    #[error("Timer with ID {0} has already been stopped")]
+    AlreadyStopped(u64),
+    #[error("You already have a running timer")]
+    AlreadyRunning,
 }
 
+/// Number of timer changes buffered for subscribers which didn't receive them yet.
+const TIMER_EVENTS_CAPACITY: usize = 64;
+
 pub struct Timers {
     data: Arc<Mutex<Vec<Timer>>>,
+    resource_locks: Mutex<HashMap<u64, Arc<AsyncMutex<()>>>>,
+    last_id: AtomicU64,
+    store: Box<dyn TimerStore>,
+    events: broadcast::Sender<Timer>,
 }
 
 impl Default for Timers {
     fn default() -> Self {
-        Timers {
-            data: Arc::new(Mutex::new(vec![])),
-        }
+        Timers::with_store(Box::new(InMemoryTimerStore))
     }
 }
 
 impl Timers {
+    /// Creates timers backed by the store, restoring timers which were still running.
+    ///
+    /// Panics when the persisted timers cannot be restored, instead of overwriting them
+    /// with the next saved change.
+    pub fn with_store(store: Box<dyn TimerStore>) -> Self {
+        let timers = store
+            .load()
+            .unwrap_or_else(|e| panic!("Could not restore timers: {:?}", e));
+        let last_id = timers.iter().map(|t| t.id).max().unwrap_or(0);
+        let timers: Vec<Timer> = timers
+            .into_iter()
+            .filter(|t| t.ended_at.is_none())
+            .collect();
+
+        Timers {
+            data: Arc::new(Mutex::new(timers)),
+            resource_locks: Mutex::new(HashMap::new()),
+            last_id: AtomicU64::new(last_id),
+            store,
+            events: broadcast::channel(TIMER_EVENTS_CAPACITY).0,
+        }
+    }
+
+    /// Returns receiver of all timers started, stopped or updated from now on.
+    fn subscribe(&self) -> broadcast::Receiver<Timer> {
+        self.events.subscribe()
+    }
+
+    fn notify(&self, timer: &Timer) {
+        // fails only when nobody is subscribed, which is fine
+        let _ = self.events.send(timer.clone());
+    }
+
+    fn persist(&self, timers: &[Timer]) {
+        if let Err(e) = self.store.save(timers) {
+            error!("Could not persist timers: {:?}", e);
+        }
+    }
+
+    /// Returns the lock serializing timer mutations of the resource.
+    ///
+    /// Mutations should hold it for their whole duration, so that concurrent
+    /// requests of the same resource cannot interleave.
+    fn resource_lock(&self, resource_id: u64) -> Arc<AsyncMutex<()>> {
+        self.resource_locks
+            .lock()
+            .unwrap()
+            .entry(resource_id)
+            .or_default()
+            .clone()
+    }
+
     fn get_by_resource_id(&self, resource_id: u64) -> Vec<Timer> {
         let timers = self.data.lock().unwrap();
         timers
@@ -91,7 +387,479 @@ impl Timers {
             .collect()
     }
 
-    fn add(&self, timer: &Timer) {
-        self.data.lock().unwrap().push(timer.clone())
+    fn get(&self, timer_id: u64) -> Option<Timer> {
+        let timers = self.data.lock().unwrap();
+        timers.iter().find(|t| t.id == timer_id).cloned()
+    }
+
+    /// Returns whether the resource has a timer which was not stopped yet.
+    fn has_active(&self, resource_id: u64) -> bool {
+        let timers = self.data.lock().unwrap();
+        timers
+            .iter()
+            .any(|t| t.resource_id == resource_id && t.ended_at.is_none())
+    }
+
+    /// Returns the most recently started timer of the resource which was started on the date.
+    pub fn latest_started_on(&self, resource_id: u64, date: NaiveDate) -> Option<Timer> {
+        self.get_by_resource_id(resource_id)
+            .into_iter()
+            .filter(|t| t.started_at.0.date_naive() == date)
+            .max_by_key(|t| t.started_at.0)
+    }
+
+    /// Stores the timer under a new unique ID and returns the ID,
+    /// unless the resource already has a running timer.
+    async fn start(&self, timer: &Timer) -> Result<u64, TimerError> {
+        let lock = self.resource_lock(timer.resource_id);
+        let _guard = lock.lock().await;
+
+        if self.has_active(timer.resource_id) {
+            return Err(TimerError::AlreadyRunning);
+        }
+        Ok(self.add(timer))
+    }
+
+    /// Stores the timer under a new unique ID and returns the ID.
+    fn add(&self, timer: &Timer) -> u64 {
+        let id = self.last_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let timer = Timer {
+            id,
+            ..timer.clone()
+        };
+        let mut timers = self.data.lock().unwrap();
+        timers.push(timer.clone());
+        self.persist(&timers);
+        self.notify(&timer);
+        id
+    }
+
+    /// Ends the running timer with the given ID and returns it.
+    fn stop(&self, timer_id: u64, ended_at: DateTime) -> Result<Timer, TimerError> {
+        let mut timers = self.data.lock().unwrap();
+        let timer = timers
+            .iter_mut()
+            .find(|t| t.id == timer_id)
+            .ok_or(TimerError::NotFound(timer_id))?;
+
+        if timer.ended_at.is_some() {
+            return Err(TimerError::AlreadyStopped(timer_id));
+        }
+
+        timer.ended_at = Some(ended_at);
+        let timer = timer.clone();
+        self.persist(&timers);
+        self.notify(&timer);
+        Ok(timer)
+    }
+
+    /// Logs the time of the running timer with `log`, stopping the timer only when it succeeds.
+    ///
+    /// `log` gets the timer as it will be once stopped, and runs under the lock of the resource.
+    async fn stop_and_log<T, F, Fut>(&self, timer_id: u64, ended_at: DateTime, log: F) -> Result<T>
+    where
+        F: FnOnce(Timer) -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let resource_id = self
+            .get(timer_id)
+            .ok_or(TimerError::NotFound(timer_id))?
+            .resource_id;
+        let lock = self.resource_lock(resource_id);
+        let _guard = lock.lock().await;
+
+        let timer = self.get(timer_id).ok_or(TimerError::NotFound(timer_id))?;
+        if timer.ended_at.is_some() {
+            return Err(TimerError::AlreadyStopped(timer_id).into());
+        }
+
+        let logged = log(Timer {
+            ended_at: Some(ended_at.clone()),
+            ..timer
+        })
+        .await?;
+        self.stop(timer_id, ended_at)?;
+        Ok(logged)
+    }
+
+    /// Removes the timer with the given ID, returning whether it existed.
+    fn remove(&self, timer_id: u64) -> bool {
+        let mut timers = self.data.lock().unwrap();
+        let count = timers.len();
+        timers.retain(|t| t.id != timer_id);
+
+        let removed = timers.len() < count;
+        if removed {
+            self.persist(&timers);
+        }
+        removed
+    }
+
+    /// Changes the given fields of the running timer with the given ID and returns it.
+    fn update(
+        &self,
+        timer_id: u64,
+        project_id: Option<u64>,
+        description: Option<String>,
+    ) -> Result<Timer, TimerError> {
+        let mut timers = self.data.lock().unwrap();
+        let timer = timers
+            .iter_mut()
+            .find(|t| t.id == timer_id)
+            .ok_or(TimerError::NotFound(timer_id))?;
+
+        if timer.ended_at.is_some() {
+            return Err(TimerError::AlreadyStopped(timer_id));
+        }
+
+        if let Some(project_id) = project_id {
+            timer.project_id = project_id;
+        }
+        if description.is_some() {
+            timer.description = description;
+        }
+        let timer = timer.clone();
+        self.persist(&timers);
+        self.notify(&timer);
+        Ok(timer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use actix_web::rt::task::yield_now;
+    use async_graphql::futures_util::future::{join, join_all};
+    use async_graphql::futures_util::StreamExt;
+    use store::FileTimerStore;
+
+    fn timer(resource_id: u64) -> Timer {
+        Timer::from_input(
+            CreateTimerInput {
+                resource_id: None,
+                project_id: 1,
+                description: None,
+            },
+            resource_id,
+        )
+    }
+
+    /// Stops the timer, logging it as an entry counted by `created` after yielding to other tasks.
+    async fn stop_and_count(timers: &Timers, timer_id: u64, created: &AtomicU64) -> Result<u64> {
+        timers
+            .stop_and_log(timer_id, DateTime(Utc::now()), |timer| async move {
+                yield_now().await;
+                created.fetch_add(1, Ordering::SeqCst);
+                Ok(timer.id)
+            })
+            .await
+    }
+
+    fn active_timers(timers: &Timers, resource_id: u64) -> Vec<Timer> {
+        timers
+            .get_by_resource_id(resource_id)
+            .into_iter()
+            .filter(|t| t.ended_at.is_none())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_concurrent_start_and_stop_of_resource() {
+        let timers = Timers::default();
+        let running = timers.add(&timer(1));
+        let created = AtomicU64::new(0);
+
+        let new_timer = timer(1);
+        let (started, stopped) = join(
+            timers.start(&new_timer),
+            stop_and_count(&timers, running, &created),
+        )
+        .await;
+
+        assert_eq!(stopped.unwrap(), running);
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+        assert!(timers.get(running).unwrap().ended_at.is_some());
+
+        let active = active_timers(&timers, 1);
+        match started {
+            Ok(id) => assert_eq!(active.iter().map(|t| t.id).collect::<Vec<_>>(), vec![id]),
+            Err(e) => {
+                assert_eq!(e, TimerError::AlreadyRunning);
+                assert!(active.is_empty());
+            }
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_concurrent_stops_log_single_entry() {
+        let timers = Timers::default();
+        let running = timers.add(&timer(1));
+        let created = AtomicU64::new(0);
+
+        let results = join_all((0..3).map(|_| stop_and_count(&timers, running, &created))).await;
+
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        assert!(active_timers(&timers, 1).is_empty());
+    }
+
+    #[test]
+    fn test_stop_timer() {
+        let timers = Timers::default();
+        let mut timer = timer(1);
+        timer.id = timers.add(&timer);
+
+        let ended_at = DateTime(Utc::now());
+        let stopped = timers.stop(timer.id, ended_at.clone()).unwrap();
+        assert_eq!(stopped.ended_at.map(|d| d.0), Some(ended_at.0));
+        assert!(timers.get_by_resource_id(1)[0].ended_at.is_some());
+    }
+
+    #[test]
+    fn test_stop_already_stopped_timer() {
+        let timers = Timers::default();
+        let mut timer = timer(1);
+        timer.id = timers.add(&timer);
+
+        timers.stop(timer.id, DateTime(Utc::now())).unwrap();
+        assert_eq!(
+            timers.stop(timer.id, DateTime(Utc::now())).unwrap_err(),
+            TimerError::AlreadyStopped(timer.id)
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_timer_keeps_running_when_logging_fails() {
+        let timers = Timers::default();
+        let id = timers.add(&timer(1));
+
+        let failed: Result<u64> = timers
+            .stop_and_log(id, DateTime(Utc::now()), |_| async {
+                Err(Error::new("Teamdeck is unreachable"))
+            })
+            .await;
+        assert!(failed.is_err());
+        assert!(timers.get(id).unwrap().ended_at.is_none());
+
+        let logged = timers
+            .stop_and_log(id, DateTime(Utc::now()), |timer| async move {
+                assert!(timer.ended_at.is_some());
+                Ok(timer.id)
+            })
+            .await
+            .unwrap();
+        assert_eq!(logged, id);
+        assert!(timers.get(id).unwrap().ended_at.is_some());
+    }
+
+    #[test]
+    fn test_update_timer_project() {
+        let timers = Timers::default();
+        let mut timer = timer(1);
+        timer.description = Some("Code review".to_string());
+        let id = timers.add(&timer);
+
+        let updated = timers.update(id, Some(7), None).unwrap();
+        assert_eq!(updated.project_id, 7);
+        assert_eq!(updated.description.as_deref(), Some("Code review"));
+        assert_eq!(timers.get(id).unwrap().project_id, 7);
+    }
+
+    #[test]
+    fn test_update_timer_description() {
+        let timers = Timers::default();
+        let id = timers.add(&timer(1));
+
+        let updated = timers
+            .update(id, None, Some("Planning".to_string()))
+            .unwrap();
+        assert_eq!(updated.project_id, 1);
+        assert_eq!(updated.description.as_deref(), Some("Planning"));
+    }
+
+    #[test]
+    fn test_update_stopped_timer() {
+        let timers = Timers::default();
+        let id = timers.add(&timer(1));
+        timers.stop(id, DateTime(Utc::now())).unwrap();
+
+        assert_eq!(
+            timers.update(id, Some(7), None).unwrap_err(),
+            TimerError::AlreadyStopped(id)
+        );
+        assert_eq!(
+            timers.update(42, Some(7), None).unwrap_err(),
+            TimerError::NotFound(42)
+        );
+    }
+
+    #[test]
+    fn test_remove_timer() {
+        let timers = Timers::default();
+        let first = timers.add(&timer(1));
+        let second = timers.add(&timer(1));
+
+        assert!(timers.remove(first));
+        assert!(!timers.remove(first));
+        let ids: Vec<u64> = timers.get_by_resource_id(1).iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![second]);
+        assert!(!timers.has_active(2));
+    }
+
+    #[actix_web::test]
+    async fn test_timer_events_of_resource() {
+        let timers = Timers::default();
+        let events = timer_events(timers.subscribe(), 1);
+
+        let first = timers.add(&timer(1));
+        timers.add(&timer(2));
+        timers.update(first, Some(7), None).unwrap();
+        timers.stop(first, DateTime(Utc::now())).unwrap();
+
+        let events: Vec<Timer> = events.take(3).collect().await;
+        assert!(events.iter().all(|t| t.id == first));
+        assert_eq!(events[1].project_id, 7);
+        assert!(events[1].ended_at.is_none());
+        assert!(events[2].ended_at.is_some());
+    }
+
+    #[actix_web::test]
+    async fn test_timer_events_end_with_timers() {
+        let timers = Timers::default();
+        let events = timer_events(timers.subscribe(), 1);
+        timers.add(&timer(2));
+        drop(timers);
+
+        let events: Vec<Timer> = events.collect().await;
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_add_assigns_unique_ids() {
+        let timers = Timers::default();
+        let first = timers.add(&timer(1));
+        let second = timers.add(&timer(2));
+        let third = timers.add(&timer(1));
+
+        assert_eq!((first, second, third), (1, 2, 3));
+        let ids: Vec<u64> = timers.get_by_resource_id(1).iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_file_store_restores_running_timers() {
+        let path = std::env::temp_dir().join(format!("timers-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let timers = Timers::with_store(Box::new(FileTimerStore::new(&path)));
+        let running = timers.add(&timer(1));
+        let stopped = timers.add(&timer(2));
+        timers.stop(stopped, DateTime(Utc::now())).unwrap();
+
+        let restored = Timers::with_store(Box::new(FileTimerStore::new(&path)));
+        let ids: Vec<u64> = restored
+            .get_by_resource_id(1)
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![running]);
+        assert!(restored.get_by_resource_id(2).is_empty());
+        assert_eq!(restored.add(&timer(1)), 3);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Could not restore timers")]
+    fn test_file_store_with_invalid_contents_is_not_discarded() {
+        let path = std::env::temp_dir().join(format!("invalid-timers-{}.json", std::process::id()));
+        std::fs::write(&path, "[{").unwrap();
+
+        Timers::with_store(Box::new(FileTimerStore::new(&path)));
+    }
+
+    #[test]
+    fn test_time_entry_body_from_stopped_timer() {
+        let timers = Timers::default();
+        let id = timers.add(&timer(1));
+        let started_at = timers.get(id).unwrap().started_at.0;
+        let timer = timers
+            .stop(id, DateTime(started_at + chrono::Duration::minutes(95)))
+            .unwrap();
+
+        let body = timer.to_time_entry_body(vec![3]);
+        assert_eq!(body.minutes, 95);
+        assert_eq!(body.project_id, 1);
+        assert_eq!(body.resource_id, 1);
+        assert_eq!(body.start_date, started_at.date_naive());
+        assert_eq!(body.tags, vec![3]);
+    }
+
+    #[test]
+    fn test_has_active_timer() {
+        let timers = Timers::default();
+        assert!(!timers.has_active(1));
+
+        let first = timers.add(&timer(1));
+        assert!(timers.has_active(1));
+        assert!(!timers.has_active(2));
+
+        timers.stop(first, DateTime(Utc::now())).unwrap();
+        assert!(!timers.has_active(1));
+    }
+
+    #[actix_web::test]
+    async fn test_start_fails_while_timer_is_running() {
+        let timers = Timers::default();
+        let running = timers.start(&timer(1)).await.unwrap();
+
+        assert_eq!(
+            timers.start(&timer(1)).await,
+            Err(TimerError::AlreadyRunning)
+        );
+        assert!(timers.start(&timer(2)).await.is_ok());
+
+        timers.stop(running, DateTime(Utc::now())).unwrap();
+        let restarted = timers.start(&timer(1)).await.unwrap();
+        assert_ne!(restarted, running);
+        assert!(timers.has_active(1));
+    }
+
+    #[test]
+    fn test_elapsed_minutes() {
+        let timer = timer(1);
+        let started_at = timer.started_at.0;
+
+        assert_eq!(timer.elapsed_minutes_at(started_at), 0);
+        assert_eq!(
+            timer.elapsed_minutes_at(started_at + chrono::Duration::seconds(59)),
+            0
+        );
+        assert_eq!(
+            timer.elapsed_minutes_at(started_at + chrono::Duration::minutes(75)),
+            75
+        );
+    }
+
+    #[test]
+    fn test_stop_missing_timer() {
+        let timers = Timers::default();
+        assert_eq!(
+            timers.stop(42, DateTime(Utc::now())).unwrap_err(),
+            TimerError::NotFound(42)
+        );
+    }
+
+    #[test]
+    fn test_resource_lock_is_shared_per_resource() {
+        let timers = Timers::default();
+        assert!(Arc::ptr_eq(
+            &timers.resource_lock(1),
+            &timers.resource_lock(1)
+        ));
+        assert!(!Arc::ptr_eq(
+            &timers.resource_lock(1),
+            &timers.resource_lock(2)
+        ));
     }
 }
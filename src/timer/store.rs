@@ -0,0 +1,81 @@
+use super::Timer;
+use std::fs;
+use std::path::PathBuf;
+use thiserror::Error;
+
+const TIMERS_FILE_ENV_VARIABLE: &str = "TIMERS_FILE";
+
+#[derive(Debug, Error)]
+pub enum TimerStoreError {
+    #[error("could not access timers file")]
+    Io(#[from] std::io::Error),
+    #[error("could not (de)serialize timers")]
+    Serialization(#[from] serde_json::Error),
+}
+
+/// Persistence backend of the timers.
+pub trait TimerStore: Send + Sync {
+    /// Returns all persisted timers.
+    fn load(&self) -> Result<Vec<Timer>, TimerStoreError>;
+
+    /// Replaces persisted timers with the given ones.
+    fn save(&self, timers: &[Timer]) -> Result<(), TimerStoreError>;
+}
+
+/// Store keeping no data, timers live only in the memory of the process.
+#[derive(Default, Debug)]
+pub struct InMemoryTimerStore;
+
+impl TimerStore for InMemoryTimerStore {
+    fn load(&self) -> Result<Vec<Timer>, TimerStoreError> {
+        Ok(vec![])
+    }
+
+    fn save(&self, _timers: &[Timer]) -> Result<(), TimerStoreError> {
+        Ok(())
+    }
+}
+
+/// Store persisting timers as JSON in a file.
+#[derive(Debug)]
+pub struct FileTimerStore {
+    path: PathBuf,
+}
+
+impl FileTimerStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileTimerStore { path: path.into() }
+    }
+}
+
+impl TimerStore for FileTimerStore {
+    fn load(&self) -> Result<Vec<Timer>, TimerStoreError> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        let contents = fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Writes the timers to a temporary file next to the timers file and renames it
+    /// into place, so that the timers file is never left partially written.
+    fn save(&self, timers: &[Timer]) -> Result<(), TimerStoreError> {
+        let contents = serde_json::to_string(timers)?;
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, contents)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Returns the store configured with the `TIMERS_FILE` env variable.
+///
+/// Timers are persisted in the file when the variable is set, kept only in memory otherwise.
+pub fn store_from_env() -> Box<dyn TimerStore> {
+    match std::env::var(TIMERS_FILE_ENV_VARIABLE) {
+        Ok(path) => Box::new(FileTimerStore::new(path)),
+        Err(_) => Box::new(InMemoryTimerStore),
+    }
+}
@@ -0,0 +1,48 @@
+use async_graphql::{ErrorExtensions, FieldError};
+use std::fmt::Display;
+use thiserror::Error;
+
+/// Invalid input of a query or mutation.
+///
+/// Errors are extended with `code: "VALIDATION"` and the GraphQL name of the invalid `field`,
+/// e.g. `startDate`, so that clients can map them to form fields.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("{message}")]
+pub struct ValidationError {
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    pub fn new(field: &str, message: impl Display) -> Self {
+        ValidationError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl ErrorExtensions for ValidationError {
+    fn extend(&self) -> FieldError {
+        self.extend_with(|err, e| {
+            e.set("code", "VALIDATION");
+            e.set("field", err.field.as_str());
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use async_graphql::Value;
+
+    #[test]
+    fn test_validation_error_extensions() {
+        let error = ValidationError::new("startDate", "`start_date` is invalid").extend();
+        let extensions = error.extensions.unwrap();
+
+        assert_eq!(error.message, "`start_date` is invalid");
+        assert_eq!(extensions.get("code"), Some(&Value::from("VALIDATION")));
+        assert_eq!(extensions.get("field"), Some(&Value::from("startDate")));
+    }
+}
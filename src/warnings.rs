@@ -0,0 +1,65 @@
+use async_graphql::Value;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+/// Problem which did not fail the operation, but left its result incomplete.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Warning {
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_entry_id: Option<u64>,
+}
+
+/// Warnings collected while executing a request, returned in the `warnings` response extension.
+#[derive(Default, Debug, Clone)]
+pub struct Warnings(Arc<Mutex<Vec<Warning>>>);
+
+impl Warnings {
+    pub fn push(&self, warning: Warning) {
+        self.0.lock().unwrap().push(warning);
+    }
+
+    /// Returns the warnings as the value of the response extension, `None` if there are none.
+    pub fn to_extension(&self) -> Option<Value> {
+        let warnings = self.0.lock().unwrap();
+        if warnings.is_empty() {
+            return None;
+        }
+
+        serde_json::to_value(&*warnings)
+            .ok()
+            .and_then(|warnings| Value::from_json(warnings).ok())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_no_extension_without_warnings() {
+        assert_eq!(Warnings::default().to_extension(), None);
+    }
+
+    #[test]
+    fn test_warnings_extension() {
+        let warnings = Warnings::default();
+        warnings.push(Warning {
+            code: "TAGS_NOT_UPDATED",
+            message: "Teamdeck did not respond in time".to_string(),
+            time_entry_id: Some(7),
+        });
+
+        let expected = serde_json::json!([{
+            "code": "TAGS_NOT_UPDATED",
+            "message": "Teamdeck did not respond in time",
+            "timeEntryId": 7,
+        }]);
+        assert_eq!(
+            warnings.to_extension(),
+            Some(Value::from_json(expected).unwrap())
+        );
+    }
+}